@@ -0,0 +1,352 @@
+use super::compress::{compressor, parse_algorithm, write_output, Protection};
+use crate::files::{self, OutputFlags};
+use crate::ui::formatter;
+use crate::ui::output::{self, say};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use owlsol_core::{CompressionMetadata, Compressor};
+use rayon::prelude::*;
+use serde_json::json;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Per-algorithm totals for the summary table
+#[derive(Default)]
+struct Totals {
+    files: usize,
+    original: u64,
+    compressed: u64,
+}
+
+/// Compress every file named by `inputs` in parallel. Each file is written
+/// next to its source, or under `output` mirroring its name; a failed file is
+/// reported at the end instead of stopping the batch.
+pub async fn execute(
+    inputs: Vec<String>,
+    output: Option<String>,
+    algorithm: String,
+    level: Option<u32>,
+    jobs: Option<usize>,
+    protection: Protection,
+    flags: OutputFlags,
+) -> Result<()> {
+    say!("{}", "🦉 OWLSOL Batch Compression".bright_cyan().bold());
+    say!();
+
+    // Skip earlier outputs so re-running over a directory does not nest them
+    let (files, skipped): (Vec<_>, Vec<_>) = files::collect(&inputs)?
+        .into_iter()
+        .partition(|(path, _)| !is_output(path));
+    if files.is_empty() {
+        anyhow::bail!("No files to compress");
+    }
+
+    let output_paths = output_paths(&files, output.as_deref())?;
+    let algo = parse_algorithm(&algorithm);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs.unwrap_or(0))
+        .build()
+        .context("Failed to start worker threads")?;
+
+    say!("  {} {}", "Files:".bright_white(), files.len());
+    if !skipped.is_empty() {
+        say!(
+            "  {} {} already compressed",
+            "Skipped:".bright_white(),
+            skipped.len()
+        );
+    }
+    say!(
+        "  {} {}",
+        "Jobs:".bright_white(),
+        pool.current_num_threads()
+    );
+    say!();
+
+    let pb = output::progress(ProgressBar::new(files.len() as u64));
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("  {spinner:.cyan} [{bar:40.cyan/blue}] {pos}/{len} {wide_msg}")
+            .unwrap()
+            .progress_chars("█▓░"),
+    );
+
+    let compressor = compressor(level);
+    let outcomes: Vec<_> = pool.install(|| {
+        files
+            .par_iter()
+            .zip(&output_paths)
+            .map(|((path, name), output_path)| {
+                let outcome =
+                    compress_file(&compressor, path, output_path, algo, &protection, flags);
+                pb.set_message(name.clone());
+                pb.inc(1);
+                (path, output_path, outcome)
+            })
+            .collect()
+    });
+    pb.finish_and_clear();
+
+    let mut totals: BTreeMap<&'static str, Totals> = BTreeMap::new();
+    let mut failures = Vec::new();
+    for (path, _, outcome) in &outcomes {
+        match outcome {
+            Ok(metadata) => {
+                let entry = totals.entry(metadata.algorithm.as_str()).or_default();
+                entry.files += 1;
+                entry.original += metadata.original_size;
+                entry.compressed += metadata.compressed_size;
+            }
+            Err(err) => failures.push((path, err)),
+        }
+    }
+
+    print_totals(&totals);
+
+    if output::is_json() {
+        output::emit(&json!({
+            "files": outcomes
+                .iter()
+                .map(|(path, output_path, outcome)| match outcome {
+                    Ok(metadata) => json!({
+                        "file": path.display().to_string(),
+                        "output": output_path.display().to_string(),
+                        "metadata": formatter::compression_json(metadata),
+                    }),
+                    Err(err) => json!({
+                        "file": path.display().to_string(),
+                        "error": format!("{:#}", err),
+                    }),
+                })
+                .collect::<Vec<_>>(),
+            "totals": totals
+                .iter()
+                .map(|(algorithm, totals)| (algorithm.to_string(), json!({
+                    "files": totals.files,
+                    "original_size": totals.original,
+                    "compressed_size": totals.compressed,
+                })))
+                .collect::<serde_json::Map<_, _>>(),
+            "skipped": skipped.len(),
+            "failed": failures.len(),
+        }))?;
+    }
+
+    if failures.is_empty() {
+        say!();
+        say!("  ✓ Compressed {} files", outcomes.len());
+        return Ok(());
+    }
+
+    say!();
+    say!("{}", "  Failures:".bright_red().bold());
+    for (path, err) in &failures {
+        say!("    {} {}: {:#}", "✗".bright_red(), path.display(), err);
+    }
+    say!();
+    anyhow::bail!("{} of {} files failed", failures.len(), outcomes.len())
+}
+
+fn compress_file(
+    compressor: &Compressor,
+    path: &Path,
+    output_path: &Path,
+    algorithm: Option<owlsol_core::CompressionAlgorithm>,
+    protection: &Protection,
+    flags: OutputFlags,
+) -> Result<CompressionMetadata> {
+    let data = fs::read(path).with_context(|| format!("Failed to read: {}", path.display()))?;
+    let result = compressor
+        .compress_with_algorithm(&data, algorithm)
+        .context("Compression failed")?;
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create: {}", parent.display()))?;
+    }
+    write_output(
+        &result,
+        &output_path.to_string_lossy(),
+        protection,
+        flags.force,
+    )?;
+    if !flags.keep {
+        files::remove_input(&path.to_string_lossy())?;
+    }
+    Ok(result.metadata)
+}
+
+/// `<file>.owlsol` next to each source, or `<output>/<name>.owlsol` when an
+/// output directory is given
+fn output_paths(files: &[(PathBuf, String)], output: Option<&str>) -> Result<Vec<PathBuf>> {
+    let Some(root) = output else {
+        return Ok(files
+            .iter()
+            .map(|(path, _)| PathBuf::from(format!("{}.owlsol", path.display())))
+            .collect());
+    };
+
+    let mut seen = HashSet::new();
+    files
+        .iter()
+        .map(|(_, name)| {
+            if !seen.insert(name) {
+                anyhow::bail!("Two inputs would both be written to {}/{}", root, name);
+            }
+            files::entry_path(Path::new(root), &format!("{}.owlsol", name))
+        })
+        .collect()
+}
+
+fn is_output(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    name.ends_with(".owlsol") || name.ends_with(".owlsol.meta.json")
+}
+
+fn print_totals(totals: &BTreeMap<&'static str, Totals>) {
+    say!(
+        "  {:<12} {:>6} {:>14} {:>14} {:>14} {:>8}",
+        "Algorithm",
+        "Files",
+        "Original",
+        "Compressed",
+        "Saved",
+        "Ratio"
+    );
+    say!("  {}", "─".repeat(73));
+
+    let mut all = Totals::default();
+    for (algorithm, totals) in totals {
+        print_row(algorithm, totals);
+        all.files += totals.files;
+        all.original += totals.original;
+        all.compressed += totals.compressed;
+    }
+    say!("  {}", "─".repeat(73));
+    print_row("Total", &all);
+}
+
+fn print_row(label: &str, totals: &Totals) {
+    let ratio = if totals.original > 0 {
+        format!(
+            "{:.1}%",
+            (1.0 - totals.compressed as f64 / totals.original as f64) * 100.0
+        )
+    } else {
+        "-".to_string()
+    };
+    say!(
+        "  {:<12} {:>6} {:>14} {:>14} {:>14} {:>8}",
+        label,
+        totals.files,
+        totals.original,
+        totals.compressed,
+        totals.original.saturating_sub(totals.compressed),
+        ratio
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch(inputs: &[&Path], output: Option<&Path>, flags: OutputFlags) -> Result<()> {
+        let inputs = inputs.iter().map(|p| p.display().to_string()).collect();
+        let output = output.map(|p| p.display().to_string());
+        tokio::runtime::Runtime::new().unwrap().block_on(execute(
+            inputs,
+            output,
+            "rle".to_string(),
+            None,
+            Some(2),
+            Protection::None,
+            flags,
+        ))
+    }
+
+    #[test]
+    fn test_output_paths() {
+        let files = vec![
+            (PathBuf::from("a/x.bin"), "a/x.bin".to_string()),
+            (PathBuf::from("b/x.bin"), "b/x.bin".to_string()),
+        ];
+        assert_eq!(
+            output_paths(&files, None).unwrap(),
+            [
+                PathBuf::from("a/x.bin.owlsol"),
+                PathBuf::from("b/x.bin.owlsol")
+            ]
+        );
+        assert_eq!(
+            output_paths(&files, Some("out")).unwrap()[1],
+            Path::new("out/b/x.bin.owlsol")
+        );
+
+        // Two files named alike from different inputs would share a path
+        let clash = vec![
+            (PathBuf::from("a/x.bin"), "x.bin".to_string()),
+            (PathBuf::from("b/x.bin"), "x.bin".to_string()),
+        ];
+        assert!(output_paths(&clash, None).is_ok());
+        assert!(output_paths(&clash, Some("out")).is_err());
+    }
+
+    #[test]
+    fn test_failures_are_summarised() {
+        let dir = tempfile::tempdir().unwrap();
+        let (good, bad) = (dir.path().join("good.bin"), dir.path().join("bad.bin"));
+        fs::write(&good, vec![0u8; 512]).unwrap();
+        fs::write(&bad, vec![0u8; 512]).unwrap();
+        // Without -f the existing output makes this file fail
+        fs::write(dir.path().join("bad.bin.owlsol"), b"earlier").unwrap();
+
+        let flags = OutputFlags {
+            keep: true,
+            ..OutputFlags::default()
+        };
+        let err = batch(&[dir.path()], None, flags).unwrap_err();
+        // Not a CompressionError, so the process exits with the generic status
+        assert!(err
+            .downcast_ref::<owlsol_core::CompressionError>()
+            .is_none());
+        assert_eq!(err.to_string(), "1 of 2 files failed");
+
+        // The other file was still written, and nothing was overwritten
+        assert!(dir.path().join("good.bin.owlsol").exists());
+        assert_eq!(
+            fs::read(dir.path().join("bad.bin.owlsol")).unwrap(),
+            b"earlier"
+        );
+        assert!(good.exists() && bad.exists());
+
+        let flags = OutputFlags {
+            force: true,
+            ..OutputFlags::default()
+        };
+        batch(&[&good, &bad], None, flags).unwrap();
+        assert!(!good.exists() && !bad.exists());
+    }
+
+    #[test]
+    fn test_output_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("accounts");
+        fs::create_dir_all(input.join("nested")).unwrap();
+        fs::write(input.join("a.bin"), vec![7u8; 256]).unwrap();
+        fs::write(input.join("nested/b.bin"), vec![9u8; 256]).unwrap();
+        let out = dir.path().join("out");
+
+        let flags = OutputFlags {
+            keep: true,
+            ..OutputFlags::default()
+        };
+        batch(&[&input], Some(&out), flags).unwrap();
+        assert!(out.join("accounts/a.bin.owlsol").exists());
+        assert!(out.join("accounts/nested/b.bin.owlsol").exists());
+        assert!(input.join("a.bin").exists());
+
+        // A second run refuses to replace the first run's outputs
+        assert!(batch(&[&input], Some(&out), flags).is_err());
+    }
+}
@@ -1,37 +1,490 @@
-use anyhow::Result;
+use super::corpus;
+use crate::files;
+use crate::ui::output::{self, say, Format};
+use anyhow::{Context, Result};
+use clap::builder::PossibleValuesParser;
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
-use owlsol_core::{CompressionAlgorithm, Compressor};
+use owlsol_core::synthetic::{AccountKind, Generator};
+use owlsol_core::{CompressionAlgorithm, Compressor, Decompressor};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
 use std::fs;
 use std::time::{Duration, Instant};
 
-pub async fn execute(input: String, iterations: usize, output: Option<String>) -> Result<()> {
-    println!("{}", "🦉 OWLSOL Benchmark".bright_cyan().bold());
-    println!();
+/// Algorithms timed besides auto, when compiled in
+const ALGORITHMS: [CompressionAlgorithm; 11] = [
+    CompressionAlgorithm::Huffman,
+    CompressionAlgorithm::Dictionary,
+    CompressionAlgorithm::RunLength,
+    CompressionAlgorithm::Hybrid,
+    CompressionAlgorithm::Lz4,
+    CompressionAlgorithm::Zstd,
+    CompressionAlgorithm::Brotli,
+    CompressionAlgorithm::Deflate,
+    CompressionAlgorithm::Gzip,
+    CompressionAlgorithm::Snappy,
+    CompressionAlgorithm::Lzma,
+];
 
-    let data = fs::read(&input)?;
-    println!("  {} {}", "File:".bright_white(), input.bright_cyan());
-    println!("  {} {} bytes", "Size:".bright_white(), data.len());
-    println!("  {} {}", "Iterations:".bright_white(), iterations);
-    println!();
+/// Label of the row where the compressor picks the algorithm
+pub const AUTO: &str = "auto";
 
-    let compressor = Compressor::new();
+/// `--synthetic` value selecting every account kind
+pub const ALL_KINDS: &str = "all";
 
-    // Benchmark each algorithm
-    let algorithms = vec![
-        ("Huffman", CompressionAlgorithm::Huffman),
-        ("Dictionary", CompressionAlgorithm::Dictionary),
-        ("RLE", CompressionAlgorithm::RunLength),
-        ("Hybrid", CompressionAlgorithm::Hybrid),
-    ];
+/// What to benchmark
+pub enum Source {
+    /// A file, or a directory or glob benchmarked as a corpus
+    Path(String),
+    Synthetic(Synthetic),
+}
 
-    println!("{}", "Running benchmarks...".bright_yellow().bold());
-    println!();
+/// Generated accounts, benchmarked as a corpus
+pub struct Synthetic {
+    pub kinds: Vec<AccountKind>,
+    /// Accounts generated per kind
+    pub count: usize,
+    pub seed: u64,
+}
 
-    let mut results = Vec::new();
+impl Synthetic {
+    fn label(&self) -> String {
+        let kinds: Vec<&str> = self.kinds.iter().map(AccountKind::as_str).collect();
+        format!("synthetic {} (seed {})", kinds.join(","), self.seed)
+    }
+
+    /// Each kind starts from the seed, so its accounts are the same whichever
+    /// other kinds are asked for
+    fn generate(&self) -> Vec<(String, Vec<u8>)> {
+        let mut corpus = Vec::new();
+        for &kind in &self.kinds {
+            let mut generator = Generator::new(self.seed);
+            for index in 0..self.count {
+                let name = format!("{}-{:03}", kind.as_str(), index);
+                corpus.push((name, generator.account(kind)));
+            }
+        }
+        corpus
+    }
+}
 
-    for (name, algo) in algorithms {
-        let pb = ProgressBar::new(iterations as u64);
+/// Account kind names accepted by `--synthetic`, plus `all`
+pub fn synthetic_kinds() -> PossibleValuesParser {
+    AccountKind::ALL
+        .iter()
+        .map(AccountKind::as_str)
+        .chain([ALL_KINDS])
+        .into()
+}
+
+/// Bytes per MB in throughput figures
+const MB: f64 = 1_000_000.0;
+
+/// Distribution of one operation's run time, in microseconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Timing {
+    pub mean_us: f64,
+    pub median_us: f64,
+    pub p95_us: f64,
+    pub p99_us: f64,
+    pub stddev_us: f64,
+    pub min_us: f64,
+    pub max_us: f64,
+}
+
+/// One algorithm's results on one input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Measurement {
+    /// Requested algorithm, or `auto`
+    pub algorithm: String,
+    /// What the compressor produced: auto's pick, or None when the requested
+    /// algorithm did not shrink the input
+    pub selected: String,
+    pub original_size: usize,
+    pub compressed_size: usize,
+    /// Compressed size over original size
+    pub ratio: f64,
+    pub compress: Timing,
+    pub decompress: Timing,
+    /// Input consumed per second at the median compression time
+    pub compress_mb_s: f64,
+    /// Output produced per second at the median decompression time
+    pub decompress_mb_s: f64,
+}
+
+/// Where to record results, and what to compare them with
+pub struct BaselineOptions {
+    pub save: Option<String>,
+    pub compare: Option<String>,
+    /// Largest tolerated regression, in percent
+    pub threshold: f64,
+}
+
+/// The parts of a saved JSON report a comparison needs. `results` holds one
+/// row per algorithm: its measurement for a single file, or its aggregate
+/// over a corpus.
+#[derive(Deserialize)]
+pub struct Baseline {
+    file: String,
+    size: usize,
+    results: Vec<Summary>,
+}
+
+/// The figures a baseline comparison looks at
+#[derive(Debug, Clone, Deserialize)]
+pub struct Summary {
+    pub algorithm: String,
+    pub ratio: f64,
+    pub compress_mb_s: f64,
+    pub decompress_mb_s: f64,
+}
+
+/// Every file's measurements from one run
+pub struct Run {
+    pub input: String,
+    pub iterations: usize,
+    pub warmup: usize,
+    /// Measurements per file, sorted by name
+    pub files: Vec<(String, Vec<Measurement>)>,
+    /// Files left out, with the reason
+    pub skipped: Vec<(String, String)>,
+}
+
+/// How one figure moved against the baseline
+#[derive(Serialize)]
+struct Delta {
+    baseline: f64,
+    current: f64,
+    delta_percent: f64,
+    regressed: bool,
+}
+
+/// One algorithm's deltas; ratio regresses when it grows, throughput when
+/// it falls
+#[derive(Serialize)]
+struct Comparison {
+    algorithm: String,
+    ratio: Delta,
+    compress_mb_s: Delta,
+    decompress_mb_s: Delta,
+}
+
+impl Delta {
+    fn new(baseline: f64, current: f64, higher_is_better: bool, threshold: f64) -> Self {
+        let delta_percent = if baseline == 0.0 {
+            0.0
+        } else {
+            (current - baseline) / baseline * 100.0
+        };
+        let loss = if higher_is_better {
+            -delta_percent
+        } else {
+            delta_percent
+        };
+        Self {
+            baseline,
+            current,
+            delta_percent,
+            regressed: loss > threshold,
+        }
+    }
+}
+
+impl Measurement {
+    pub fn summary(&self) -> Summary {
+        Summary {
+            algorithm: self.algorithm.clone(),
+            ratio: self.ratio,
+            compress_mb_s: self.compress_mb_s,
+            decompress_mb_s: self.decompress_mb_s,
+        }
+    }
+}
+
+impl Comparison {
+    fn regressed(&self) -> bool {
+        self.ratio.regressed || self.compress_mb_s.regressed || self.decompress_mb_s.regressed
+    }
+}
+
+/// Parse `5%` or `5` as a percentage
+pub fn parse_threshold(value: &str) -> std::result::Result<f64, String> {
+    let number = value.trim().trim_end_matches('%');
+    match number.parse::<f64>() {
+        Ok(percent) if percent >= 0.0 && percent.is_finite() => Ok(percent),
+        _ => Err(format!("expected a percentage such as 5%, got {:?}", value)),
+    }
+}
+
+impl Timing {
+    fn from_samples(samples: &mut [Duration]) -> Self {
+        samples.sort_unstable();
+        let micros: Vec<f64> = samples
+            .iter()
+            .map(|sample| sample.as_secs_f64() * 1e6)
+            .collect();
+        let n = micros.len() as f64;
+        let mean = micros.iter().sum::<f64>() / n;
+        let variance = micros.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+        // Nearest rank, so every figure is a time that was actually measured
+        let percentile =
+            |p: f64| micros[((p / 100.0 * n).ceil() as usize).clamp(1, micros.len()) - 1];
+
+        Self {
+            mean_us: mean,
+            median_us: percentile(50.0),
+            p95_us: percentile(95.0),
+            p99_us: percentile(99.0),
+            stddev_us: variance.sqrt(),
+            min_us: micros[0],
+            max_us: micros[micros.len() - 1],
+        }
+    }
+}
+
+/// Time `iterations` compressions and decompressions of `data` with
+/// `algorithm` (`None` for auto), after `warmup` untimed rounds of each
+pub fn measure(
+    compressor: &Compressor,
+    decompressor: &Decompressor,
+    data: &[u8],
+    algorithm: Option<CompressionAlgorithm>,
+    iterations: usize,
+    warmup: usize,
+    pb: &ProgressBar,
+) -> Result<Measurement> {
+    let label = algorithm.map_or(AUTO, |algo| algo.as_str());
+    let compress = || {
+        compressor
+            .compress_with_algorithm(data, algorithm)
+            .with_context(|| format!("{} compression failed", label))
+    };
+
+    for _ in 0..warmup {
+        compress()?;
+    }
+    let mut compress_samples = Vec::with_capacity(iterations);
+    let mut result = None;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let compressed = compress()?;
+        compress_samples.push(start.elapsed());
+        result = Some(compressed);
+        pb.inc(1);
+    }
+    let result = result.context("At least one iteration is needed")?;
+
+    let decompress = || {
+        decompressor
+            .decompress(&result.data, &result.metadata)
+            .with_context(|| format!("{} decompression failed", label))
+    };
+    if decompress()? != data {
+        anyhow::bail!("{} did not round-trip", label);
+    }
+    for _ in 0..warmup {
+        decompress()?;
+    }
+    let mut decompress_samples = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        decompress()?;
+        decompress_samples.push(start.elapsed());
+        pb.inc(1);
+    }
+
+    let compress = Timing::from_samples(&mut compress_samples);
+    let decompress = Timing::from_samples(&mut decompress_samples);
+    let mb_s = |median_us: f64| data.len() as f64 / MB / (median_us / 1e6);
+    Ok(Measurement {
+        algorithm: label.to_string(),
+        selected: result.metadata.algorithm.as_str().to_string(),
+        original_size: data.len(),
+        compressed_size: result.data.len(),
+        ratio: result.data.len() as f64 / data.len() as f64,
+        compress_mb_s: mb_s(compress.median_us),
+        decompress_mb_s: mb_s(decompress.median_us),
+        compress,
+        decompress,
+    })
+}
+
+pub async fn execute(
+    source: Source,
+    iterations: usize,
+    warmup: usize,
+    output: Option<String>,
+    baseline: BaselineOptions,
+) -> Result<()> {
+    if iterations == 0 {
+        anyhow::bail!("--iterations must be at least 1");
+    }
+    // Read the baseline first, so a bad path fails before the long run
+    let previous = baseline
+        .compare
+        .as_deref()
+        .map(|path| load_baseline(path).map(|previous| (path, previous)))
+        .transpose()?;
+
+    say!("{}", "🦉 OWLSOL Benchmark".bright_cyan().bold());
+    say!();
+
+    let (run, corpus) = match &source {
+        Source::Path(input) => {
+            let inputs = std::slice::from_ref(input);
+            let paths = files::collect(inputs)?;
+            if files::is_batch(inputs) {
+                (corpus::run(input, &paths, iterations, warmup)?, true)
+            } else {
+                (run_file(input, iterations, warmup)?, false)
+            }
+        }
+        Source::Synthetic(synthetic) => {
+            let accounts = synthetic.generate();
+            let run = corpus::measure_corpus(
+                &synthetic.label(),
+                &accounts,
+                Vec::new(),
+                iterations,
+                warmup,
+            )?;
+            (run, true)
+        }
+    };
+    if run.files.is_empty() {
+        anyhow::bail!("No files to benchmark");
+    }
+
+    let summaries: Vec<Summary> = if corpus {
+        corpus::aggregate(&run.files)
+            .iter()
+            .map(corpus::Aggregate::summary)
+            .collect()
+    } else {
+        run.files[0].1.iter().map(Measurement::summary).collect()
+    };
+    let size = run
+        .files
+        .iter()
+        .map(|(_, results)| results[0].original_size)
+        .sum();
+    let comparisons = previous
+        .as_ref()
+        .map(|(_, previous)| compare(&previous.results, &summaries, baseline.threshold));
+
+    let json_report = || {
+        if corpus {
+            corpus::json_report(&run)
+        } else {
+            json_report(&run)
+        }
+    };
+    let report = match output::format() {
+        Format::Text => {
+            if corpus {
+                corpus::print_text(&run);
+            } else {
+                print_text(&run.files[0].1);
+            }
+            if let (Some((path, previous)), Some(comparisons)) = (&previous, &comparisons) {
+                print_comparison(
+                    path,
+                    previous,
+                    (&run.input, size),
+                    &summaries,
+                    comparisons,
+                    baseline.threshold,
+                );
+            }
+            if corpus {
+                corpus::text_report(&run)
+            } else {
+                text_report(&run)
+            }
+        }
+        Format::Json => {
+            let mut document = json_report();
+            if let (Some((path, _)), Some(comparisons)) = (&previous, &comparisons) {
+                document["comparison"] = json!({
+                    "baseline": path,
+                    "threshold_percent": baseline.threshold,
+                    "algorithms": comparisons,
+                    "regressions": comparisons.iter().filter(|c| c.regressed()).count(),
+                });
+            }
+            output::emit(&document)?;
+            serde_json::to_string_pretty(&document)?
+        }
+        Format::Csv => {
+            let table = csv_report(&run.files);
+            print!("{}", table);
+            table
+        }
+    };
+
+    if let Some(output_path) = output {
+        fs::write(&output_path, report)
+            .with_context(|| format!("Failed to write: {}", output_path))?;
+        say!();
+        say!("  ✓ Report saved to {}", output_path.bright_cyan());
+    }
+
+    if let Some(path) = &baseline.save {
+        fs::write(path, serde_json::to_string_pretty(&json_report())?)
+            .with_context(|| format!("Failed to write: {}", path))?;
+        say!("  ✓ Baseline saved to {}", path.bright_cyan());
+    }
+
+    let regressed: Vec<&str> = comparisons
+        .iter()
+        .flatten()
+        .filter(|comparison| comparison.regressed())
+        .map(|comparison| comparison.algorithm.as_str())
+        .collect();
+    if !regressed.is_empty() {
+        anyhow::bail!(
+            "{} regressed by more than {}% against the baseline",
+            regressed.join(", "),
+            baseline.threshold
+        );
+    }
+
+    Ok(())
+}
+
+/// Every algorithm that is compiled in, then auto
+pub fn algorithms() -> impl Iterator<Item = Option<CompressionAlgorithm>> {
+    ALGORITHMS
+        .into_iter()
+        .filter(CompressionAlgorithm::is_available)
+        .map(Some)
+        .chain([None])
+}
+
+fn run_file(input: &str, iterations: usize, warmup: usize) -> Result<Run> {
+    let data = fs::read(input).with_context(|| format!("Failed to read: {}", input))?;
+    say!("  {} {}", "File:".bright_white(), input.bright_cyan());
+    say!("  {} {} bytes", "Size:".bright_white(), data.len());
+    say!(
+        "  {} {} (+{} warmup)",
+        "Iterations:".bright_white(),
+        iterations,
+        warmup
+    );
+    say!();
+
+    let compressor = Compressor::new();
+    let decompressor = Decompressor::new();
+
+    say!("{}", "Running benchmarks...".bright_yellow().bold());
+    say!();
+
+    let mut results = Vec::new();
+    for algorithm in algorithms() {
+        let name = algorithm.map_or(AUTO, |algo| algo.as_str());
+        let pb = output::progress(ProgressBar::new(2 * iterations as u64));
         pb.set_style(
             ProgressStyle::default_bar()
                 .template(&format!(
@@ -41,119 +494,350 @@ pub async fn execute(input: String, iterations: usize, output: Option<String>) -
                 .unwrap()
                 .progress_chars("█▓░"),
         );
+        results.push(measure(
+            &compressor,
+            &decompressor,
+            &data,
+            algorithm,
+            iterations,
+            warmup,
+            &pb,
+        )?);
+        pb.finish();
+    }
 
-        let mut total_compress_time = Duration::ZERO;
-        let mut total_decompress_time = Duration::ZERO;
-        let mut compressed_size = 0;
-
-        for _ in 0..iterations {
-            // Compression benchmark
-            let start = Instant::now();
-            let result = compressor.compress_with_algorithm(&data, Some(algo))?;
-            total_compress_time += start.elapsed();
-            compressed_size = result.data.len();
+    Ok(Run {
+        input: input.to_string(),
+        iterations,
+        warmup,
+        files: vec![(input.to_string(), results)],
+        skipped: Vec::new(),
+    })
+}
 
-            // Decompression benchmark
-            let decompressor = owlsol_core::Decompressor::new();
-            let start = Instant::now();
-            let _ = decompressor.decompress(&result.data, &result.metadata)?;
-            total_decompress_time += start.elapsed();
+fn load_baseline(path: &str) -> Result<Baseline> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("Failed to read baseline: {}", path))?;
+    serde_json::from_str(&text).with_context(|| {
+        format!(
+            "{} is not a baseline saved by `owlsol benchmark --save-baseline`",
+            path
+        )
+    })
+}
 
-            pb.inc(1);
-        }
+/// Deltas for every algorithm measured both times
+fn compare(previous: &[Summary], current: &[Summary], threshold: f64) -> Vec<Comparison> {
+    let previous: BTreeMap<&str, &Summary> =
+        previous.iter().map(|m| (m.algorithm.as_str(), m)).collect();
+    current
+        .iter()
+        .filter_map(|now| {
+            let then = previous.get(now.algorithm.as_str())?;
+            Some(Comparison {
+                algorithm: now.algorithm.clone(),
+                ratio: Delta::new(then.ratio, now.ratio, false, threshold),
+                compress_mb_s: Delta::new(then.compress_mb_s, now.compress_mb_s, true, threshold),
+                decompress_mb_s: Delta::new(
+                    then.decompress_mb_s,
+                    now.decompress_mb_s,
+                    true,
+                    threshold,
+                ),
+            })
+        })
+        .collect()
+}
 
-        pb.finish_with_message("Done");
+fn print_comparison(
+    path: &str,
+    previous: &Baseline,
+    (input, size): (&str, usize),
+    current: &[Summary],
+    comparisons: &[Comparison],
+    threshold: f64,
+) {
+    say!();
+    say!(
+        "{}",
+        format!("Against {} (threshold {}%):", path, threshold)
+            .bright_green()
+            .bold()
+    );
+    if previous.file != input || previous.size != size {
+        say!(
+            "  {}",
+            format!(
+                "Baseline was recorded on {} ({} bytes); deltas compare different inputs",
+                previous.file, previous.size
+            )
+            .yellow()
+        );
+    }
+    say!();
+    say!(
+        "  {:<12} {:>10} {:>14} {:>16}",
+        "Algorithm",
+        "Ratio",
+        "Compress MB/s",
+        "Decompress MB/s"
+    );
+    say!("  {}", "─".repeat(55).bright_black());
 
-        let avg_compress = total_compress_time.as_micros() as f64 / iterations as f64;
-        let avg_decompress = total_decompress_time.as_micros() as f64 / iterations as f64;
-        let ratio = compressed_size as f64 / data.len() as f64;
+    // Pad before coloring; escape codes would count towards the width
+    let cell = |delta: &Delta, width: usize| {
+        let text = format!("{:>width$}", format!("{:+.1}%", delta.delta_percent));
+        if delta.regressed {
+            text.bright_red().bold().to_string()
+        } else {
+            text
+        }
+    };
+    for comparison in comparisons {
+        say!(
+            "  {:<12} {} {} {}",
+            comparison.algorithm.bright_yellow(),
+            cell(&comparison.ratio, 10),
+            cell(&comparison.compress_mb_s, 14),
+            cell(&comparison.decompress_mb_s, 16)
+        );
+    }
 
-        results.push((name, avg_compress, avg_decompress, compressed_size, ratio));
+    let compared: Vec<&str> = comparisons.iter().map(|c| c.algorithm.as_str()).collect();
+    let missing: Vec<&str> = previous
+        .results
+        .iter()
+        .map(|m| m.algorithm.as_str())
+        .chain(current.iter().map(|m| m.algorithm.as_str()))
+        .filter(|name| !compared.contains(name))
+        .collect();
+    if !missing.is_empty() {
+        say!();
+        say!(
+            "  {} {}",
+            "Not in both runs:".bright_white(),
+            missing.join(", ")
+        );
     }
+}
 
-    // Display results
-    println!();
-    println!("{}", "Benchmark Results:".bright_green().bold());
-    println!();
-    println!(
-        "  {:<12} {:<15} {:<15} {:<15} {:<10}",
-        "Algorithm", "Compress (μs)", "Decompress (μs)", "Size (bytes)", "Ratio"
+fn print_text(results: &[Measurement]) {
+    say!();
+    say!("{}", "Benchmark Results:".bright_green().bold());
+    say!();
+    say!(
+        "  {:<12} {:>10} {:>8} │ {:>24} {:>10} {:>9} │ {:>24} {:>10} {:>9}",
+        "Algorithm",
+        "Size",
+        "Ratio",
+        "Compress median (μs)",
+        "p99",
+        "MB/s",
+        "Decompress median (μs)",
+        "p99",
+        "MB/s"
     );
-    println!("  {}", "─".repeat(70).bright_black());
+    say!("  {}", "─".repeat(136).bright_black());
 
-    for (name, comp_time, decomp_time, size, ratio) in &results {
-        println!(
-            "  {:<12} {:>14.2} {:>14.2} {:>14} {:>9.2}%",
+    for result in results {
+        let name = if result.algorithm == result.selected {
+            result.algorithm.clone()
+        } else {
+            format!("{}→{}", result.algorithm, result.selected)
+        };
+        say!(
+            "  {:<12} {:>10} {:>7.2}% │ {:>24} {:>10.2} {:>9.2} │ {:>24} {:>10.2} {:>9.2}",
             name.bright_yellow(),
-            comp_time,
-            decomp_time,
-            size,
-            ratio * 100.0
+            result.compressed_size,
+            result.ratio * 100.0,
+            spread(&result.compress),
+            result.compress.p99_us,
+            result.compress_mb_s,
+            spread(&result.decompress),
+            result.decompress.p99_us,
+            result.decompress_mb_s
         );
     }
 
-    println!();
+    let summaries: Vec<Summary> = results.iter().map(Measurement::summary).collect();
+    let (compress, decompress, ratio) = leaders(&summaries);
+    say!();
+    say!(
+        "  {} {} ({:.2} MB/s)",
+        "Fastest compression:".bright_green(),
+        compress.algorithm,
+        compress.compress_mb_s
+    );
+    say!(
+        "  {} {} ({:.2} MB/s)",
+        "Fastest decompression:".bright_green(),
+        decompress.algorithm,
+        decompress.decompress_mb_s
+    );
+    say!(
+        "  {} {} ({:.2}%)",
+        "Best ratio:".bright_green(),
+        ratio.algorithm,
+        ratio.ratio * 100.0
+    );
+}
 
-    // Calculate throughput
-    let best_compress = results
-        .iter()
-        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
-        .unwrap();
-    let best_ratio = results
+/// `median ± stddev`
+fn spread(timing: &Timing) -> String {
+    format!("{:.2} ± {:.2}", timing.median_us, timing.stddev_us)
+}
+
+/// Fastest compression, fastest decompression and smallest output
+pub fn leaders(results: &[Summary]) -> (&Summary, &Summary, &Summary) {
+    let best = |key: fn(&Summary) -> f64| {
+        results
+            .iter()
+            .min_by(|a, b| key(a).total_cmp(&key(b)))
+            .expect("at least one algorithm is benchmarked")
+    };
+    (
+        best(|m| -m.compress_mb_s),
+        best(|m| -m.decompress_mb_s),
+        best(|m| m.ratio),
+    )
+}
+
+fn text_report(run: &Run) -> String {
+    let results = &run.files[0].1;
+    let rows: Vec<String> = results
         .iter()
-        .min_by(|a, b| a.4.partial_cmp(&b.4).unwrap())
-        .unwrap();
+        .map(|m| {
+            format!(
+                "{} ({}): size={}, ratio={:.2}%, compress median={:.2}μs p95={:.2}μs p99={:.2}μs stddev={:.2}μs {:.2}MB/s, \
+                 decompress median={:.2}μs p95={:.2}μs p99={:.2}μs stddev={:.2}μs {:.2}MB/s",
+                m.algorithm,
+                m.selected,
+                m.compressed_size,
+                m.ratio * 100.0,
+                m.compress.median_us,
+                m.compress.p95_us,
+                m.compress.p99_us,
+                m.compress.stddev_us,
+                m.compress_mb_s,
+                m.decompress.median_us,
+                m.decompress.p95_us,
+                m.decompress.p99_us,
+                m.decompress.stddev_us,
+                m.decompress_mb_s
+            )
+        })
+        .collect();
+    format!(
+        "OWLSOL Benchmark Report\n\
+         =======================\n\
+         File: {}\n\
+         Size: {} bytes\n\
+         Iterations: {} (+{} warmup)\n\
+         Results:\n\
+         {}\n",
+        run.input,
+        results[0].original_size,
+        run.iterations,
+        run.warmup,
+        rows.join("\n")
+    )
+}
 
-    println!(
-        "  {} {}",
-        "Fastest compression:".bright_green(),
-        best_compress.0
+fn json_report(run: &Run) -> serde_json::Value {
+    let results = &run.files[0].1;
+    let summaries: Vec<Summary> = results.iter().map(Measurement::summary).collect();
+    let (compress, decompress, ratio) = leaders(&summaries);
+    json!({
+        "file": run.input,
+        "size": results[0].original_size,
+        "iterations": run.iterations,
+        "warmup": run.warmup,
+        "results": results,
+        "fastest_compression": compress.algorithm,
+        "fastest_decompression": decompress.algorithm,
+        "best_ratio": ratio.algorithm,
+    })
+}
+
+/// One row per file and algorithm, headed by the column names
+fn csv_report(files: &[(String, Vec<Measurement>)]) -> String {
+    let mut table = String::from(
+        "file,algorithm,selected,original_size,compressed_size,ratio,\
+         compress_mean_us,compress_median_us,compress_p95_us,compress_p99_us,compress_stddev_us,compress_mb_s,\
+         decompress_mean_us,decompress_median_us,decompress_p95_us,decompress_p99_us,decompress_stddev_us,decompress_mb_s\n",
     );
-    println!("  {} {}", "Best ratio:".bright_green(), best_ratio.0);
+    for (input, m) in files
+        .iter()
+        .flat_map(|(name, results)| results.iter().map(move |m| (name, m)))
+    {
+        table.push_str(&format!(
+            "{},{},{},{},{},{:.6},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3}\n",
+            csv_field(input),
+            m.algorithm,
+            m.selected,
+            m.original_size,
+            m.compressed_size,
+            m.ratio,
+            m.compress.mean_us,
+            m.compress.median_us,
+            m.compress.p95_us,
+            m.compress.p99_us,
+            m.compress.stddev_us,
+            m.compress_mb_s,
+            m.decompress.mean_us,
+            m.decompress.median_us,
+            m.decompress.p95_us,
+            m.decompress.p99_us,
+            m.decompress.stddev_us,
+            m.decompress_mb_s
+        ));
+    }
+    table
+}
 
-    // Calculate throughput
-    let throughput_mb_s = (data.len() as f64 / 1024.0 / 1024.0) / (best_compress.1 / 1_000_000.0);
+/// Quote a field holding a comma, quote or newline
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
 
-    println!();
-    println!(
-        "  {} {:.2} MB/s",
-        "Peak throughput:".bright_cyan(),
-        throughput_mb_s
-    );
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // Save report
-    if let Some(output_path) = output {
-        let report = format!(
-            "OWLSOL Benchmark Report\n\
-             =======================\n\
-             File: {}\n\
-             Size: {} bytes\n\
-             Iterations: {}\n\
-             Results:\n\
-             {}\n",
-            input,
-            data.len(),
-            iterations,
-            results
-                .iter()
-                .map(|(name, comp, decomp, size, ratio)| {
-                    format!(
-                        "{}: compress={:.2}μs, decompress={:.2}μs, size={}, ratio={:.2}%",
-                        name,
-                        comp,
-                        decomp,
-                        size,
-                        ratio * 100.0
-                    )
-                })
-                .collect::<Vec<_>>()
-                .join("\n")
-        );
+    #[test]
+    fn test_threshold() {
+        assert_eq!(parse_threshold("5%"), Ok(5.0));
+        assert_eq!(parse_threshold("2.5"), Ok(2.5));
+        assert!(parse_threshold("-1%").is_err());
+        assert!(parse_threshold("fast").is_err());
 
-        fs::write(&output_path, report)?;
-        println!();
-        println!("  ✓ Report saved to {}", output_path.bright_cyan());
+        // A smaller ratio and faster runs are never regressions
+        assert!(!Delta::new(0.5, 0.4, false, 5.0).regressed);
+        assert!(!Delta::new(100.0, 150.0, true, 5.0).regressed);
+        assert!(!Delta::new(100.0, 96.0, true, 5.0).regressed);
+        assert!(Delta::new(100.0, 94.0, true, 5.0).regressed);
+        assert!(Delta::new(0.50, 0.53, false, 5.0).regressed);
     }
 
-    Ok(())
+    #[test]
+    fn test_timing() {
+        let mut samples: Vec<Duration> = (1..=100).rev().map(Duration::from_micros).collect();
+        let timing = Timing::from_samples(&mut samples);
+        assert_eq!(timing.median_us, 50.0);
+        assert_eq!(timing.p95_us, 95.0);
+        assert_eq!(timing.p99_us, 99.0);
+        assert_eq!((timing.min_us, timing.max_us), (1.0, 100.0));
+        assert!((timing.mean_us - 50.5).abs() < 1e-9);
+        assert!((timing.stddev_us - 28.866).abs() < 1e-3);
+
+        let timing = Timing::from_samples(&mut [Duration::from_micros(7)]);
+        assert_eq!(
+            (timing.median_us, timing.p99_us, timing.stddev_us),
+            (7.0, 7.0, 0.0)
+        );
+    }
 }
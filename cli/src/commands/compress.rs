@@ -1,32 +1,128 @@
+use crate::files::{self, OutputFlags, STDIO};
+use crate::keys;
+use crate::profile::Profile;
 use crate::ui::formatter;
+use crate::ui::output::{self, say};
 use anyhow::{Context, Result};
 use colored::Colorize;
-use indicatif::{ProgressBar, ProgressStyle};
-use owlsol_core::{CompressionAlgorithm, Compressor};
-use owlsol_solana::SolanaClient;
-use std::fs;
+use owlsol_core::container::FIXED_HEADER_LEN;
+use owlsol_core::encryption::{self, Key};
+use owlsol_core::rent::Rent;
+use owlsol_core::{
+    CompressionAlgorithm, CompressionError, CompressionResult, Compressor, Container,
+};
+use owlsol_solana::{seal, Pubkey};
+use serde_json::{json, Value};
+use std::str::FromStr;
+
+/// How compressed output is protected on disk
+pub enum Protection {
+    None,
+    Key(Key),
+    Recipient(Pubkey),
+}
+
+impl Protection {
+    /// Resolve the command-line options, prompting for a passphrase if needed
+    pub fn from_args(
+        encrypt: bool,
+        key_file: Option<&str>,
+        recipient: Option<&str>,
+    ) -> Result<Self> {
+        if let Some(recipient) = recipient {
+            let pubkey = Pubkey::from_str(recipient).map_err(|err| {
+                CompressionError::invalid_input(format!(
+                    "Invalid recipient public key {}: {}",
+                    recipient, err
+                ))
+            })?;
+            return Ok(Self::Recipient(pubkey));
+        }
+        Ok(match (encrypt, key_file) {
+            (false, _) => Self::None,
+            (true, Some(path)) => Self::Key(keys::read_key_file(path)?),
+            (true, None) => Self::Key(keys::read_passphrase(true)?),
+        })
+    }
+}
+
+/// Write `result` to `output_path` (`-` for stdout). Protected output and
+/// anything streamed to stdout is a self-describing container; plain files
+/// get the payload plus a `.meta.json` sidecar. Existing files are only
+/// replaced with `force`. Returns the container if one was written.
+pub fn write_output(
+    result: &CompressionResult,
+    output_path: &str,
+    protection: &Protection,
+    force: bool,
+) -> Result<Option<Container>> {
+    let container = match protection {
+        Protection::None => None,
+        Protection::Key(key) => {
+            Some(encryption::encrypt(result, key).context("Encryption failed")?)
+        }
+        Protection::Recipient(recipient) => {
+            Some(seal::seal(result, recipient).context("Sealing failed")?)
+        }
+    };
+
+    // There is nowhere to put a sidecar next to stdout
+    let container = match container {
+        None if output_path == STDIO => Some(Container::new(result.clone())),
+        container => container,
+    };
+
+    if let Some(container) = &container {
+        // The container header holds the metadata and, when encrypted, the
+        // salt, nonce and KDF parameters
+        files::write(output_path, &container.to_bytes(), force)?;
+    } else {
+        let metadata_path = format!("{}.meta.json", output_path);
+        files::check_overwrite(&metadata_path, force)?;
+        files::write(output_path, &result.data, force)?;
+
+        let metadata_json = serde_json::to_string_pretty(&result.metadata)?;
+        files::write(&metadata_path, metadata_json.as_bytes(), force)?;
+    }
+    Ok(container)
+}
 
-// ...existing code...
 pub async fn execute(
     input: String,
     output: Option<String>,
     algorithm: String,
-    deploy: bool,
-) -> Result<owlsol_core::CompressionResult> {
-    println!("{}", "🦉 OWLSOL Compression".bright_cyan().bold());
-    println!();
+    level: Option<u32>,
+    deploy: Option<(Profile, Rent)>,
+    protection: Protection,
+    flags: OutputFlags,
+) -> Result<CompressionResult> {
+    if input.ends_with(".owlsol") && !flags.force {
+        return Err(CompressionError::invalid_input(format!(
+            "{} already has the .owlsol suffix; pass -f to compress it again",
+            input
+        ))
+        .into());
+    }
+
+    // stdin has no name to derive an output from, so it streams to stdout
+    let output_path = match output {
+        _ if flags.stdout => STDIO.to_string(),
+        Some(output) => output,
+        None if input == STDIO => STDIO.to_string(),
+        None => format!("{}.owlsol", input),
+    };
+    if output_path == STDIO {
+        output::set_data_on_stdout();
+    }
+    files::check_overwrite(&output_path, flags.force)?;
+
+    say!("{}", "🦉 OWLSOL Compression".bright_cyan().bold());
+    say!();
 
     // Read input file
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner:.cyan} {msg}")
-            .unwrap(),
-    );
-    spinner.set_message("Reading input file...");
-    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+    let spinner = output::spinner("Reading input file...");
 
-    let data = fs::read(&input).with_context(|| format!("Failed to read file: {}", input))?;
+    let data = files::read(&input)?;
 
     spinner.finish_with_message(format!("✓ Read {} bytes", data.len()));
 
@@ -34,18 +130,10 @@ pub async fn execute(
     let algo = parse_algorithm(&algorithm);
 
     // Compress
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner:.cyan} {msg}")
-            .unwrap(),
-    );
-    spinner.set_message("Compressing...");
-    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+    let spinner = output::spinner("Compressing...");
 
     let start = std::time::Instant::now();
-    let compressor = Compressor::new();
-    let result = compressor
+    let result = compressor(level)
         .compress_with_algorithm(&data, algo)
         .context("Compression failed")?;
     let elapsed = start.elapsed();
@@ -53,61 +141,114 @@ pub async fn execute(
     spinner.finish_with_message(format!("✓ Compressed in {:.2}ms", elapsed.as_millis()));
 
     // Display results
-    println!();
+    say!();
     formatter::print_compression_result(&result, elapsed);
 
     // Save to file
-    let output_path = output.unwrap_or_else(|| format!("{}.owlsol", input));
-
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_message("Saving compressed data...");
-    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
-
-    // Save compressed data
-    fs::write(&output_path, &result.data)
-        .with_context(|| format!("Failed to write to: {}", output_path))?;
-
-    // Save metadata
-    let metadata_path = format!("{}.meta.json", output_path);
-    let metadata_json = serde_json::to_string_pretty(&result.metadata)?;
-    fs::write(&metadata_path, metadata_json)?;
+    let shown = if output_path == STDIO {
+        "stdout"
+    } else {
+        output_path.as_str()
+    };
+    let spinner = output::spinner("Saving compressed data...");
+
+    let container = write_output(&result, &output_path, &protection, flags.force)?;
+    if let Some(container) = &container {
+        spinner.finish_with_message(format!("✓ Saved to {}", shown.bright_cyan()));
+        if let Some(encryption) = &container.encryption {
+            say!(
+                "  Encrypted: {} ({})",
+                encryption.cipher.as_str(),
+                encryption.kdf.as_str()
+            );
+        }
+        if let Some(recipient) = seal::recipient(container) {
+            say!("  Recipient: {}", recipient.to_string().bright_cyan());
+        }
+    } else {
+        let metadata_path = format!("{}.meta.json", output_path);
+        spinner.finish_with_message(format!("✓ Saved to {}", shown.bright_cyan()));
+        say!("  Metadata: {}", metadata_path.bright_black());
+    }
 
-    spinner.finish_with_message(format!("✓ Saved to {}", output_path.bright_cyan()));
-    println!("  ✓ Saved to {}", output_path.bright_cyan());
-    println!("  Metadata: {}", metadata_path.bright_black());
+    // Like gzip, replace the input unless asked to keep it or streaming
+    if !flags.keep && output_path != STDIO {
+        files::remove_input(&input)?;
+    }
 
     // Deploy to Solana
-    if deploy {
-        println!();
-        deploy_to_solana(&result).await?;
+    let deployment = match &deploy {
+        Some((profile, rent)) => {
+            say!();
+            Some(deploy_to_solana(&result, profile, rent).await?)
+        }
+        None => None,
+    };
+
+    if output::is_json() {
+        let encryption = container
+            .as_ref()
+            .and_then(|container| container.encryption);
+        output::emit(&json!({
+            "input": input,
+            "output": output_path,
+            "sidecar": container
+                .is_none()
+                .then(|| format!("{}.meta.json", output_path)),
+            "metadata": formatter::compression_json(&result.metadata),
+            "decode_cu": result.decode_cu,
+            "elapsed_ms": elapsed.as_secs_f64() * 1000.0,
+            "encryption": encryption.map(|encryption| encryption.kdf.as_str()),
+            "recipient": container
+                .as_ref()
+                .and_then(seal::recipient)
+                .map(|recipient| recipient.to_string()),
+            "deployment": deployment,
+        }))?;
     }
 
     Ok(result)
 }
 
-fn parse_algorithm(algo: &str) -> Option<CompressionAlgorithm> {
+/// Compressor at `level`, or each backend's default level
+pub fn compressor(level: Option<u32>) -> Compressor {
+    match level {
+        Some(level) => Compressor::new().with_level(level),
+        None => Compressor::new(),
+    }
+}
+
+pub fn parse_algorithm(algo: &str) -> Option<CompressionAlgorithm> {
     match algo.to_lowercase().as_str() {
         "huffman" => Some(CompressionAlgorithm::Huffman),
         "dictionary" | "dict" => Some(CompressionAlgorithm::Dictionary),
         "rle" | "runlength" => Some(CompressionAlgorithm::RunLength),
         "hybrid" => Some(CompressionAlgorithm::Hybrid),
+        "lz4" => Some(CompressionAlgorithm::Lz4),
+        "zstd" => Some(CompressionAlgorithm::Zstd),
+        "brotli" => Some(CompressionAlgorithm::Brotli),
+        "deflate" => Some(CompressionAlgorithm::Deflate),
+        "gzip" | "gz" => Some(CompressionAlgorithm::Gzip),
+        "snappy" => Some(CompressionAlgorithm::Snappy),
+        "lzma" | "xz" => Some(CompressionAlgorithm::Lzma),
         "none" => Some(CompressionAlgorithm::None),
         "auto" => None,
         _ => None,
     }
 }
 
-async fn deploy_to_solana(result: &owlsol_core::CompressionResult) -> Result<()> {
-    println!(
-        "{}",
-        "📡 Deploying to Solana Devnet...".bright_yellow().bold()
-    );
+/// Simulate deploying to the profile's cluster, returning the wallet and
+/// rent figures priced with `rent`
+async fn deploy_to_solana(
+    result: &CompressionResult,
+    profile: &Profile,
+    rent: &Rent,
+) -> Result<Value> {
+    say!("{}", "📡 Deploying to Solana...".bright_yellow().bold());
 
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_message("Connecting to devnet...");
-    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+    let spinner = output::spinner(format!("Connecting to {}...", profile.rpc_url()));
 
-    let client = SolanaClient::devnet().context("Failed to connect to Solana devnet")?;
+    let client = profile.connect()?;
 
     spinner.finish_with_message("✓ Connected");
 
@@ -115,58 +256,76 @@ async fn deploy_to_solana(result: &owlsol_core::CompressionResult) -> Result<()>
     let balance = client.get_balance()?;
     let balance_sol = balance as f64 / 1_000_000_000.0;
 
-    println!();
-    println!("  {} {}", "Wallet:".bright_white(), client.pubkey());
-    println!("  {} {:.4} SOL", "Balance:".bright_white(), balance_sol);
+    say!();
+    say!("  {} {}", "Wallet:".bright_white(), client.pubkey());
+    say!("  {} {:.4} SOL", "Balance:".bright_white(), balance_sol);
 
     // Request airdrop if needed
     if balance < 100_000_000 {
-        let spinner = ProgressBar::new_spinner();
-        spinner.set_message("Requesting airdrop...");
-        spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+        let spinner = output::spinner("Requesting airdrop...");
 
         client.airdrop(1_000_000_000)?;
 
         spinner.finish_with_message("✓ Received 1 SOL airdrop");
     }
 
-    // Calculate rent
-    let rent = client.calculate_rent(result.data.len())?;
-    let rent_sol = rent as f64 / 1_000_000_000.0;
+    // Rent is computed offline, for the payload behind its container header
+    let rent = rent.compare(
+        result.metadata.original_size as usize,
+        result.data.len(),
+        FIXED_HEADER_LEN,
+    );
 
-    println!();
-    println!(
-        "  {} {} bytes",
+    say!();
+    say!(
+        "  {} {} bytes (+{} header)",
         "Compressed size:".bright_white(),
-        result.data.len()
+        result.data.len(),
+        FIXED_HEADER_LEN
     );
-    println!("  {} {:.6} SOL", "Storage rent:".bright_white(), rent_sol);
-
-    // Calculate savings
-    let original_rent = client.calculate_rent(result.metadata.original_size as usize)?;
-    let original_rent_sol = original_rent as f64 / 1_000_000_000.0;
-    let savings_sol = original_rent_sol - rent_sol;
-
-    println!(
+    say!(
+        "  {} {:.6} SOL",
+        "Storage rent:".bright_white(),
+        formatter::sol(rent.stored_lamports)
+    );
+    say!(
         "  {} {:.6} SOL",
         "Original would cost:".bright_black(),
-        original_rent_sol
+        formatter::sol(rent.original_lamports)
     );
-    println!("  {} {:.6} SOL saved!", "💰".bright_green(), savings_sol);
+    if rent.is_loss() {
+        say!(
+            "  {} {:.6} SOL more than storing it raw",
+            "⚠".bright_yellow(),
+            formatter::sol(rent.stored_lamports - rent.original_lamports)
+        );
+    } else {
+        say!(
+            "  {} {:.6} SOL saved!",
+            "💰".bright_green(),
+            formatter::sol(rent.original_lamports - rent.stored_lamports)
+        );
+    }
 
-    println!();
-    println!(
+    say!();
+    say!(
         "  {}",
         "Note: Actual on-chain deployment requires the Solana program to be deployed."
             .bright_black()
             .italic()
     );
-    println!(
+    say!(
         "  {}",
         "This is a simulation showing potential savings."
             .bright_black()
             .italic()
     );
 
-    Ok(())
+    Ok(json!({
+        "wallet": client.pubkey().to_string(),
+        "balance_lamports": balance,
+        "rent_lamports": rent.stored_lamports,
+        "original_rent_lamports": rent.original_lamports,
+        "saved_lamports": rent.saved_lamports(),
+    }))
 }
@@ -0,0 +1,89 @@
+use crate::profile::{Config, FILE_NAME};
+use crate::ui::output;
+use anyhow::Result;
+use colored::Colorize;
+use serde_json::json;
+
+/// Show which files were read and what the selected profile resolves to,
+/// marking settings left at their built-in default
+pub async fn show(config: &Config) -> Result<()> {
+    let settings = &config.settings;
+    let level = settings
+        .level
+        .map_or_else(|| "codec default".to_string(), |level| level.to_string());
+    let keypair = settings.keypair().display().to_string();
+    let format = settings.format.unwrap_or_default();
+    let rows = [
+        (
+            "algorithm",
+            settings.algorithm().to_string(),
+            settings.algorithm.is_some(),
+        ),
+        ("level", level, settings.level.is_some()),
+        (
+            "rpc_url",
+            settings.rpc_url().to_string(),
+            settings.rpc_url.is_some(),
+        ),
+        ("keypair", keypair, settings.keypair.is_some()),
+        (
+            "commitment",
+            settings.commitment().as_str().to_string(),
+            settings.commitment.is_some(),
+        ),
+        (
+            "format",
+            format!("{:?}", format).to_lowercase(),
+            settings.format.is_some(),
+        ),
+    ];
+
+    if output::is_json() {
+        let mut effective: serde_json::Map<_, _> = rows
+            .iter()
+            .map(|(name, value, _)| (name.to_string(), json!(value)))
+            .collect();
+        // null rather than prose when each codec picks its own level
+        effective.insert("level".to_string(), json!(settings.level));
+        return output::emit(&json!({
+            "profile": config.profile,
+            "files": config.sources,
+            "profiles": config.profiles,
+            "configured": settings,
+            "effective": effective,
+        }));
+    }
+
+    println!("{}", "🦉 OWLSOL Config".bright_cyan().bold());
+    println!();
+    println!(
+        "  {} {}",
+        "Profile:".bright_white(),
+        config.profile.bright_cyan()
+    );
+    if config.sources.is_empty() {
+        println!("  {} no {} found", "Files:".bright_white(), FILE_NAME);
+    } else {
+        for (index, source) in config.sources.iter().enumerate() {
+            let label = if index == 0 { "Files:" } else { "" };
+            println!("  {:<9} {}", label.bright_white(), source.display());
+        }
+    }
+    if !config.profiles.is_empty() {
+        println!(
+            "  {} {}",
+            "Profiles:".bright_white(),
+            config.profiles.join(", ")
+        );
+    }
+    println!();
+
+    for (name, value, configured) in rows {
+        if configured {
+            println!("  {:<12} {}", name, value);
+        } else {
+            println!("  {:<12} {} {}", name, value, "(built-in)".dimmed());
+        }
+    }
+    Ok(())
+}
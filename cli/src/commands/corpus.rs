@@ -0,0 +1,473 @@
+//! Benchmarking a directory or glob of files as one corpus: every algorithm
+//! runs over every file, and the results are totalled per algorithm
+
+use super::benchmark::{self, leaders, measure, Measurement, Run, Summary, AUTO};
+use crate::ui::output::{self, say};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use owlsol_core::metadata::MAX_DATA_SIZE;
+use owlsol_core::{Compressor, Decompressor};
+use serde::Serialize;
+use serde_json::json;
+use std::fs;
+use std::path::PathBuf;
+
+/// Histogram buckets, each ten percentage points of compressed/original
+const BUCKETS: usize = 10;
+
+/// One algorithm's results over the whole corpus
+#[derive(Debug, Clone, Serialize)]
+pub struct Aggregate {
+    pub algorithm: String,
+    pub files: usize,
+    pub original_size: u64,
+    pub compressed_size: u64,
+    /// Total compressed size over total original size
+    pub ratio: f64,
+    /// Total input over the summed median compression times
+    pub compress_mb_s: f64,
+    /// Total output over the summed median decompression times
+    pub decompress_mb_s: f64,
+    /// Files this algorithm shrank where no other produced smaller output
+    /// (ties count for each); none for auto, which always matches the best
+    /// it tried
+    pub wins: Option<usize>,
+    /// Files per ratio bucket: bucket `i` counts ratios from `i`×10% up to
+    /// (`i`+1)×10%, and the last one also counts 100%
+    pub histogram: [usize; BUCKETS],
+}
+
+impl Aggregate {
+    pub fn summary(&self) -> Summary {
+        Summary {
+            algorithm: self.algorithm.clone(),
+            ratio: self.ratio,
+            compress_mb_s: self.compress_mb_s,
+            decompress_mb_s: self.decompress_mb_s,
+        }
+    }
+}
+
+/// Measure every algorithm on each of `paths`. Empty files, and files too
+/// large to compress, are skipped.
+pub fn run(
+    input: &str,
+    paths: &[(PathBuf, String)],
+    iterations: usize,
+    warmup: usize,
+) -> Result<Run> {
+    let mut corpus = Vec::new();
+    let mut skipped = Vec::new();
+    for (path, name) in paths {
+        let data = fs::read(path).with_context(|| format!("Failed to read: {}", path.display()))?;
+        if data.is_empty() {
+            skipped.push((name.clone(), "empty".to_string()));
+        } else if data.len() > MAX_DATA_SIZE {
+            skipped.push((name.clone(), format!("over {} bytes", MAX_DATA_SIZE)));
+        } else {
+            corpus.push((name.clone(), data));
+        }
+    }
+    measure_corpus(input, &corpus, skipped, iterations, warmup)
+}
+
+/// Measure every algorithm on each named buffer of `corpus`
+pub fn measure_corpus(
+    input: &str,
+    corpus: &[(String, Vec<u8>)],
+    skipped: Vec<(String, String)>,
+    iterations: usize,
+    warmup: usize,
+) -> Result<Run> {
+    let total: usize = corpus.iter().map(|(_, data)| data.len()).sum();
+
+    say!("  {} {}", "Corpus:".bright_white(), input.bright_cyan());
+    say!(
+        "  {} {} ({} bytes)",
+        "Files:".bright_white(),
+        corpus.len(),
+        total
+    );
+    if !skipped.is_empty() {
+        say!("  {} {}", "Skipped:".bright_white(), skipped.len());
+    }
+    say!(
+        "  {} {} (+{} warmup) per file and algorithm",
+        "Iterations:".bright_white(),
+        iterations,
+        warmup
+    );
+    say!();
+
+    let compressor = Compressor::new();
+    let decompressor = Decompressor::new();
+    let steps = corpus.len() * benchmark::algorithms().count() * 2 * iterations;
+    let pb = output::progress(ProgressBar::new(steps as u64));
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("  {spinner:.cyan} [{bar:40.cyan/blue}] {percent}% {wide_msg}")
+            .unwrap()
+            .progress_chars("█▓░"),
+    );
+
+    let mut files = Vec::new();
+    for (name, data) in corpus {
+        let mut results = Vec::new();
+        for algorithm in benchmark::algorithms() {
+            pb.set_message(format!(
+                "{} · {}",
+                name,
+                algorithm.map_or(AUTO, |algo| algo.as_str())
+            ));
+            let measurement = measure(
+                &compressor,
+                &decompressor,
+                data,
+                algorithm,
+                iterations,
+                warmup,
+                &pb,
+            )
+            .with_context(|| format!("Benchmark failed on {}", name))?;
+            results.push(measurement);
+        }
+        files.push((name.clone(), results));
+    }
+    pb.finish_and_clear();
+
+    Ok(Run {
+        input: input.to_string(),
+        iterations,
+        warmup,
+        files,
+        skipped,
+    })
+}
+
+/// Per-algorithm totals, wins and ratio histograms, in benchmark order
+pub fn aggregate(files: &[(String, Vec<Measurement>)]) -> Vec<Aggregate> {
+    let Some((_, first)) = files.first() else {
+        return Vec::new();
+    };
+    let mut aggregates: Vec<Aggregate> = first
+        .iter()
+        .map(|m| Aggregate {
+            algorithm: m.algorithm.clone(),
+            files: 0,
+            original_size: 0,
+            compressed_size: 0,
+            ratio: 0.0,
+            compress_mb_s: 0.0,
+            decompress_mb_s: 0.0,
+            wins: (m.algorithm != AUTO).then_some(0),
+            histogram: [0; BUCKETS],
+        })
+        .collect();
+    let mut compress_us = vec![0.0; aggregates.len()];
+    let mut decompress_us = vec![0.0; aggregates.len()];
+
+    for (_, results) in files {
+        let smallest = results
+            .iter()
+            .filter(|m| m.algorithm != AUTO)
+            .map(|m| m.compressed_size)
+            .min()
+            // Nobody wins a file that no algorithm shrinks
+            .filter(|&size| results.iter().all(|m| size < m.original_size));
+        for (index, m) in results.iter().enumerate() {
+            let total = &mut aggregates[index];
+            total.files += 1;
+            total.original_size += m.original_size as u64;
+            total.compressed_size += m.compressed_size as u64;
+            compress_us[index] += m.compress.median_us;
+            decompress_us[index] += m.decompress.median_us;
+            if let Some(wins) = &mut total.wins {
+                *wins += usize::from(Some(m.compressed_size) == smallest);
+            }
+            total.histogram[bucket(m.ratio)] += 1;
+        }
+    }
+
+    for (index, total) in aggregates.iter_mut().enumerate() {
+        let bytes = total.original_size as f64;
+        total.ratio = total.compressed_size as f64 / bytes;
+        total.compress_mb_s = bytes / 1e6 / (compress_us[index] / 1e6);
+        total.decompress_mb_s = bytes / 1e6 / (decompress_us[index] / 1e6);
+    }
+    aggregates
+}
+
+fn bucket(ratio: f64) -> usize {
+    ((ratio * BUCKETS as f64) as usize).min(BUCKETS - 1)
+}
+
+/// The result with the smallest output on one file, leaving out auto
+fn best(results: &[Measurement]) -> &Measurement {
+    results
+        .iter()
+        .filter(|m| m.algorithm != AUTO)
+        .min_by_key(|m| m.compressed_size)
+        .unwrap_or(&results[0])
+}
+
+pub fn print_text(run: &Run) {
+    say!("{}", "Per File:".bright_green().bold());
+    say!();
+    say!(
+        "  {:<40} {:>10} {:<12} {:>8}   {:<12} {:>8}",
+        "File",
+        "Size",
+        "Best",
+        "Ratio",
+        "Auto",
+        "Ratio"
+    );
+    say!("  {}", "─".repeat(98).bright_black());
+    for (name, results) in &run.files {
+        let best = best(results);
+        let auto = results.iter().find(|m| m.algorithm == AUTO);
+        say!(
+            "  {:<40} {:>10} {:<12} {:>7.2}%   {:<12} {:>7}",
+            name,
+            best.original_size,
+            best.algorithm.bright_yellow(),
+            best.ratio * 100.0,
+            auto.map_or("-", |m| m.selected.as_str()),
+            auto.map_or("-".to_string(), |m| format!("{:.2}%", m.ratio * 100.0))
+        );
+    }
+    for (name, reason) in &run.skipped {
+        say!("  {:<40} {}", name, format!("skipped: {}", reason).dimmed());
+    }
+
+    let aggregates = aggregate(&run.files);
+    say!();
+    say!("{}", "Aggregate:".bright_green().bold());
+    say!();
+    say!(
+        "  {:<12} {:>12} {:>12} {:>8} {:>14} {:>16} {:>6}",
+        "Algorithm",
+        "Original",
+        "Compressed",
+        "Ratio",
+        "Compress MB/s",
+        "Decompress MB/s",
+        "Wins"
+    );
+    say!("  {}", "─".repeat(88).bright_black());
+    for total in &aggregates {
+        say!(
+            "  {:<12} {:>12} {:>12} {:>7.2}% {:>14.2} {:>16.2} {:>6}",
+            total.algorithm.bright_yellow(),
+            total.original_size,
+            total.compressed_size,
+            total.ratio * 100.0,
+            total.compress_mb_s,
+            total.decompress_mb_s,
+            total.wins.map_or("-".to_string(), |wins| wins.to_string())
+        );
+    }
+
+    say!();
+    say!(
+        "{}",
+        "Ratio Distribution (files per compressed/original range):"
+            .bright_green()
+            .bold()
+    );
+    say!();
+    let header: String = (0..BUCKETS)
+        .map(|i| format!("{:>7}", format!("<{}%", (i + 1) * 10)))
+        .collect();
+    say!("  {:<12}{}", "Algorithm", header);
+    say!("  {}", "─".repeat(12 + 7 * BUCKETS).bright_black());
+    for total in &aggregates {
+        let counts: String = total
+            .histogram
+            .iter()
+            .map(|&count| match count {
+                0 => format!("{:>7}", "·"),
+                _ => format!("{:>7}", count),
+            })
+            .collect();
+        say!("  {:<12}{}", total.algorithm.bright_yellow(), counts);
+    }
+
+    let summaries: Vec<Summary> = aggregates.iter().map(Aggregate::summary).collect();
+    let (compress, decompress, ratio) = leaders(&summaries);
+    say!();
+    say!(
+        "  {} {} ({:.2} MB/s)",
+        "Fastest compression:".bright_green(),
+        compress.algorithm,
+        compress.compress_mb_s
+    );
+    say!(
+        "  {} {} ({:.2} MB/s)",
+        "Fastest decompression:".bright_green(),
+        decompress.algorithm,
+        decompress.decompress_mb_s
+    );
+    say!(
+        "  {} {} ({:.2}% overall)",
+        "Best ratio:".bright_green(),
+        ratio.algorithm,
+        ratio.ratio * 100.0
+    );
+}
+
+pub fn text_report(run: &Run) -> String {
+    let aggregates = aggregate(&run.files);
+    let totals: Vec<String> = aggregates
+        .iter()
+        .map(|total| {
+            format!(
+                "{}: original={}, compressed={}, ratio={:.2}%, compress={:.2}MB/s, decompress={:.2}MB/s, wins={}, histogram={:?}",
+                total.algorithm,
+                total.original_size,
+                total.compressed_size,
+                total.ratio * 100.0,
+                total.compress_mb_s,
+                total.decompress_mb_s,
+                total.wins.map_or("-".to_string(), |wins| wins.to_string()),
+                total.histogram
+            )
+        })
+        .collect();
+    let files: Vec<String> = run
+        .files
+        .iter()
+        .map(|(name, results)| {
+            let best = best(results);
+            format!(
+                "{}: size={}, best={} ({:.2}%)",
+                name,
+                best.original_size,
+                best.algorithm,
+                best.ratio * 100.0
+            )
+        })
+        .collect();
+    format!(
+        "OWLSOL Corpus Benchmark Report\n\
+         ==============================\n\
+         Corpus: {}\n\
+         Files: {} ({} skipped)\n\
+         Iterations: {} (+{} warmup)\n\
+         Aggregate:\n\
+         {}\n\
+         Files:\n\
+         {}\n",
+        run.input,
+        run.files.len(),
+        run.skipped.len(),
+        run.iterations,
+        run.warmup,
+        totals.join("\n"),
+        files.join("\n")
+    )
+}
+
+/// The aggregates go under `results`, so a saved corpus report works as a
+/// baseline for the next corpus run
+pub fn json_report(run: &Run) -> serde_json::Value {
+    let aggregates = aggregate(&run.files);
+    let summaries: Vec<Summary> = aggregates.iter().map(Aggregate::summary).collect();
+    let (compress, decompress, ratio) = leaders(&summaries);
+    json!({
+        "file": run.input,
+        "size": aggregates.first().map_or(0, |total| total.original_size),
+        "files": run.files.len(),
+        "iterations": run.iterations,
+        "warmup": run.warmup,
+        "results": aggregates,
+        "per_file": run
+            .files
+            .iter()
+            .map(|(name, results)| json!({
+                "file": name,
+                "size": results[0].original_size,
+                "best": best(results).algorithm,
+                "results": results,
+            }))
+            .collect::<Vec<_>>(),
+        "skipped": run
+            .skipped
+            .iter()
+            .map(|(name, reason)| json!({ "file": name, "reason": reason }))
+            .collect::<Vec<_>>(),
+        "fastest_compression": compress.algorithm,
+        "fastest_decompression": decompress.algorithm,
+        "best_ratio": ratio.algorithm,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::benchmark::Timing;
+
+    fn measurement(algorithm: &str, original: usize, compressed: usize, us: f64) -> Measurement {
+        let timing = Timing {
+            mean_us: us,
+            median_us: us,
+            p95_us: us,
+            p99_us: us,
+            stddev_us: 0.0,
+            min_us: us,
+            max_us: us,
+        };
+        Measurement {
+            algorithm: algorithm.to_string(),
+            selected: algorithm.to_string(),
+            original_size: original,
+            compressed_size: compressed,
+            ratio: compressed as f64 / original as f64,
+            compress: timing.clone(),
+            decompress: timing,
+            compress_mb_s: 0.0,
+            decompress_mb_s: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let files = vec![
+            (
+                "a".to_string(),
+                vec![
+                    measurement("LZ4", 1000, 500, 10.0),
+                    measurement("Zstd", 1000, 300, 20.0),
+                    measurement(AUTO, 1000, 300, 40.0),
+                ],
+            ),
+            (
+                "b".to_string(),
+                vec![
+                    measurement("LZ4", 3000, 3000, 10.0),
+                    measurement("Zstd", 3000, 3000, 20.0),
+                    measurement(AUTO, 3000, 3000, 40.0),
+                ],
+            ),
+        ];
+        let totals = aggregate(&files);
+
+        let lz4 = &totals[0];
+        assert_eq!(
+            (lz4.files, lz4.original_size, lz4.compressed_size),
+            (2, 4000, 3500)
+        );
+        assert_eq!(lz4.ratio, 0.875);
+        // 4000 bytes in 20μs
+        assert!((lz4.compress_mb_s - 200.0).abs() < 1e-9);
+        // Every algorithm ties on the incompressible "b"; none of them wins it
+        assert_eq!(lz4.wins, Some(0));
+        assert_eq!(lz4.histogram[5], 1);
+        assert_eq!(lz4.histogram[9], 1);
+
+        assert_eq!(totals[1].wins, Some(1));
+        assert_eq!(totals[1].histogram[3], 1);
+        assert_eq!(totals[2].wins, None);
+    }
+}
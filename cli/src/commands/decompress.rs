@@ -1,52 +1,100 @@
+use crate::files::{self, OutputFlags, STDIO};
+use crate::keys;
 use crate::ui::formatter;
+use crate::ui::output::{self, say};
 use anyhow::{Context, Result};
 use colored::Colorize;
-use indicatif::{ProgressBar, ProgressStyle};
-use owlsol_core::{CompressionMetadata, Decompressor};
+use owlsol_core::container::Kdf;
+use owlsol_core::{
+    encryption, signing, CompressionError, CompressionMetadata, CompressionResult, Container,
+    Decompressor,
+};
+use owlsol_solana::{seal, Pubkey, Signer};
+use serde_json::json;
 use std::fs;
+use std::path::PathBuf;
 
-pub async fn execute(input: String, output: Option<String>, from_solana: bool) -> Result<()> {
-    println!("{}", "🦉 OWLSOL Decompression".bright_cyan().bold());
-    println!();
+pub async fn execute(
+    input: String,
+    output: Option<String>,
+    from_solana: bool,
+    key_file: Option<String>,
+    keypair: Option<PathBuf>,
+    flags: OutputFlags,
+) -> Result<()> {
+    let output_path = match output {
+        _ if flags.stdout => STDIO.to_string(),
+        Some(output) => output,
+        None if input == STDIO || from_solana => STDIO.to_string(),
+        None => match input.strip_suffix(".owlsol") {
+            Some(stripped) if !stripped.is_empty() => stripped.to_string(),
+            _ => {
+                return Err(CompressionError::invalid_input(format!(
+                    "Cannot derive an output name from {} (no .owlsol suffix); pass -o or -c",
+                    input
+                ))
+                .into())
+            }
+        },
+    };
+    if output_path == STDIO {
+        output::set_data_on_stdout();
+    }
+    files::check_overwrite(&output_path, flags.force)?;
+
+    say!("{}", "🦉 OWLSOL Decompression".bright_cyan().bold());
+    say!();
 
     if from_solana {
-        println!(
+        say!(
             "  {}",
             "Note: Fetching from Solana requires deployed program."
                 .bright_yellow()
                 .italic()
         );
-        println!(
+        say!(
             "  {}",
             "Coming soon in future versions!".bright_yellow().italic()
         );
+        if output::is_json() {
+            output::emit(&json!({
+                "input": input,
+                "message": "Fetching from Solana requires the deployed program",
+            }))?;
+        }
         return Ok(());
     }
 
     // Read compressed file
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner:.cyan} {msg}")
-            .unwrap(),
-    );
-    spinner.set_message("Reading compressed file...");
-    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
-
-    let data = fs::read(&input).with_context(|| format!("Failed to read: {}", input))?;
-
-    // Read metadata
-    let metadata_path = format!("{}.meta.json", input);
-    let metadata_json = fs::read_to_string(&metadata_path)
-        .with_context(|| format!("Failed to read metadata: {}", metadata_path))?;
-    let metadata: CompressionMetadata = serde_json::from_str(&metadata_json)?;
+    let spinner = output::spinner("Reading compressed file...");
+
+    let container = load_container(&input)?;
+
+    spinner.finish_with_message(format!(
+        "✓ Read {} bytes (compressed)",
+        container.payload.len()
+    ));
 
-    spinner.finish_with_message(format!("✓ Read {} bytes (compressed)", data.len()));
+    let signer = if container.is_signed() {
+        let signer = signing::verify(&container, None)
+            .with_context(|| format!("Invalid signature: {}", input))?;
+        let signer = Pubkey::new_from_array(signer);
+        say!("✓ Signed by {}", signer);
+        Some(signer)
+    } else {
+        None
+    };
+    let encryption = container.encryption;
+
+    let metadata = container.metadata.clone();
+    let data = if container.is_encrypted() {
+        decrypt(&container, key_file, keypair)?
+    } else {
+        container.payload
+    };
 
     // Decompress
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_message("Decompressing...");
-    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+    let spinner = output::spinner("Decompressing...");
 
     let start = std::time::Instant::now();
     let decompressor = Decompressor::new();
@@ -66,18 +114,101 @@ pub async fn execute(input: String, output: Option<String>, from_solana: bool) -
         );
     }
 
-    println!();
+    say!();
     formatter::print_decompression_result(&metadata, decompressed.len(), elapsed);
 
     // Save output
-    if let Some(output_path) = output {
-        fs::write(&output_path, &decompressed)
-            .with_context(|| format!("Failed to write: {}", output_path))?;
-        println!();
-        println!("  ✓ Saved to {}", output_path.bright_cyan());
-        let metadata_path = format!("{}.meta.json", input);
-        println!("  Metadata: {}", metadata_path.bright_black());
+    files::write(&output_path, &decompressed, flags.force)?;
+    if output_path != STDIO {
+        say!();
+        say!("  ✓ Saved to {}", output_path.bright_cyan());
+
+        // Like gzip, remove the compressed input and its sidecar
+        if !flags.keep {
+            files::remove_input(&input)?;
+            let metadata_path = format!("{}.meta.json", input);
+            if std::path::Path::new(&metadata_path).exists() {
+                files::remove_input(&metadata_path)?;
+            }
+        }
+    }
+
+    if output::is_json() {
+        output::emit(&json!({
+            "input": input,
+            "output": output_path,
+            "metadata": formatter::compression_json(&metadata),
+            "decompressed_size": decompressed.len(),
+            "elapsed_ms": elapsed.as_secs_f64() * 1000.0,
+            "encryption": encryption.map(|encryption| encryption.kdf.as_str()),
+            "signer": signer.map(|signer| signer.to_string()),
+        }))?;
     }
 
     Ok(())
 }
+
+/// Load a container, or wrap a plain payload and its JSON sidecar in one.
+/// `-` reads a container from stdin.
+pub fn load_container(input: &str) -> Result<Container> {
+    parse_container(input, files::read(input)?)
+}
+
+/// [`load_container`] for bytes already read from `input`
+pub fn parse_container(input: &str, data: Vec<u8>) -> Result<Container> {
+    if Container::is_container(&data) {
+        return Container::from_bytes(&data)
+            .with_context(|| format!("Invalid container: {}", input));
+    }
+    if input == STDIO {
+        return Err(CompressionError::InvalidMetadata(
+            "stdin does not hold an OWLSOL container".into(),
+        )
+        .into());
+    }
+
+    let metadata_path = format!("{}.meta.json", input);
+    let metadata_json = fs::read_to_string(&metadata_path)
+        .with_context(|| format!("Failed to read metadata: {}", metadata_path))?;
+    let metadata: CompressionMetadata = serde_json::from_str(&metadata_json)
+        .map_err(CompressionError::from)
+        .with_context(|| format!("Invalid metadata: {}", metadata_path))?;
+    Ok(Container::new(CompressionResult {
+        data,
+        metadata,
+        decode_cu: None,
+    }))
+}
+
+/// Decrypt a container with the key file, or a passphrase if it was
+/// encrypted with one. Containers sealed to a Solana key are opened with the
+/// keypair, defaulting to the Solana CLI's.
+fn decrypt(
+    container: &Container,
+    key_file: Option<String>,
+    keypair: Option<PathBuf>,
+) -> Result<Vec<u8>> {
+    let kdf = container.encryption.map(|encryption| encryption.kdf);
+    if let Some(Kdf::X25519 { .. }) = kdf {
+        let path = keypair.unwrap_or_else(owlsol_solana::default_keypair_path);
+        let keypair = owlsol_solana::read_keypair(&path)
+            .with_context(|| format!("Failed to load keypair: {}", path.display()))?;
+
+        let spinner = output::spinner("Decrypting...");
+        let compressed = seal::open(container, &keypair).context("Decryption failed")?;
+        spinner.finish_with_message(format!("✓ Decrypted with {}", keypair.pubkey()));
+        return Ok(compressed);
+    }
+
+    let key = match (key_file, kdf) {
+        (Some(path), _) => keys::read_key_file(&path)?,
+        (None, Some(Kdf::Argon2id { .. })) => keys::read_passphrase(false)?,
+        (None, _) => return Err(CompressionError::KeyRequired).context("Pass the key with --key"),
+    };
+
+    let spinner = output::spinner("Decrypting...");
+    let compressed = encryption::decrypt(container, &key).context("Decryption failed")?;
+    spinner.finish_with_message("✓ Decrypted");
+
+    Ok(compressed)
+}
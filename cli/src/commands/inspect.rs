@@ -0,0 +1,229 @@
+use super::decompress::load_container;
+use crate::ui::output;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use owlsol_core::inspect::{self, Details, Section};
+use owlsol_core::Container;
+use owlsol_solana::{seal, Pubkey};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// Table rows shown without --verbose
+const SUMMARY_ROWS: usize = 10;
+
+pub async fn execute(input: String, verbose: bool) -> Result<()> {
+    let container = load_container(&input)?;
+    // An encrypted payload is ciphertext; only the headers can be shown
+    let sections = if container.is_encrypted() {
+        None
+    } else {
+        Some(
+            inspect::inspect(&container.payload, &container.metadata)
+                .context("Payload cannot be broken down")?,
+        )
+    };
+
+    if output::is_json() {
+        return print_json(&input, &container, sections.as_deref());
+    }
+
+    println!("{}", "🦉 OWLSOL Inspect".bright_cyan().bold());
+    println!();
+    print_headers(&input, &container);
+
+    let Some(sections) = sections else {
+        println!();
+        println!("  Payload is encrypted; decompress it to look inside");
+        return Ok(());
+    };
+    for (index, section) in sections.iter().enumerate() {
+        println!();
+        if sections.len() > 1 {
+            println!(
+                "{}",
+                format!(
+                    "Segment {}: {} ({} → {} bytes)",
+                    index + 1,
+                    section.algorithm.as_str(),
+                    section.original_len,
+                    section.compressed_len
+                )
+                .bright_yellow()
+                .bold()
+            );
+        } else {
+            println!("{}", section.algorithm.as_str().bright_yellow().bold());
+        }
+        print_details(section, verbose);
+    }
+
+    Ok(())
+}
+
+fn print_headers(input: &str, container: &Container) {
+    let metadata = &container.metadata;
+    let field = |name: &str, value: String| println!("  {} {}", name.bright_white(), value);
+
+    field("File:", input.bright_cyan().to_string());
+    field("Algorithm:", metadata.algorithm.as_str().to_string());
+    field("Format version:", metadata.version.to_string());
+    field(
+        "Original size:",
+        format!("{} bytes", metadata.original_size),
+    );
+    field(
+        "Compressed size:",
+        format!("{} bytes", metadata.compressed_size),
+    );
+    field("Checksum:", format!("{:08x}", metadata.checksum));
+    field("Timestamp:", metadata.timestamp.to_string());
+    if let Some(encryption) = &container.encryption {
+        let mut value = format!(
+            "{} ({})",
+            encryption.cipher.as_str(),
+            encryption.kdf.as_str()
+        );
+        if let Some(recipient) = seal::recipient(container) {
+            value.push_str(&format!(", sealed to {}", recipient));
+        }
+        field("Encryption:", value);
+    }
+    if let Some(signature) = &container.signature {
+        field(
+            "Signer:",
+            Pubkey::new_from_array(signature.signer).to_string(),
+        );
+    }
+}
+
+fn print_details(section: &Section, verbose: bool) {
+    match &section.details {
+        Details::Huffman(info) => {
+            println!(
+                "  Tree {} bytes, bitstream {} bytes",
+                info.tree_len, info.encoded_len
+            );
+            println!(
+                "  {} symbols, average code length {:.2} bits ({:.2} bits per byte)",
+                info.code_lengths.len(),
+                info.avg_code_length,
+                info.bits_per_symbol
+            );
+            if verbose {
+                println!("  {:<8} {:>6}", "Symbol", "Bits");
+                for code in &info.code_lengths {
+                    println!("  {:<8} {:>6}", symbol(code.symbol), code.bits);
+                }
+            } else {
+                let mut histogram = BTreeMap::new();
+                for code in &info.code_lengths {
+                    *histogram.entry(code.bits).or_insert(0) += 1;
+                }
+                println!("  {:<8} {:>8}", "Bits", "Symbols");
+                for (bits, count) in histogram {
+                    println!("  {:<8} {:>8}", bits, count);
+                }
+            }
+        }
+        Details::Dictionary(stats) => {
+            println!(
+                "  {} entries, {} references, {} literals, {} escaped markers",
+                stats.entries.len(),
+                stats.references,
+                stats.literals,
+                stats.escaped_markers
+            );
+            let mut entries: Vec<_> = stats.entries.iter().collect();
+            entries.sort_by(|a, b| b.uses.cmp(&a.uses).then(a.id.cmp(&b.id)));
+            let shown = if verbose { entries.len() } else { SUMMARY_ROWS };
+            println!("  {:>5} {:>6}  Bytes", "Id", "Uses");
+            for entry in entries.iter().take(shown) {
+                println!(
+                    "  {:>5} {:>6}  {}",
+                    entry.id,
+                    entry.uses,
+                    escape(&entry.bytes)
+                );
+            }
+            if entries.len() > shown {
+                println!("  … {} more (--verbose shows all)", entries.len() - shown);
+            }
+        }
+        Details::RunLength(stats) => {
+            println!(
+                "  {} runs covering {} bytes (longest {})",
+                stats.runs, stats.run_bytes, stats.longest_run
+            );
+            println!(
+                "  {} literals, {} escaped markers",
+                stats.literals, stats.escaped_markers
+            );
+        }
+        Details::Lz4(info) => {
+            println!("  Raw block, {} sequences", info.sequences);
+            println!(
+                "  {} literal bytes, {} matched bytes (longest {}, farthest offset {})",
+                info.literal_bytes, info.match_bytes, info.longest_match, info.max_offset
+            );
+        }
+        Details::Zstd(info) => {
+            for (index, frame) in info.frames.iter().enumerate() {
+                let size = |value: Option<u64>| {
+                    value.map_or_else(|| "unknown".to_string(), |v| format!("{} bytes", v))
+                };
+                println!("  Frame {}", index + 1);
+                println!("    Window size: {}", size(frame.window_size));
+                println!("    Content size: {}", size(frame.content_size));
+                if let Some(id) = frame.dictionary_id {
+                    println!("    Dictionary id: {}", id);
+                }
+                println!(
+                    "    Blocks: {} compressed, {} raw, {} RLE",
+                    frame.compressed_blocks, frame.raw_blocks, frame.rle_blocks
+                );
+                println!(
+                    "    Checksum: {}",
+                    if frame.checksum { "yes" } else { "no" }
+                );
+            }
+            if info.skippable_frames > 0 {
+                println!("  {} skippable frames", info.skippable_frames);
+            }
+        }
+        Details::Opaque => println!("  No codec internals to show"),
+    }
+}
+
+fn print_json(input: &str, container: &Container, sections: Option<&[Section]>) -> Result<()> {
+    let document = json!({
+        "file": input,
+        "metadata": container.metadata,
+        "encryption": container.encryption.map(|encryption| json!({
+            "cipher": encryption.cipher.as_str(),
+            "kdf": encryption.kdf.as_str(),
+            "recipient": seal::recipient(container).map(|pubkey| pubkey.to_string()),
+        })),
+        "signer": container
+            .signature
+            .map(|signature| Pubkey::new_from_array(signature.signer).to_string()),
+        "sections": sections,
+    });
+    output::emit(&document)
+}
+
+fn symbol(byte: u8) -> String {
+    if byte.is_ascii_graphic() {
+        format!("{:#04x} {}", byte, byte as char)
+    } else {
+        format!("{:#04x}", byte)
+    }
+}
+
+/// Printable bytes as-is, everything else as `\xNN`
+fn escape(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .flat_map(|&byte| std::ascii::escape_default(byte))
+        .map(char::from)
+        .collect()
+}
@@ -0,0 +1,77 @@
+use crate::ui::formatter;
+use crate::ui::output::{self, say};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use owlsol_core::ArchiveReader;
+use serde_json::json;
+use std::fs::File;
+use std::io::BufReader;
+
+pub async fn execute(input: String) -> Result<()> {
+    say!("{}", "🦉 OWLSOL Archive".bright_cyan().bold());
+    say!();
+
+    let file = File::open(&input).with_context(|| format!("Failed to read: {}", input))?;
+    let reader = ArchiveReader::new(BufReader::new(file))
+        .with_context(|| format!("Invalid archive: {}", input))?;
+
+    say!(
+        "  {:<40} {:<12} {:>12} {:>12} {:>8} {:>10}",
+        "Name",
+        "Algorithm",
+        "Original",
+        "Compressed",
+        "Ratio",
+        "Checksum"
+    );
+    say!("  {}", "─".repeat(99));
+
+    let mut original = 0;
+    let mut compressed = 0;
+    for entry in reader.entries() {
+        let meta = &entry.metadata;
+        original += meta.original_size;
+        compressed += meta.compressed_size;
+        let ratio = if meta.original_size > 0 {
+            format!("{:.1}%", meta.compression_percentage())
+        } else {
+            "-".to_string()
+        };
+        say!(
+            "  {:<40} {:<12} {:>12} {:>12} {:>8} {:>10}",
+            entry.name,
+            meta.algorithm.as_str(),
+            meta.original_size,
+            meta.compressed_size,
+            ratio,
+            format!("{:08x}", meta.checksum)
+        );
+    }
+
+    say!();
+    say!(
+        "  {} entries, {} bytes → {} bytes",
+        reader.entries().len(),
+        original,
+        compressed
+    );
+
+    if output::is_json() {
+        output::emit(&json!({
+            "input": input,
+            "entries": reader
+                .entries()
+                .iter()
+                .map(|entry| json!({
+                    "name": entry.name,
+                    "offset": entry.offset,
+                    "metadata": formatter::compression_json(&entry.metadata),
+                }))
+                .collect::<Vec<_>>(),
+            "original_size": original,
+            "compressed_size": compressed,
+        }))?;
+    }
+
+    Ok(())
+}
@@ -1,4 +1,13 @@
+pub mod batch;
 pub mod benchmark;
 pub mod compress;
+pub mod config;
+pub mod corpus;
 pub mod decompress;
+pub mod inspect;
+pub mod list;
+pub mod pack;
+pub mod sign;
 pub mod stats;
+pub mod unpack;
+pub mod verify;
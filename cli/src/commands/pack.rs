@@ -0,0 +1,86 @@
+use super::compress::{compressor, parse_algorithm};
+use crate::files;
+use crate::ui::formatter;
+use crate::ui::output::{self, say};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use owlsol_core::ArchiveWriter;
+use serde_json::json;
+use std::fs::{self, File};
+use std::io::BufWriter;
+
+pub async fn execute(
+    inputs: Vec<String>,
+    output: String,
+    algorithm: String,
+    level: Option<u32>,
+) -> Result<()> {
+    say!("{}", "🦉 OWLSOL Pack".bright_cyan().bold());
+    say!();
+
+    let files = files::collect(&inputs)?;
+    if files.is_empty() {
+        anyhow::bail!("No files to pack");
+    }
+    let algo = parse_algorithm(&algorithm);
+
+    let file = File::create(&output).with_context(|| format!("Failed to create: {}", output))?;
+    let mut writer = ArchiveWriter::new(BufWriter::new(file))?.with_compressor(compressor(level));
+
+    let pb = output::progress(ProgressBar::new(files.len() as u64));
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("  {spinner:.cyan} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+            .unwrap()
+            .progress_chars("█▓░"),
+    );
+
+    let mut original = 0u64;
+    let mut compressed = 0u64;
+    let mut packed = Vec::new();
+    for (path, name) in &files {
+        pb.set_message(name.clone());
+        let data = fs::read(path).with_context(|| format!("Failed to read: {}", path.display()))?;
+        let entry = writer
+            .compress(name, &data, algo)
+            .with_context(|| format!("Failed to pack: {}", name))?;
+        original += entry.metadata.original_size;
+        compressed += entry.metadata.compressed_size;
+        packed.push(json!({
+            "name": name,
+            "metadata": formatter::compression_json(&entry.metadata),
+        }));
+        pb.inc(1);
+    }
+    writer.finish()?;
+    pb.finish_and_clear();
+
+    say!("  {} {}", "Entries:".bright_white(), files.len());
+    say!("  {} {} bytes", "Original size:".bright_white(), original);
+    say!("  {} {} bytes", "Packed size:".bright_white(), compressed);
+    if original > 0 {
+        say!(
+            "  {} {}",
+            "Compression ratio:".bright_white(),
+            format!(
+                "{:.2}%",
+                (1.0 - compressed as f64 / original as f64) * 100.0
+            )
+            .bright_green()
+            .bold()
+        );
+    }
+    say!("  ✓ Saved to {}", output.bright_cyan());
+
+    if output::is_json() {
+        output::emit(&json!({
+            "output": output,
+            "entries": packed,
+            "original_size": original,
+            "packed_size": compressed,
+        }))?;
+    }
+
+    Ok(())
+}
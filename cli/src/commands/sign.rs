@@ -0,0 +1,69 @@
+use super::decompress::parse_container;
+use crate::files;
+use crate::ui::output::{self, say};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use owlsol_core::{signing, Container};
+use owlsol_solana::Pubkey;
+use serde_json::json;
+use std::fs;
+use std::path::PathBuf;
+
+pub async fn execute(
+    input: String,
+    output: Option<String>,
+    keypair: Option<PathBuf>,
+    force: bool,
+) -> Result<()> {
+    // Plain payloads become a container, so the signature travels with them
+    let output_path = output.unwrap_or_else(|| input.clone());
+    // Signing replaces the input in place, so only another file needs -f
+    let in_place = output_path == input && input != files::STDIO;
+    files::check_overwrite(&output_path, force || in_place)?;
+    if output_path == files::STDIO {
+        output::set_data_on_stdout();
+    }
+
+    say!("{}", "🦉 OWLSOL Sign".bright_cyan().bold());
+    say!();
+
+    let data = files::read(&input)?;
+    let sidecar = !Container::is_container(&data);
+    let mut container = parse_container(&input, data)?;
+
+    let keypair_path = keypair.unwrap_or_else(owlsol_solana::default_keypair_path);
+    let keypair = owlsol_solana::read_keypair(&keypair_path)
+        .with_context(|| format!("Failed to load keypair: {}", keypair_path.display()))?;
+
+    let signer = signing::sign(&mut container, &keypair.to_bytes()).context("Signing failed")?;
+
+    files::write(&output_path, &container.to_bytes(), true)?;
+
+    // The container now carries the metadata; a sidecar left beside it would
+    // describe a payload that is gone
+    let stale_sidecar = (sidecar && in_place).then(|| format!("{}.meta.json", input));
+    if let Some(path) = &stale_sidecar {
+        fs::remove_file(path).with_context(|| format!("Failed to remove: {}", path))?;
+    }
+
+    say!(
+        "  {} {}",
+        "Signer:".bright_white(),
+        Pubkey::new_from_array(signer).to_string().bright_cyan()
+    );
+    say!("  ✓ Saved to {}", output_path.bright_cyan());
+    if let Some(path) = &stale_sidecar {
+        say!("  Removed {}", path.bright_black());
+    }
+
+    if output::is_json() {
+        output::emit(&json!({
+            "input": input,
+            "output": output_path,
+            "signer": Pubkey::new_from_array(signer).to_string(),
+            "removed_sidecar": stale_sidecar,
+        }))?;
+    }
+
+    Ok(())
+}
@@ -1,45 +1,70 @@
 use crate::ui::formatter;
+use crate::ui::output::{self, say};
 use anyhow::{Context, Result};
 use colored::Colorize;
+use owlsol_core::container::FIXED_HEADER_LEN;
+use owlsol_core::rent::{self, Rent, ACCOUNT_STORAGE_OVERHEAD};
 use owlsol_core::{CompressionAlgorithm, Compressor};
+use serde_json::json;
 use std::fs;
 
-pub async fn execute(input: String, verbose: bool) -> Result<()> {
-    println!("{}", "🦉 OWLSOL Statistics".bright_cyan().bold());
-    println!();
+pub async fn execute(input: String, verbose: bool, rent: Rent) -> Result<()> {
+    say!("{}", "🦉 OWLSOL Statistics".bright_cyan().bold());
+    say!();
 
     let data = fs::read(&input).with_context(|| format!("Failed to read: {}", input))?;
 
-    println!("  {} {}", "File:".bright_white(), input.bright_cyan());
-    println!("  {} {} bytes", "Size:".bright_white(), data.len());
-    println!();
+    say!("  {} {}", "File:".bright_white(), input.bright_cyan());
+    say!("  {} {} bytes", "Size:".bright_white(), data.len());
+    say!();
 
     let compressor = Compressor::new();
 
     // Analyze data
-    if verbose {
-        let analysis = compressor.analyze_data(&data);
-        formatter::print_data_analysis(&analysis);
-        println!();
+    let analysis = verbose.then(|| compressor.analyze_data(&data));
+    if let Some(analysis) = &analysis {
+        formatter::print_data_analysis(analysis);
+        say!();
     }
 
     // Try all algorithms
-    let algorithms = vec![
+    let algorithms: Vec<_> = [
         CompressionAlgorithm::Huffman,
         CompressionAlgorithm::Dictionary,
         CompressionAlgorithm::RunLength,
         CompressionAlgorithm::Hybrid,
-    ];
+        CompressionAlgorithm::Lz4,
+        CompressionAlgorithm::Zstd,
+        CompressionAlgorithm::Brotli,
+        CompressionAlgorithm::Deflate,
+        CompressionAlgorithm::Gzip,
+        CompressionAlgorithm::Snappy,
+        CompressionAlgorithm::Lzma,
+    ]
+    .into_iter()
+    .filter(CompressionAlgorithm::is_available)
+    .collect();
 
-    println!("{}", "Compression Analysis:".bright_yellow().bold());
-    println!();
+    say!("{}", "Compression Analysis:".bright_yellow().bold());
+    say!();
 
     let mut best_algo = CompressionAlgorithm::None;
     let mut best_size = data.len();
 
+    let mut results = Vec::new();
     for algo in algorithms {
         if let Ok(result) = compressor.compress_with_algorithm(&data, Some(algo)) {
-            formatter::print_algorithm_stats(&algo, &result.metadata);
+            let meta = &result.metadata;
+            let comparison = rent.compare(data.len(), result.data.len(), FIXED_HEADER_LEN);
+            formatter::print_algorithm_stats(&algo, meta, &comparison);
+            let mut metadata = formatter::compression_json(meta);
+            metadata["rent"] =
+                formatter::rent_json(&rent, meta.original_size, meta.compressed_size);
+            results.push(json!({
+                "requested": algo.as_str(),
+                "decode_cu": result.decode_cu,
+                "result": metadata,
+            }));
 
             if result.data.len() < best_size {
                 best_size = result.data.len();
@@ -48,18 +73,109 @@ pub async fn execute(input: String, verbose: bool) -> Result<()> {
         }
     }
 
-    println!();
-    println!(
+    say!();
+    say!(
         "  {} {}",
         "Recommended:".bright_green().bold(),
         best_algo.as_str().bright_yellow()
     );
-    println!(
+    say!(
         "  {} {} bytes ({:.2}% reduction)",
         "Best compression:".bright_green().bold(),
         best_size,
         (1.0 - best_size as f64 / data.len() as f64) * 100.0
     );
 
+    let comparison = rent.compare(data.len(), best_size, FIXED_HEADER_LEN);
+    let ratio = best_size as f64 / data.len() as f64;
+    let break_even_size = rent::break_even_size(ratio, FIXED_HEADER_LEN);
+    print_rent(&rent, &comparison, break_even_size);
+
+    if output::is_json() {
+        output::emit(&json!({
+            "file": input,
+            "size": data.len(),
+            "analysis": analysis.as_ref().map(formatter::analysis_json),
+            "algorithms": results,
+            "recommended": best_algo.as_str(),
+            "best_size": best_size,
+            "rent": {
+                "lamports_per_byte_year": rent.lamports_per_byte_year,
+                "exemption_threshold": rent.exemption_threshold,
+                "account_overhead": ACCOUNT_STORAGE_OVERHEAD,
+                "header_bytes": FIXED_HEADER_LEN,
+                "original_lamports": comparison.original_lamports,
+                "compressed_lamports": comparison.stored_lamports,
+                "saved_lamports": comparison.saved_lamports(),
+                "saved_percent": comparison.saved_percent(),
+                "loss": comparison.is_loss(),
+                "break_even_payload": comparison.break_even_payload(),
+                "break_even_size": break_even_size,
+            },
+        }))?;
+    }
+
     Ok(())
 }
+
+/// Rent of the best result against the raw account, and where the container
+/// header stops compression from paying off
+fn print_rent(rent: &Rent, comparison: &rent::RentComparison, break_even_size: Option<usize>) {
+    say!();
+    say!(
+        "{} {} lamports/byte-year, {} years to exemption",
+        "Rent:".bright_yellow().bold(),
+        rent.lamports_per_byte_year,
+        rent.exemption_threshold
+    );
+    say!();
+    say!(
+        "  {} {:.6} SOL ({} bytes + {} overhead)",
+        "Raw account:".bright_white(),
+        formatter::sol(comparison.original_lamports),
+        comparison.original_size,
+        ACCOUNT_STORAGE_OVERHEAD
+    );
+    say!(
+        "  {} {:.6} SOL ({} bytes + {} header + {} overhead)",
+        "Compressed:".bright_white(),
+        formatter::sol(comparison.stored_lamports),
+        comparison.stored_size - comparison.header_len,
+        comparison.header_len,
+        ACCOUNT_STORAGE_OVERHEAD
+    );
+    if comparison.is_loss() {
+        say!(
+            "  {} the {}-byte header outweighs the bytes saved; compressing costs {:.6} SOL more",
+            "⚠".bright_yellow(),
+            comparison.header_len,
+            formatter::sol(comparison.stored_lamports - comparison.original_lamports)
+        );
+    } else {
+        say!(
+            "  {} {:.6} SOL ({:.2}% of the rent)",
+            "Saved:".bright_green().bold(),
+            formatter::sol(comparison.original_lamports - comparison.stored_lamports),
+            comparison.saved_percent()
+        );
+    }
+
+    match comparison.break_even_payload() {
+        Some(payload) => say!(
+            "  {} payloads up to {} bytes save rent",
+            "Break-even:".bright_white(),
+            payload
+        ),
+        None => say!(
+            "  {} the data is no larger than the header; compression cannot save rent",
+            "Break-even:".bright_white()
+        ),
+    }
+    if let Some(size) = break_even_size {
+        say!(
+            "  {:<11} at this ratio, accounts of {} bytes or more save rent",
+            "",
+            size
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use crate::files;
+use crate::ui::output::{self, say};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use owlsol_core::{ArchiveReader, DecompressOptions};
+use serde_json::json;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+pub async fn execute(input: String, output: String, entries: Vec<String>) -> Result<()> {
+    say!("{}", "🦉 OWLSOL Unpack".bright_cyan().bold());
+    say!();
+
+    let file = File::open(&input).with_context(|| format!("Failed to read: {}", input))?;
+    let mut reader = ArchiveReader::new(BufReader::new(file))
+        .with_context(|| format!("Invalid archive: {}", input))?;
+
+    // Only the requested entries are read; the rest of the archive is skipped
+    let selected = if entries.is_empty() {
+        reader.entries().to_vec()
+    } else {
+        entries
+            .iter()
+            .map(|name| {
+                reader
+                    .find(name)
+                    .cloned()
+                    .with_context(|| format!("No entry named {} in {}", name, input))
+            })
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    let root = Path::new(&output);
+    let mut extracted = Vec::new();
+    for entry in &selected {
+        let path = files::entry_path(root, &entry.name)?;
+        let data = reader
+            .extract_entry(entry, &DecompressOptions::default())
+            .with_context(|| format!("Failed to extract: {}", entry.name))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create: {}", parent.display()))?;
+        }
+        fs::write(&path, &data).with_context(|| format!("Failed to write: {}", path.display()))?;
+        say!(
+            "  ✓ {} ({} bytes)",
+            path.display().to_string().bright_cyan(),
+            data.len()
+        );
+        extracted.push(json!({
+            "name": entry.name,
+            "path": path.display().to_string(),
+            "size": data.len(),
+        }));
+    }
+
+    say!();
+    say!(
+        "  Extracted {} of {} entries",
+        selected.len(),
+        reader.entries().len()
+    );
+
+    if output::is_json() {
+        output::emit(&json!({
+            "input": input,
+            "extracted": extracted,
+            "entries": reader.entries().len(),
+        }))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,405 @@
+use super::decompress::parse_container;
+use crate::files::{self, STDIO};
+use crate::keys;
+use crate::ui::output;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use owlsol_core::archive::ARCHIVE_MAGIC;
+use owlsol_core::container::Kdf;
+use owlsol_core::encryption::{self, Key};
+use owlsol_core::{
+    signing, ArchiveReader, CompressionError, CompressionMetadata, Container, DecompressOptions,
+    Decompressor,
+};
+use owlsol_solana::{seal, Keypair, Pubkey};
+use rayon::prelude::*;
+use serde_json::json;
+use std::borrow::Cow;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// What a file needs to pass beyond decoding cleanly, and the keys for
+/// decoding encrypted ones
+struct Checks {
+    signer: Option<[u8; 32]>,
+    key: Option<Key>,
+    keypair: Option<Keypair>,
+    /// Pass over encrypted files there is no key for instead of failing them
+    skip_encrypted: bool,
+}
+
+enum Status {
+    Pass,
+    Fail(anyhow::Error),
+    /// Encrypted, no key to decode it with, and `--skip-encrypted` given
+    Skipped,
+}
+
+struct Report {
+    name: String,
+    status: Status,
+    metadata: Option<CompressionMetadata>,
+    encrypted: bool,
+    signer: Option<Pubkey>,
+}
+
+/// Check every file named by `inputs` without writing anything: the header
+/// or sidecar must parse, the signature (if any) must hold, and the payload
+/// must decode to its checksum and original size. Archives are checked
+/// entry by entry. An encrypted file without a key to decode it fails,
+/// unless `skip_encrypted`.
+pub async fn execute(
+    inputs: Vec<String>,
+    signer: Option<String>,
+    key_file: Option<String>,
+    keypair: Option<String>,
+    skip_encrypted: bool,
+) -> Result<()> {
+    let checks = Checks {
+        signer: signer.map(|signer| parse_pubkey(&signer)).transpose()?,
+        key: match key_file {
+            Some(path) => Some(keys::read_key_file(&path)?),
+            None => std::env::var(keys::PASSPHRASE_ENV)
+                .ok()
+                .map(Key::passphrase),
+        },
+        keypair: keypair
+            .map(|path| {
+                owlsol_solana::read_keypair(&path)
+                    .with_context(|| format!("Failed to load keypair: {}", path))
+            })
+            .transpose()?,
+        skip_encrypted,
+    };
+
+    let files = if inputs.iter().any(|input| input == STDIO) {
+        if inputs.len() > 1 {
+            anyhow::bail!("stdin cannot be verified together with other files");
+        }
+        vec![(PathBuf::from(STDIO), STDIO.to_string())]
+    } else {
+        let batch = files::is_batch(&inputs);
+        files::collect(&inputs)?
+            .into_iter()
+            .filter(|(path, _)| !batch || is_compressed(path))
+            .collect()
+    };
+    if files.is_empty() {
+        anyhow::bail!("No compressed files to verify");
+    }
+
+    let reports: Vec<Report> = files
+        .par_iter()
+        .flat_map_iter(|(path, name)| verify_file(path, name, &checks))
+        .collect();
+
+    if output::is_json() {
+        print_json(&reports)?;
+    } else {
+        print_text(&reports);
+    }
+
+    let total = reports.len();
+    let mut failures = reports
+        .into_iter()
+        .filter_map(|report| match report.status {
+            Status::Fail(err) => Some(err),
+            _ => None,
+        });
+    match failures.next() {
+        // The first failure decides the exit status
+        Some(err) => Err(err.context(format!(
+            "{} of {} files failed verification",
+            failures.count() + 1,
+            total
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Containers, archives and payloads with a sidecar; anything else found in
+/// a directory or glob is not ours to verify
+fn is_compressed(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    if name.ends_with(".meta.json") {
+        return false;
+    }
+    name.ends_with(".owlsol")
+        || name.ends_with(".owlpack")
+        || Path::new(&format!("{}.meta.json", name)).exists()
+}
+
+fn verify_file(path: &Path, name: &str, checks: &Checks) -> Vec<Report> {
+    let input = path.to_string_lossy();
+    let data = match files::read(&input) {
+        Ok(data) => data,
+        Err(err) => return vec![Report::failed(name.to_string(), err)],
+    };
+
+    if data.starts_with(&ARCHIVE_MAGIC) {
+        return verify_archive(name, data, checks);
+    }
+    match parse_container(&input, data) {
+        Ok(container) => vec![verify_container(name.to_string(), &container, checks)],
+        Err(err) => vec![Report::failed(name.to_string(), err)],
+    }
+}
+
+fn verify_container(name: String, container: &Container, checks: &Checks) -> Report {
+    let mut report = Report {
+        name,
+        status: Status::Pass,
+        metadata: Some(container.metadata.clone()),
+        encrypted: container.is_encrypted(),
+        signer: None,
+    };
+
+    if container.is_signed() || checks.signer.is_some() {
+        match signing::verify(container, checks.signer.as_ref()) {
+            Ok(signer) => report.signer = Some(Pubkey::new_from_array(signer)),
+            Err(err) => {
+                report.status = Status::Fail(signature_error(err));
+                return report;
+            }
+        }
+    }
+
+    let kdf = container.encryption.map(|encryption| encryption.kdf);
+    let payload = match (kdf, &checks.key, &checks.keypair) {
+        (None, _, _) => Ok(Cow::Borrowed(&container.payload[..])),
+        (Some(Kdf::X25519 { .. }), _, Some(keypair)) => seal::open(container, keypair)
+            .map(Cow::Owned)
+            .context("Decryption failed"),
+        (Some(Kdf::RawKey | Kdf::Argon2id { .. }), Some(key), _) => {
+            encryption::decrypt(container, key)
+                .map(Cow::Owned)
+                .context("Decryption failed")
+        }
+        _ if checks.skip_encrypted => {
+            report.status = Status::Skipped;
+            return report;
+        }
+        _ => Err(anyhow::Error::new(CompressionError::KeyRequired)
+            .context("Encrypted; pass --key or --keypair to decode, or --skip-encrypted")),
+    };
+
+    let decoded = payload.and_then(|payload| {
+        Decompressor::new()
+            .decompress_with(&payload, &container.metadata, &DecompressOptions::default())
+            .context("Payload does not decode")
+    });
+    if let Err(err) = decoded {
+        report.status = Status::Fail(err);
+    }
+    report
+}
+
+/// One report per entry, named `<archive>:<entry>`. Entries carry no
+/// signature, so each fails when a signer is required.
+fn verify_archive(name: &str, data: Vec<u8>, checks: &Checks) -> Vec<Report> {
+    let mut reader = match ArchiveReader::new(Cursor::new(data)) {
+        Ok(reader) => reader,
+        Err(err) => {
+            return vec![Report::failed(
+                name.to_string(),
+                anyhow::Error::new(err).context(format!("Invalid archive: {}", name)),
+            )]
+        }
+    };
+
+    let entries = reader.entries().to_vec();
+    entries
+        .iter()
+        .map(|entry| {
+            let status = if checks.signer.is_some() {
+                Status::Fail(
+                    anyhow::Error::new(CompressionError::NotSigned)
+                        .context("Archive entries cannot be signed"),
+                )
+            } else {
+                match reader.extract_entry(entry, &DecompressOptions::default()) {
+                    Ok(_) => Status::Pass,
+                    Err(err) => {
+                        Status::Fail(anyhow::Error::new(err).context("Entry does not decode"))
+                    }
+                }
+            };
+            Report {
+                name: format!("{}:{}", name, entry.name),
+                status,
+                metadata: Some(entry.metadata.clone()),
+                encrypted: false,
+                signer: None,
+            }
+        })
+        .collect()
+}
+
+impl Report {
+    fn failed(name: String, err: anyhow::Error) -> Self {
+        Self {
+            name,
+            status: Status::Fail(err),
+            metadata: None,
+            encrypted: false,
+            signer: None,
+        }
+    }
+}
+
+fn parse_pubkey(pubkey: &str) -> Result<[u8; 32]> {
+    let pubkey = Pubkey::from_str(pubkey).map_err(|err| {
+        CompressionError::invalid_input(format!("Invalid signer public key {}: {}", pubkey, err))
+    })?;
+    Ok(pubkey.to_bytes())
+}
+
+fn signature_error(err: CompressionError) -> anyhow::Error {
+    let context = match &err {
+        CompressionError::UnexpectedSigner { expected, actual } => format!(
+            "Expected a signature by {}, found one by {}",
+            Pubkey::new_from_array(*expected),
+            Pubkey::new_from_array(*actual)
+        ),
+        CompressionError::NotSigned => "File is not signed".to_string(),
+        _ => "Signature check failed".to_string(),
+    };
+    anyhow::Error::new(err).context(context)
+}
+
+fn print_text(reports: &[Report]) {
+    println!("{}", "🦉 OWLSOL Verify".bright_cyan().bold());
+    println!();
+
+    let (mut passed, mut failed, mut skipped) = (0, 0, 0);
+    for report in reports {
+        match &report.status {
+            Status::Pass => {
+                passed += 1;
+                let mut detail = report
+                    .metadata
+                    .as_ref()
+                    .map(|metadata| {
+                        format!(
+                            "{} → {} bytes ({})",
+                            metadata.compressed_size,
+                            metadata.original_size,
+                            metadata.algorithm.as_str()
+                        )
+                    })
+                    .unwrap_or_default();
+                if let Some(signer) = report.signer {
+                    detail.push_str(&format!(", signed by {}", signer));
+                }
+                println!(
+                    "  {} {} {}",
+                    "✓".bright_green(),
+                    report.name,
+                    detail.dimmed()
+                );
+            }
+            Status::Fail(err) => {
+                failed += 1;
+                println!("  {} {}: {:#}", "✗".bright_red(), report.name, err);
+            }
+            Status::Skipped => {
+                skipped += 1;
+                println!(
+                    "  {} {} {}",
+                    "-".yellow(),
+                    report.name,
+                    "encrypted, not decoded".dimmed()
+                );
+            }
+        }
+    }
+
+    println!();
+    let summary = format!("{} passed, {} failed, {} skipped", passed, failed, skipped);
+    if failed == 0 {
+        println!("  ✓ {}", summary);
+    } else {
+        println!("  {}", summary.bright_red());
+    }
+}
+
+fn print_json(reports: &[Report]) -> Result<()> {
+    let count = |pass: fn(&Status) -> bool| reports.iter().filter(|r| pass(&r.status)).count();
+    let files: Vec<_> = reports
+        .iter()
+        .map(|report| {
+            let (status, error) = match &report.status {
+                Status::Pass => ("pass", None),
+                Status::Fail(err) => ("fail", Some(err)),
+                Status::Skipped => ("skipped", None),
+            };
+            let code = error.and_then(|err| {
+                err.chain()
+                    .find_map(|cause| cause.downcast_ref::<CompressionError>())
+                    .map(CompressionError::code)
+            });
+            json!({
+                "file": report.name,
+                "status": status,
+                "algorithm": report.metadata.as_ref().map(|m| m.algorithm.as_str()),
+                "original_size": report.metadata.as_ref().map(|m| m.original_size),
+                "compressed_size": report.metadata.as_ref().map(|m| m.compressed_size),
+                "checksum": report.metadata.as_ref().map(|m| format!("{:08x}", m.checksum)),
+                "encrypted": report.encrypted,
+                "signer": report.signer.map(|signer| signer.to_string()),
+                "error": error.map(|err| json!({ "code": code, "message": format!("{:#}", err) })),
+            })
+        })
+        .collect();
+
+    let document = json!({
+        "passed": count(|status| matches!(status, Status::Pass)),
+        "failed": count(|status| matches!(status, Status::Fail(_))),
+        "skipped": count(|status| matches!(status, Status::Skipped)),
+        "files": files,
+    });
+    output::emit(&document)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use owlsol_core::ArchiveWriter;
+
+    fn checks(signer: Option<[u8; 32]>) -> Checks {
+        Checks {
+            signer,
+            key: None,
+            keypair: None,
+            skip_encrypted: false,
+        }
+    }
+
+    #[test]
+    fn test_archive_signer() {
+        let mut writer = ArchiveWriter::new(Vec::new()).unwrap();
+        writer.compress("a", &[7u8; 100], None).unwrap();
+        writer.compress("b", b"hello hello hello", None).unwrap();
+        let archive = writer.finish().unwrap();
+
+        let reports = verify_archive("x.owlpack", archive.clone(), &checks(None));
+        assert_eq!(reports.len(), 2);
+        assert!(reports
+            .iter()
+            .all(|report| matches!(report.status, Status::Pass)));
+
+        // A required signer can't be satisfied by unsigned entries
+        let reports = verify_archive("x.owlpack", archive, &checks(Some([1; 32])));
+        assert_eq!(reports[0].name, "x.owlpack:a");
+        for report in &reports {
+            let Status::Fail(err) = &report.status else {
+                panic!("{} passed without a signature", report.name);
+            };
+            assert!(matches!(
+                err.downcast_ref::<CompressionError>(),
+                Some(CompressionError::NotSigned)
+            ));
+        }
+    }
+}
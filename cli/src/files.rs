@@ -0,0 +1,181 @@
+//! Expanding command-line inputs into the files they name
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+/// Stands for stdin as an input and stdout as an output
+pub const STDIO: &str = "-";
+
+/// gzip-style flags shared by `compress` and `decompress`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OutputFlags {
+    /// Write to stdout (`-c`)
+    pub stdout: bool,
+    /// Keep input files after success (`-k`)
+    pub keep: bool,
+    /// Replace existing output files (`-f`)
+    pub force: bool,
+}
+
+/// Read a file, or stdin for `-`
+pub fn read(input: &str) -> Result<Vec<u8>> {
+    if input == STDIO {
+        let mut data = Vec::new();
+        io::stdin()
+            .read_to_end(&mut data)
+            .context("Failed to read stdin")?;
+        return Ok(data);
+    }
+    fs::read(input).with_context(|| format!("Failed to read: {}", input))
+}
+
+/// Write a file, or stdout for `-`. An existing file is only replaced with
+/// `force`.
+pub fn write(path: &str, data: &[u8], force: bool) -> Result<()> {
+    if path == STDIO {
+        let mut stdout = io::stdout().lock();
+        stdout
+            .write_all(data)
+            .and_then(|_| stdout.flush())
+            .context("Failed to write stdout")?;
+        return Ok(());
+    }
+    check_overwrite(path, force)?;
+    fs::write(path, data).with_context(|| format!("Failed to write: {}", path))
+}
+
+/// Refuse to replace an existing file unless `force`
+pub fn check_overwrite(path: &str, force: bool) -> Result<()> {
+    if !force && path != STDIO && Path::new(path).exists() {
+        return Err(io::Error::from(io::ErrorKind::AlreadyExists))
+            .with_context(|| format!("{} already exists; pass -f to overwrite", path));
+    }
+    Ok(())
+}
+
+/// Delete an input once its output is written, unless it is stdin
+pub fn remove_input(input: &str) -> Result<()> {
+    if input != STDIO {
+        fs::remove_file(input).with_context(|| format!("Failed to remove: {}", input))?;
+    }
+    Ok(())
+}
+
+/// Files named by `inputs` (paths, directories or glob patterns), each with
+/// a `/`-separated name relative to the input it came from: a file keeps its
+/// file name, and files under a directory are named
+/// `<directory>/<relative path>`. Sorted by name.
+pub fn collect(inputs: &[String]) -> Result<Vec<(PathBuf, String)>> {
+    let mut files = Vec::new();
+    for input in inputs {
+        let path = Path::new(input);
+        if path.exists() || !is_pattern(input) {
+            add(path, &mut files)?;
+            continue;
+        }
+
+        let matches = glob::glob(input).with_context(|| format!("Invalid pattern: {}", input))?;
+        let before = files.len();
+        for path in matches {
+            add(&path?, &mut files)?;
+        }
+        if files.len() == before {
+            anyhow::bail!("No files match {}", input);
+        }
+    }
+    files.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok(files)
+}
+
+/// Whether `input` names more than one file: several inputs, a directory or
+/// a glob pattern
+pub fn is_batch(inputs: &[String]) -> bool {
+    match inputs {
+        [input] => {
+            let path = Path::new(input);
+            path.is_dir() || (!path.exists() && is_pattern(input))
+        }
+        _ => true,
+    }
+}
+
+fn is_pattern(input: &str) -> bool {
+    input.contains(['*', '?', '['])
+}
+
+fn add(path: &Path, files: &mut Vec<(PathBuf, String)>) -> Result<()> {
+    let metadata =
+        fs::metadata(path).with_context(|| format!("Failed to read: {}", path.display()))?;
+    if metadata.is_dir() {
+        let prefix = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        walk(path, &prefix, files)
+    } else {
+        let name = path
+            .file_name()
+            .with_context(|| format!("Not a file: {}", path.display()))?;
+        files.push((path.to_path_buf(), name.to_string_lossy().into_owned()));
+        Ok(())
+    }
+}
+
+fn walk(dir: &Path, prefix: &str, files: &mut Vec<(PathBuf, String)>) -> Result<()> {
+    let entries =
+        fs::read_dir(dir).with_context(|| format!("Failed to read: {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let name = if prefix.is_empty() {
+            file_name
+        } else {
+            format!("{}/{}", prefix, file_name)
+        };
+        if entry.file_type()?.is_dir() {
+            walk(&entry.path(), &name, files)?;
+        } else {
+            files.push((entry.path(), name));
+        }
+    }
+    Ok(())
+}
+
+/// Where an entry called `name` goes under `root`, refusing names that would
+/// escape it
+pub fn entry_path(root: &Path, name: &str) -> Result<PathBuf> {
+    let relative = Path::new(name);
+    let safe = !name.contains('\\')
+        && relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    if !safe {
+        anyhow::bail!("Refusing to extract unsafe entry name: {}", name);
+    }
+    Ok(root.join(relative))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_path_rejects_escapes() {
+        let root = Path::new("out");
+        assert_eq!(
+            entry_path(root, "accounts/a.bin").unwrap(),
+            root.join("accounts/a.bin")
+        );
+        for name in [
+            "../etc/passwd",
+            "/etc/passwd",
+            "a/../../b",
+            "a\\..\\b",
+            "./a",
+        ] {
+            assert!(entry_path(root, name).is_err(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,36 @@
+//! Key and passphrase input for encrypted containers
+
+use anyhow::{Context, Result};
+use owlsol_core::encryption::{Key, KEY_LEN};
+use std::fs;
+
+/// Read instead of prompting, for scripts
+pub const PASSPHRASE_ENV: &str = "OWLSOL_PASSPHRASE";
+
+/// Load a key file holding either 32 raw bytes or 64 hex digits
+pub fn read_key_file(path: &str) -> Result<Key> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read key file: {}", path))?;
+
+    let text = std::str::from_utf8(&bytes).unwrap_or_default().trim();
+    if text.len() == KEY_LEN * 2 && text.bytes().all(|b| b.is_ascii_hexdigit()) {
+        let decoded: Vec<u8> = (0..KEY_LEN)
+            .map(|i| u8::from_str_radix(&text[2 * i..2 * i + 2], 16).unwrap())
+            .collect();
+        return Ok(Key::raw(&decoded)?);
+    }
+    Ok(Key::raw(&bytes)?)
+}
+
+/// Passphrase from `OWLSOL_PASSPHRASE`, or prompted on the terminal
+pub fn read_passphrase(confirm: bool) -> Result<Key> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(Key::passphrase(passphrase));
+    }
+
+    let mut prompt = dialoguer::Password::new().with_prompt("Passphrase");
+    if confirm {
+        prompt = prompt.with_confirmation("Confirm passphrase", "Passphrases do not match");
+    }
+    let passphrase = prompt.interact().context("Failed to read passphrase")?;
+    Ok(Key::passphrase(passphrase))
+}
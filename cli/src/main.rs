@@ -1,8 +1,20 @@
 mod commands;
+mod files;
+mod keys;
+mod profile;
 mod ui;
 
 use clap::{Parser, Subcommand};
-use commands::{benchmark, compress, decompress, stats};
+use commands::{
+    batch, benchmark, compress, config, decompress, inspect, list, pack, sign, stats, unpack,
+    verify,
+};
+use owlsol_core::rent::{self, Rent};
+use owlsol_core::synthetic::AccountKind;
+use owlsol_core::{CompressionError, ErrorKind};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use ui::output::{self, Format};
 
 #[derive(Parser)]
 #[command(name = "owlsol")]
@@ -10,6 +22,28 @@ use commands::{benchmark, compress, decompress, stats};
 #[command(version)]
 #[command(author = "OWLSOL Team")]
 struct Cli {
+    /// Log more detail to stderr (-v debug, -vv trace); RUST_LOG overrides
+    #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Only log errors
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Report as colored text, as one JSON document on stdout, or (for
+    /// `benchmark`) as CSV rows; defaults to the profile's format, else text
+    #[arg(long, global = true, value_enum)]
+    format: Option<Format>,
+
+    /// Same as --format json; kept for scripts written against the
+    /// `verify --json` and `inspect --json` flags
+    #[arg(long, global = true, hide = true, conflicts_with = "format")]
+    json: bool,
+
+    /// owlsol.toml profile to take defaults from (defaults to `default`)
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -18,25 +52,55 @@ struct Cli {
 enum Commands {
     /// Compress data and optionally deploy to Solana
     Compress {
-        /// Input file path
-        #[arg(short, long)]
-        input: String,
+        /// Input files, directories or glob patterns; more than one file
+        /// compresses them as a batch
+        #[arg(short, long, required = true, num_args = 1..)]
+        input: Vec<String>,
 
-        /// Output file path (optional, defaults to <input>.owlsol)
+        /// Output file path (optional, defaults to <input>.owlsol); for a
+        /// batch, the directory to write into
         #[arg(short, long)]
         output: Option<String>,
 
-        /// Compression algorithm (huffman, dictionary, rle, hybrid, auto)
-        #[arg(short, long, default_value = "auto")]
-        algorithm: String,
+        /// Worker threads for a batch (defaults to one per CPU)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+
+        #[command(flatten)]
+        flags: OutputArgs,
 
-        /// Deploy compressed data to Solana devnet
+        /// Compression algorithm (huffman, dictionary, rle, hybrid, lz4, zstd, brotli, deflate, gzip, snappy, lzma, auto);
+        /// defaults to the profile's, else auto
+        #[arg(short, long)]
+        algorithm: Option<String>,
+
+        /// Compression level for zstd, brotli, deflate, gzip and lzma
+        /// (clamped to each one's range; defaults to the profile's)
+        #[arg(short, long)]
+        level: Option<u32>,
+
+        /// Deploy compressed data to the profile's cluster (devnet by default)
         #[arg(short, long)]
         deploy: bool,
 
+        #[command(flatten)]
+        rent: RentArgs,
+
         /// Show results in ratatui UI
         #[arg(long)]
         ui: bool,
+
+        /// Encrypt the compressed data (passphrase prompt, or OWLSOL_PASSPHRASE)
+        #[arg(long)]
+        encrypt: bool,
+
+        /// Encrypt with a 32-byte key file (raw or hex) instead of a passphrase
+        #[arg(long, value_name = "FILE", requires = "encrypt")]
+        key: Option<String>,
+
+        /// Seal the output to a Solana public key; only its keypair can decrypt
+        #[arg(long, value_name = "PUBKEY", conflicts_with = "encrypt")]
+        recipient: Option<String>,
     },
 
     /// Decompress data from file or Solana account
@@ -50,8 +114,107 @@ enum Commands {
         output: Option<String>,
 
         /// Fetch from Solana account instead of file
-        #[arg(short, long)]
+        #[arg(long)]
         from_solana: bool,
+
+        #[command(flatten)]
+        flags: OutputArgs,
+
+        /// Key file for data encrypted with `compress --encrypt --key`
+        #[arg(long, value_name = "FILE")]
+        key: Option<String>,
+
+        /// Keypair for data sealed with `compress --recipient` (defaults to
+        /// the profile's, else ~/.config/solana/id.json)
+        #[arg(long, value_name = "FILE")]
+        keypair: Option<PathBuf>,
+    },
+
+    /// Sign a compressed file with a Solana keypair
+    Sign {
+        /// Compressed file (container, or payload with a .meta.json sidecar)
+        #[arg(short, long)]
+        input: String,
+
+        /// Output file path (optional, defaults to signing in place)
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Signing keypair (defaults to the profile's, else
+        /// ~/.config/solana/id.json)
+        #[arg(long, value_name = "FILE")]
+        keypair: Option<PathBuf>,
+
+        /// Replace an existing output file other than the input
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Check files decode to their checksum and size (and signatures hold)
+    /// without writing anything
+    Verify {
+        /// Containers, payloads with a sidecar, archives, directories or
+        /// glob patterns
+        #[arg(short, long, required = true, num_args = 1..)]
+        input: Vec<String>,
+
+        /// Public key every file must be signed by
+        #[arg(long, value_name = "PUBKEY")]
+        signer: Option<String>,
+
+        /// Key file to decode encrypted files with (OWLSOL_PASSPHRASE is
+        /// used for passphrase-encrypted ones)
+        #[arg(long)]
+        key: Option<String>,
+
+        /// Solana keypair to decode files sealed to it
+        #[arg(long)]
+        keypair: Option<String>,
+
+        /// Report encrypted files there is no key for as skipped instead of
+        /// failing them
+        #[arg(long)]
+        skip_encrypted: bool,
+    },
+
+    /// Bundle files and directories into one .owlpack archive
+    Pack {
+        /// Files or directories to pack
+        #[arg(required = true)]
+        inputs: Vec<String>,
+
+        /// Archive to write
+        #[arg(short, long)]
+        output: String,
+
+        /// Compression algorithm for every entry (see `compress`)
+        #[arg(short, long)]
+        algorithm: Option<String>,
+
+        /// Compression level (see `compress`)
+        #[arg(short, long)]
+        level: Option<u32>,
+    },
+
+    /// Extract entries from an .owlpack archive
+    Unpack {
+        /// Archive to read
+        #[arg(short, long)]
+        input: String,
+
+        /// Directory to extract into
+        #[arg(short, long, default_value = ".")]
+        output: String,
+
+        /// Entries to extract (all when omitted); others are not read
+        entries: Vec<String>,
+    },
+
+    /// List the entries of an .owlpack archive
+    List {
+        /// Archive to read
+        #[arg(short, long)]
+        input: String,
     },
 
     /// Show compression statistics for a file
@@ -63,38 +226,279 @@ enum Commands {
         /// Show detailed analysis
         #[arg(short = 'v', long)]
         verbose: bool,
+
+        #[command(flatten)]
+        rent: RentArgs,
     },
 
-    /// Run compression benchmarks
-    Benchmark {
-        /// Input file or directory
+    /// Show a compressed file's metadata and codec internals
+    Inspect {
+        /// Container, or payload with a sidecar
         #[arg(short, long)]
         input: String,
 
-        /// Number of iterations per test
+        /// Show every code length and dictionary entry
+        #[arg(short = 'v', long)]
+        verbose: bool,
+    },
+
+    /// Run compression benchmarks
+    Benchmark {
+        /// Input file, or a directory or glob pattern to benchmark as a
+        /// corpus with per-file and aggregate results
+        #[arg(short, long, required_unless_present = "synthetic")]
+        input: Option<String>,
+
+        #[command(flatten)]
+        synthetic: SyntheticArgs,
+
+        /// Number of timed iterations per algorithm
         #[arg(short = 'n', long, default_value = "100")]
         iterations: usize,
 
-        /// Output report file (optional)
+        /// Untimed iterations run first, so caches and allocators settle
+        #[arg(short, long, default_value = "10")]
+        warmup: usize,
+
+        /// Also save the report, in the --format chosen, to this file
         #[arg(short, long)]
         output: Option<String>,
+
+        #[command(flatten)]
+        baseline: BaselineArgs,
+    },
+
+    /// Inspect owlsol.toml settings
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
     },
 }
 
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Show the config files found and the selected profile's settings
+    Show,
+}
+
+/// Generated accounts to benchmark instead of files
+#[derive(clap::Args)]
+struct SyntheticArgs {
+    /// Benchmark generated accounts of these kinds (comma-separated, or
+    /// `all`) instead of --input
+    #[arg(
+        long,
+        value_name = "KIND",
+        value_delimiter = ',',
+        conflicts_with = "input",
+        value_parser = benchmark::synthetic_kinds()
+    )]
+    synthetic: Vec<String>,
+
+    /// Seed for the generated accounts; the same seed gives the same data
+    #[arg(long, default_value = "0", requires = "synthetic")]
+    seed: u64,
+
+    /// Accounts generated per kind
+    #[arg(long, default_value = "16", requires = "synthetic")]
+    count: usize,
+}
+
+impl From<SyntheticArgs> for benchmark::Synthetic {
+    fn from(args: SyntheticArgs) -> Self {
+        let kinds = if args
+            .synthetic
+            .iter()
+            .any(|kind| kind == benchmark::ALL_KINDS)
+        {
+            AccountKind::ALL.to_vec()
+        } else {
+            args.synthetic
+                .iter()
+                .filter_map(|kind| AccountKind::from_name(kind))
+                .collect()
+        };
+        Self {
+            kinds,
+            count: args.count,
+            seed: args.seed,
+        }
+    }
+}
+
+/// gzip-style output flags; `-` as a path also means stdin/stdout
+#[derive(clap::Args)]
+struct OutputArgs {
+    /// Write to stdout, keeping the input
+    #[arg(short = 'c', long)]
+    stdout: bool,
+
+    /// Keep the input file instead of removing it
+    #[arg(short, long)]
+    keep: bool,
+
+    /// Overwrite existing output files
+    #[arg(short, long)]
+    force: bool,
+}
+
+/// Rent parameters for pricing accounts offline
+#[derive(clap::Args)]
+struct RentArgs {
+    /// Override the rent rate, for clusters that charge differently
+    #[arg(long, value_name = "LAMPORTS", default_value_t = rent::LAMPORTS_PER_BYTE_YEAR)]
+    lamports_per_byte_year: u64,
+
+    /// Override the years of rent an account holds to be exempt
+    #[arg(long, value_name = "YEARS", default_value_t = rent::EXEMPTION_THRESHOLD)]
+    exemption_threshold: f64,
+}
+
+impl From<RentArgs> for Rent {
+    fn from(args: RentArgs) -> Self {
+        Rent::new(args.lamports_per_byte_year, args.exemption_threshold)
+    }
+}
+
+/// Record benchmark results, or check them against earlier ones
+#[derive(clap::Args)]
+struct BaselineArgs {
+    /// Save the results as a baseline for later --baseline runs
+    #[arg(long, value_name = "FILE")]
+    save_baseline: Option<String>,
+
+    /// Compare against a saved baseline; exits with an error when any
+    /// algorithm's ratio or throughput regresses beyond --threshold
+    #[arg(long, value_name = "FILE")]
+    baseline: Option<String>,
+
+    /// Largest tolerated regression, such as 5%
+    #[arg(long, default_value = "5%", value_parser = benchmark::parse_threshold)]
+    threshold: f64,
+}
+
+impl From<BaselineArgs> for benchmark::BaselineOptions {
+    fn from(args: BaselineArgs) -> Self {
+        Self {
+            save: args.save_baseline,
+            compare: args.baseline,
+            threshold: args.threshold,
+        }
+    }
+}
+
+impl From<OutputArgs> for files::OutputFlags {
+    fn from(args: OutputArgs) -> Self {
+        Self {
+            stdout: args.stdout,
+            keep: args.keep,
+            force: args.force,
+        }
+    }
+}
+
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> ExitCode {
     let cli = Cli::parse();
+    init_tracing(cli.verbose, cli.quiet);
+
+    let config = profile::load(cli.profile.as_deref());
+    let format = cli
+        .format
+        .or(cli.json.then_some(Format::Json))
+        .or_else(|| config.as_ref().ok()?.settings.format)
+        .unwrap_or_default();
+    output::set_format(format);
+
+    let result = match config {
+        Ok(config) => run(cli.command, config).await,
+        Err(err) => Err(err),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            let code = find_compression_error(&err).map(CompressionError::code);
+            if let Some(code) = code {
+                eprintln!("Error code: {}", code);
+            }
+            // Scripts reading stdout still get a document when a command
+            // fails before producing its own
+            if output::is_json() && !output::emitted() {
+                let _ = output::emit(&serde_json::json!({
+                    "error": {
+                        "message": format!("{:#}", err),
+                        "code": code,
+                        "exit_code": exit_code(&err),
+                    }
+                }));
+            }
+            ExitCode::from(exit_code(&err))
+        }
+    }
+}
+
+async fn run(command: Commands, config: profile::Config) -> anyhow::Result<()> {
+    let settings = &config.settings;
+    let or_profile_algorithm =
+        |algorithm: Option<String>| algorithm.unwrap_or_else(|| settings.algorithm().to_string());
+    let or_profile_keypair =
+        |keypair: Option<PathBuf>| keypair.or_else(|| settings.keypair.clone());
+
+    if output::format() == Format::Csv && !matches!(command, Commands::Benchmark { .. }) {
+        return Err(
+            CompressionError::invalid_input("--format csv only applies to benchmark").into(),
+        );
+    }
 
-    match cli.command {
+    match command {
         Commands::Compress {
             input,
             output,
+            jobs,
+            flags,
             algorithm,
+            level,
             deploy,
+            rent,
             ui,
+            encrypt,
+            key,
+            recipient,
         } => {
-            let result =
-                compress::execute(input.clone(), output.clone(), algorithm.clone(), deploy).await?;
+            let protection =
+                compress::Protection::from_args(encrypt, key.as_deref(), recipient.as_deref())?;
+            let algorithm = or_profile_algorithm(algorithm);
+            let level = level.or(settings.level);
+            if files::is_batch(&input) {
+                if deploy || ui || flags.stdout {
+                    anyhow::bail!("--deploy, --ui and --stdout only apply to a single file");
+                }
+                return batch::execute(
+                    input,
+                    output,
+                    algorithm,
+                    level,
+                    jobs,
+                    protection,
+                    flags.into(),
+                )
+                .await;
+            }
+            if ui && output::is_json() {
+                anyhow::bail!("--ui cannot be combined with --format json");
+            }
+            let input = input.into_iter().next().unwrap_or_default();
+            let result = compress::execute(
+                input,
+                output,
+                algorithm,
+                level,
+                deploy.then(|| (settings.clone(), rent.into())),
+                protection,
+                flags.into(),
+            )
+            .await?;
             if ui {
                 use ui::ratatui_ui::show_compression_stats;
                 let stats = vec![
@@ -130,20 +534,140 @@ async fn main() -> anyhow::Result<()> {
             input,
             output,
             from_solana,
+            flags,
+            key,
+            keypair,
+        } => {
+            decompress::execute(
+                input,
+                output,
+                from_solana,
+                key,
+                or_profile_keypair(keypair),
+                flags.into(),
+            )
+            .await?;
+        }
+        Commands::Sign {
+            input,
+            output,
+            keypair,
+            force,
+        } => {
+            sign::execute(input, output, or_profile_keypair(keypair), force).await?;
+        }
+        Commands::Verify {
+            input,
+            signer,
+            key,
+            keypair,
+            skip_encrypted,
+        } => {
+            verify::execute(input, signer, key, keypair, skip_encrypted).await?;
+        }
+        Commands::Pack {
+            inputs,
+            output,
+            algorithm,
+            level,
+        } => {
+            let algorithm = or_profile_algorithm(algorithm);
+            pack::execute(inputs, output, algorithm, level.or(settings.level)).await?;
+        }
+        Commands::Unpack {
+            input,
+            output,
+            entries,
+        } => {
+            unpack::execute(input, output, entries).await?;
+        }
+        Commands::List { input } => {
+            list::execute(input).await?;
+        }
+        Commands::Stats {
+            input,
+            verbose,
+            rent,
         } => {
-            decompress::execute(input, output, from_solana).await?;
+            stats::execute(input, verbose, rent.into()).await?;
         }
-        Commands::Stats { input, verbose } => {
-            stats::execute(input, verbose).await?;
+        Commands::Inspect { input, verbose } => {
+            inspect::execute(input, verbose).await?;
         }
         Commands::Benchmark {
             input,
+            synthetic,
             iterations,
+            warmup,
             output,
+            baseline,
         } => {
-            benchmark::execute(input, iterations, output).await?;
+            let source = match input {
+                Some(input) => benchmark::Source::Path(input),
+                None => benchmark::Source::Synthetic(synthetic.into()),
+            };
+            benchmark::execute(source, iterations, warmup, output, baseline.into()).await?;
+        }
+        Commands::Config {
+            command: ConfigCommand::Show,
+        } => {
+            config::show(&config).await?;
         }
     }
 
     Ok(())
 }
+
+fn find_compression_error(err: &anyhow::Error) -> Option<&CompressionError> {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<CompressionError>())
+}
+
+/// Exit status for a failed command. Each error kind gets its own status so
+/// scripts can tell bad input from damaged or unsupported data:
+///
+/// | status | meaning |
+/// |--------|---------|
+/// | 1 | other failure |
+/// | 2 | invalid command line (from clap) |
+/// | 3 | invalid input |
+/// | 4 | unsupported algorithm |
+/// | 5 | corrupted data |
+/// | 6 | codec failure |
+/// | 7 | I/O error |
+/// | 8 | missing or wrong key |
+fn exit_code(err: &anyhow::Error) -> u8 {
+    if let Some(err) = find_compression_error(err) {
+        return match err.kind() {
+            ErrorKind::InvalidInput => 3,
+            ErrorKind::Unsupported => 4,
+            ErrorKind::Corrupted => 5,
+            ErrorKind::Backend => 6,
+            ErrorKind::Io => 7,
+            ErrorKind::Authentication => 8,
+        };
+    }
+    if err.chain().any(|cause| cause.is::<std::io::Error>()) {
+        return 7;
+    }
+    1
+}
+
+/// Send logs to stderr so they never mix with command output
+fn init_tracing(verbose: u8, quiet: bool) {
+    use tracing_subscriber::EnvFilter;
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        EnvFilter::new(match (quiet, verbose) {
+            (true, _) => "error",
+            (false, 0) => "warn",
+            (false, 1) => "warn,owlsol_core=debug,owlsol_solana=debug,owlsol_cli=debug",
+            (false, _) => "warn,owlsol_core=trace,owlsol_solana=trace,owlsol_cli=trace",
+        })
+    });
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
+}
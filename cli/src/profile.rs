@@ -0,0 +1,319 @@
+//! `owlsol.toml` profiles
+//!
+//! Settings come from `owlsol.toml` in the current directory or the nearest
+//! parent that has one, then from `$XDG_CONFIG_HOME/owlsol/owlsol.toml`
+//! (`~/.config` when unset). Each file holds named tables:
+//!
+//! ```toml
+//! [profiles.default]
+//! algorithm = "zstd"
+//! level = 19
+//!
+//! [profiles.mainnet]
+//! rpc_url = "https://api.mainnet-beta.solana.com"
+//! keypair = "~/.config/solana/mainnet.json"
+//! commitment = "finalized"
+//! format = "json"
+//! ```
+//!
+//! `--profile` picks a table (`default` otherwise). Its unset fields fall
+//! back to the `default` table, and the project file wins over the user
+//! file field by field. Command-line flags override everything.
+
+use crate::commands::compress::parse_algorithm;
+use crate::ui::output::Format;
+use anyhow::{Context, Result};
+use owlsol_core::CompressionError;
+use owlsol_solana::{CommitmentConfig, SolanaClient, DEVNET_RPC_URL};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub const FILE_NAME: &str = "owlsol.toml";
+
+/// Profile used when `--profile` is not given
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Settings a profile can set; `None` leaves the built-in default
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub algorithm: Option<String>,
+    pub level: Option<u32>,
+    pub rpc_url: Option<String>,
+    pub keypair: Option<PathBuf>,
+    pub commitment: Option<Commitment>,
+    pub format: Option<Format>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    profiles: BTreeMap<String, Profile>,
+}
+
+/// The selected profile, merged from every file found
+#[derive(Debug, Default)]
+pub struct Config {
+    pub profile: String,
+    /// Files read, project first
+    pub sources: Vec<PathBuf>,
+    /// Every profile name the files define
+    pub profiles: Vec<String>,
+    pub settings: Profile,
+}
+
+impl Profile {
+    /// Fill unset fields from `fallback`
+    fn or(self, fallback: &Profile) -> Profile {
+        Profile {
+            algorithm: self.algorithm.or_else(|| fallback.algorithm.clone()),
+            level: self.level.or(fallback.level),
+            rpc_url: self.rpc_url.or_else(|| fallback.rpc_url.clone()),
+            keypair: self.keypair.or_else(|| fallback.keypair.clone()),
+            commitment: self.commitment.or(fallback.commitment),
+            format: self.format.or(fallback.format),
+        }
+    }
+
+    pub fn algorithm(&self) -> &str {
+        self.algorithm.as_deref().unwrap_or("auto")
+    }
+
+    pub fn rpc_url(&self) -> &str {
+        self.rpc_url.as_deref().unwrap_or(DEVNET_RPC_URL)
+    }
+
+    pub fn keypair(&self) -> PathBuf {
+        self.keypair
+            .clone()
+            .unwrap_or_else(owlsol_solana::default_keypair_path)
+    }
+
+    pub fn commitment(&self) -> Commitment {
+        self.commitment.unwrap_or(Commitment::Confirmed)
+    }
+
+    /// Connect to the profile's cluster with its keypair
+    pub fn connect(&self) -> Result<SolanaClient> {
+        let commitment = match self.commitment() {
+            Commitment::Processed => CommitmentConfig::processed(),
+            Commitment::Confirmed => CommitmentConfig::confirmed(),
+            Commitment::Finalized => CommitmentConfig::finalized(),
+        };
+        SolanaClient::with_commitment(self.rpc_url(), self.keypair(), commitment)
+            .with_context(|| format!("Failed to connect to {}", self.rpc_url()))
+    }
+
+    /// Reject what would otherwise surface much later, and make keypair
+    /// paths independent of the working directory
+    fn resolve(mut self, dir: &Path, file: &Path) -> Result<Profile> {
+        if let Some(algorithm) = &self.algorithm {
+            if algorithm != "auto" && parse_algorithm(algorithm).is_none() {
+                return Err(CompressionError::invalid_input(format!(
+                    "Unknown algorithm {:?} in {}",
+                    algorithm,
+                    file.display()
+                ))
+                .into());
+            }
+        }
+        // CSV only suits benchmark rows, so as a default it would break
+        // every other command
+        if self.format == Some(Format::Csv) {
+            return Err(CompressionError::invalid_input(format!(
+                "format = \"csv\" in {} is only available as --format csv for benchmark",
+                file.display()
+            ))
+            .into());
+        }
+        self.keypair = self.keypair.map(|path| expand(&path, dir));
+        Ok(self)
+    }
+}
+
+impl Commitment {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Commitment::Processed => "processed",
+            Commitment::Confirmed => "confirmed",
+            Commitment::Finalized => "finalized",
+        }
+    }
+}
+
+/// Find and merge the config files, selecting `profile` (or the default)
+pub fn load(profile: Option<&str>) -> Result<Config> {
+    let cwd = std::env::current_dir().context("Failed to read the current directory")?;
+    let mut paths: Vec<PathBuf> = [project_file(&cwd), user_file()]
+        .into_iter()
+        .flatten()
+        .collect();
+    // Run from inside the user config directory, both are the same file
+    paths.dedup();
+    let files = paths
+        .into_iter()
+        .map(|path| read(&path).map(|file| (path, file)))
+        .collect::<Result<Vec<_>>>()?;
+    select(files, profile)
+}
+
+fn select(files: Vec<(PathBuf, ConfigFile)>, profile: Option<&str>) -> Result<Config> {
+    let name = profile.unwrap_or(DEFAULT_PROFILE);
+    let mut profiles: Vec<String> = files
+        .iter()
+        .flat_map(|(_, file)| file.profiles.keys().cloned())
+        .collect();
+    profiles.sort();
+    profiles.dedup();
+    if profile.is_some() && !profiles.iter().any(|known| known == name) {
+        return Err(CompressionError::invalid_input(format!(
+            "Profile {:?} is not defined{}",
+            name,
+            if files.is_empty() {
+                format!(" (no {} found)", FILE_NAME)
+            } else {
+                String::new()
+            }
+        ))
+        .into());
+    }
+
+    // The named profile beats the default one, and within each the project
+    // file beats the user file
+    let lookup = |name: &str| {
+        files
+            .iter()
+            .filter_map(|(_, file)| file.profiles.get(name))
+            .fold(Profile::default(), |merged, next| merged.or(next))
+    };
+    let mut settings = lookup(name);
+    if name != DEFAULT_PROFILE {
+        settings = settings.or(&lookup(DEFAULT_PROFILE));
+    }
+
+    Ok(Config {
+        profile: name.to_string(),
+        sources: files.into_iter().map(|(path, _)| path).collect(),
+        profiles,
+        settings,
+    })
+}
+
+fn read(path: &Path) -> Result<ConfigFile> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read: {}", path.display()))?;
+    parse(&text, path)
+}
+
+fn parse(text: &str, path: &Path) -> Result<ConfigFile> {
+    let file: ConfigFile = toml::from_str(text).map_err(|err| {
+        CompressionError::invalid_input(format!("Invalid {}: {}", path.display(), err))
+    })?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let profiles = file
+        .profiles
+        .into_iter()
+        .map(|(name, profile)| Ok((name, profile.resolve(dir, path)?)))
+        .collect::<Result<_>>()?;
+    Ok(ConfigFile { profiles })
+}
+
+/// `owlsol.toml` in `dir` or its nearest ancestor
+fn project_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+fn user_file() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(base.join("owlsol").join(FILE_NAME)).filter(|path| path.is_file())
+}
+
+/// Expand `~/` and anchor relative paths at the config file's directory
+fn expand(path: &Path, dir: &Path) -> PathBuf {
+    if let Ok(rest) = path.strip_prefix("~") {
+        if let Some(home) = std::env::var_os("HOME") {
+            return Path::new(&home).join(rest);
+        }
+    }
+    dir.join(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_in(dir: &str, text: &str) -> (PathBuf, ConfigFile) {
+        let path = Path::new(dir).join(FILE_NAME);
+        let file = parse(text, &path).unwrap();
+        (path, file)
+    }
+
+    #[test]
+    fn test_profile_merge() {
+        let project = parse_in(
+            "/work",
+            r#"
+            [profiles.default]
+            algorithm = "zstd"
+
+            [profiles.mainnet]
+            commitment = "finalized"
+            keypair = "keys/main.json"
+            "#,
+        );
+        let user = parse_in(
+            "/home/owl/.config/owlsol",
+            r#"
+            [profiles.default]
+            algorithm = "lz4"
+            level = 3
+            format = "json"
+
+            [profiles.mainnet]
+            rpc_url = "https://api.mainnet-beta.solana.com"
+            commitment = "confirmed"
+            "#,
+        );
+        let files = vec![project, user];
+
+        let config = select(files.clone(), None).unwrap();
+        assert_eq!(config.profile, DEFAULT_PROFILE);
+        assert_eq!(config.profiles, ["default", "mainnet"]);
+        assert_eq!(config.settings.algorithm(), "zstd");
+        assert_eq!(config.settings.level, Some(3));
+        assert_eq!(config.settings.rpc_url(), DEVNET_RPC_URL);
+
+        let config = select(files, Some("mainnet")).unwrap();
+        let settings = config.settings;
+        assert_eq!(settings.commitment(), Commitment::Finalized);
+        assert_eq!(settings.rpc_url(), "https://api.mainnet-beta.solana.com");
+        assert_eq!(settings.keypair(), Path::new("/work/keys/main.json"));
+        assert_eq!(settings.algorithm(), "zstd");
+        assert_eq!(settings.format, Some(Format::Json));
+    }
+
+    #[test]
+    fn test_invalid_profiles() {
+        assert!(select(vec![], Some("missing")).is_err());
+        assert!(select(vec![], None).is_ok());
+        assert!(toml::from_str::<ConfigFile>("[profiles.x]\nalgo = \"zstd\"").is_err());
+
+        assert!(parse("[profiles.x]\nalgorithm = \"zip\"", Path::new(FILE_NAME)).is_err());
+        assert!(parse("[profiles.x]\nlevel = \"high\"", Path::new(FILE_NAME)).is_err());
+        assert!(parse("[profiles.x]\nformat = \"csv\"", Path::new(FILE_NAME)).is_err());
+    }
+}
@@ -1,48 +1,52 @@
+use super::output::say;
 use colored::Colorize;
+use owlsol_core::container::FIXED_HEADER_LEN;
+use owlsol_core::rent::{Rent, RentComparison, LAMPORTS_PER_SOL};
 use owlsol_core::{
     analyzer::DataAnalysis, CompressionAlgorithm, CompressionMetadata, CompressionResult,
 };
+use serde_json::{json, Value};
 use std::time::Duration;
 
 pub fn print_compression_result(result: &CompressionResult, elapsed: Duration) {
     let meta = &result.metadata;
 
-    println!("{}", "  Compression Results:".bright_green().bold());
-    println!(
+    say!("{}", "  Compression Results:".bright_green().bold());
+    say!(
         "    {} {}",
         "Algorithm:".bright_white(),
         meta.algorithm.as_str().bright_yellow()
     );
-    println!(
+    say!(
         "    {} {} bytes",
         "Original size:".bright_white(),
         meta.original_size
     );
-    println!(
+    say!(
         "    {} {} bytes",
         "Compressed size:".bright_white(),
         meta.compressed_size
     );
-    println!(
+    say!(
         "    {} {}",
         "Compression ratio:".bright_white(),
         format!("{:.2}%", meta.compression_percentage())
             .bright_green()
             .bold()
     );
-    println!(
+    say!(
         "    {} {} bytes",
         "Space saved:".bright_white(),
         meta.space_saved()
     );
-    println!(
+    say!(
         "    {} {:.2}ms",
         "Time taken:".bright_white(),
         elapsed.as_millis()
     );
 
     if meta.checksum != 0 {
-        println!("    {} {:08x}", "Checksum:".bright_black(), meta.checksum);
+        say!("    {} {:08x}", "Checksum:".bright_black(), meta.checksum);
     }
 }
 
@@ -51,70 +55,141 @@ pub fn print_decompression_result(
     decompressed_size: usize,
     elapsed: Duration,
 ) {
-    println!("{}", "  Decompression Results:".bright_green().bold());
-    println!(
+    say!("{}", "  Decompression Results:".bright_green().bold());
+    say!(
         "    {} {}",
         "Algorithm:".bright_white(),
         meta.algorithm.as_str().bright_yellow()
     );
-    println!(
+    say!(
         "    {} {} bytes",
         "Decompressed size:".bright_white(),
         decompressed_size
     );
-    println!(
+    say!(
         "    {} {:.2}ms",
         "Time taken:".bright_white(),
         elapsed.as_millis()
     );
-    println!("    {} ✓", "Integrity:".bright_white());
+    say!("    {} ✓", "Integrity:".bright_white());
 }
 
-pub fn print_algorithm_stats(algo: &CompressionAlgorithm, meta: &CompressionMetadata) {
-    println!("  {} {}", "▸".bright_blue(), algo.as_str().bright_yellow());
-    println!("    Compressed: {} bytes", meta.compressed_size);
-    println!("    Ratio: {:.2}%", meta.compression_percentage());
-    println!("    Savings: {} bytes", meta.space_saved());
-    println!();
+pub fn print_algorithm_stats(
+    algo: &CompressionAlgorithm,
+    meta: &CompressionMetadata,
+    rent: &RentComparison,
+) {
+    say!("  {} {}", "▸".bright_blue(), algo.as_str().bright_yellow());
+    say!("    Compressed: {} bytes", meta.compressed_size);
+    say!("    Ratio: {:.2}%", meta.compression_percentage());
+    say!("    Savings: {} bytes", meta.space_saved());
+    if rent.is_loss() {
+        say!(
+            "    Rent: {:.6} SOL ({} {:.6} SOL with the header)",
+            sol(rent.stored_lamports),
+            "costs".bright_red(),
+            sol(rent.stored_lamports - rent.original_lamports)
+        );
+    } else {
+        say!(
+            "    Rent: {:.6} SOL (saves {:.6} SOL, {:.2}%)",
+            sol(rent.stored_lamports),
+            sol(rent.original_lamports - rent.stored_lamports),
+            rent.saved_percent()
+        );
+    }
+    say!();
+}
+
+pub fn sol(lamports: u64) -> f64 {
+    lamports as f64 / LAMPORTS_PER_SOL as f64
 }
 
 pub fn print_data_analysis(analysis: &DataAnalysis) {
-    println!("{}", "  Data Analysis:".bright_yellow().bold());
-    println!("    {} {:.2}", "Entropy:".bright_white(), analysis.entropy);
-    println!(
+    say!("{}", "  Data Analysis:".bright_yellow().bold());
+    say!("    {} {:.2}", "Entropy:".bright_white(), analysis.entropy);
+    say!(
         "    {} {}",
         "Unique bytes:".bright_white(),
         analysis.unique_bytes
     );
-    println!(
+    say!(
         "    {} {}",
         "Max run length:".bright_white(),
         analysis.max_run_length
     );
-    println!(
+    say!(
         "    {} {:.2}",
         "Avg run length:".bright_white(),
         analysis.avg_run_length
     );
-    println!(
+    say!(
         "    {} {}",
         "Repeated sequences:".bright_white(),
         analysis.repeated_sequences
     );
 
-    println!();
-    println!("    {} Characteristics:", "Characteristics:".bright_white());
+    say!();
+    say!("    {} Characteristics:", "Characteristics:".bright_white());
 
     if analysis.is_random() {
-        println!("      • {} Random/encrypted data", "⚠".bright_yellow());
+        say!("      • {} Random/encrypted data", "⚠".bright_yellow());
     }
     if analysis.has_runs() {
-        println!("      • {} Contains long runs", "✓".bright_green());
+        say!("      • {} Contains long runs", "✓".bright_green());
     }
     if analysis.has_patterns() {
-        println!("      • {} Has repeated patterns", "✓".bright_green());
+        say!("      • {} Has repeated patterns", "✓".bright_green());
     }
     if analysis.is_text_like() {
-        println!("      • {} Text-like data", "✓".bright_green());
+        say!("      • {} Text-like data", "✓".bright_green());
     }
 }
+
+/// Metadata plus the derived ratio, savings and rent-exempt minimums at the
+/// default rent, for JSON output
+pub fn compression_json(meta: &CompressionMetadata) -> Value {
+    json!({
+        "algorithm": meta.algorithm.as_str(),
+        "original_size": meta.original_size,
+        "compressed_size": meta.compressed_size,
+        "ratio_percent": meta.compression_percentage(),
+        "space_saved": meta.space_saved(),
+        "checksum": format!("{:08x}", meta.checksum),
+        "version": meta.version,
+        "timestamp": meta.timestamp,
+        "rent": rent_json(&Rent::default(), meta.original_size, meta.compressed_size),
+    })
+}
+
+/// Rent-exempt minimum balance for the data raw and compressed behind a
+/// container header; `saved_lamports` is negative when compressing costs more
+pub fn rent_json(rent: &Rent, original_size: u64, compressed_size: u64) -> Value {
+    let comparison = rent.compare(
+        original_size as usize,
+        compressed_size as usize,
+        FIXED_HEADER_LEN,
+    );
+    json!({
+        "original_lamports": comparison.original_lamports,
+        "compressed_lamports": comparison.stored_lamports,
+        "header_bytes": comparison.header_len,
+        "saved_lamports": comparison.saved_lamports(),
+        "saved_percent": comparison.saved_percent(),
+        "break_even_payload": comparison.break_even_payload(),
+    })
+}
+
+pub fn analysis_json(analysis: &DataAnalysis) -> Value {
+    json!({
+        "entropy": analysis.entropy,
+        "unique_bytes": analysis.unique_bytes,
+        "max_run_length": analysis.max_run_length,
+        "avg_run_length": analysis.avg_run_length,
+        "repeated_sequences": analysis.repeated_sequences,
+        "random": analysis.is_random(),
+        "has_runs": analysis.has_runs(),
+        "has_patterns": analysis.has_patterns(),
+        "text_like": analysis.is_text_like(),
+    })
+}
@@ -1,2 +1,3 @@
 pub mod formatter;
+pub mod output;
 pub mod ratatui_ui;
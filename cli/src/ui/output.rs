@@ -0,0 +1,120 @@
+//! Where human-readable output goes
+//!
+//! Reports normally go to stdout. When a command streams data to stdout
+//! (`-c` or `-o -`), they move to stderr so the data stays clean. With
+//! `--format json` the text reports are dropped, progress bars are hidden,
+//! and each command emits a single JSON document instead. `--format csv`
+//! does the same with a table, for commands whose results are rows.
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::time::Duration;
+
+static DATA_ON_STDOUT: AtomicBool = AtomicBool::new(false);
+static FORMAT: AtomicU8 = AtomicU8::new(Format::Text as u8);
+static EMITTED: AtomicBool = AtomicBool::new(false);
+
+/// Output format selected with `--format`
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    clap::ValueEnum,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+    /// Only `benchmark` has rows to put in a table
+    Csv,
+}
+
+pub fn set_format(format: Format) {
+    FORMAT.store(format as u8, Ordering::Relaxed);
+}
+
+pub fn format() -> Format {
+    match FORMAT.load(Ordering::Relaxed) {
+        1 => Format::Json,
+        2 => Format::Csv,
+        _ => Format::Text,
+    }
+}
+
+pub fn is_json() -> bool {
+    format() == Format::Json
+}
+
+/// Whether reports are for people rather than scripts
+pub fn is_text() -> bool {
+    format() == Format::Text
+}
+
+/// Route reports to stderr from now on, because stdout carries data
+pub fn set_data_on_stdout() {
+    DATA_ON_STDOUT.store(true, Ordering::Relaxed);
+}
+
+pub fn data_on_stdout() -> bool {
+    DATA_ON_STDOUT.load(Ordering::Relaxed)
+}
+
+/// Print a command's JSON document: on stdout, or stderr while stdout
+/// carries data
+pub fn emit(document: &serde_json::Value) -> anyhow::Result<()> {
+    let text = serde_json::to_string_pretty(document)?;
+    if data_on_stdout() {
+        eprintln!("{}", text);
+    } else {
+        println!("{}", text);
+    }
+    EMITTED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Whether the command already emitted its document
+pub fn emitted() -> bool {
+    EMITTED.load(Ordering::Relaxed)
+}
+
+/// Hide `bar` unless reporting text; otherwise it draws on stderr as usual
+pub fn progress(bar: ProgressBar) -> ProgressBar {
+    if !is_text() {
+        bar.set_draw_target(ProgressDrawTarget::hidden());
+    }
+    bar
+}
+
+/// A ticking spinner showing `message`
+pub fn spinner(message: impl Into<String>) -> ProgressBar {
+    let spinner = progress(ProgressBar::new_spinner());
+    spinner.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.cyan} {msg}")
+            .unwrap(),
+    );
+    spinner.set_message(message.into());
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    spinner
+}
+
+/// `println!` for reports: stdout, or stderr while stdout carries data, and
+/// nothing in JSON or CSV mode
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if !$crate::ui::output::is_text() {
+        } else if $crate::ui::output::data_on_stdout() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+pub(crate) use say;
@@ -1,29 +1,58 @@
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 use owlsol_core::prelude::*;
+use owlsol_core::synthetic::{AccountKind, Generator};
 
-fn bench_huffman(c: &mut Criterion) {
-    let data = b"hello world hello world hello world";
-    let compressor = Compressor::new();
-    c.bench_function("huffman_compress", |b| {
-        b.iter(|| compressor.compress_with_algorithm(data, Some(CompressionAlgorithm::Huffman)))
-    });
+/// Fixed so runs compare against each other
+const SEED: u64 = 42;
+
+/// Every algorithm compiled into this build
+fn available_algorithms() -> Vec<CompressionAlgorithm> {
+    (0..=u8::MAX)
+        .map_while(CompressionAlgorithm::from_u8)
+        .filter(|algo| algo.is_available() && *algo != CompressionAlgorithm::None)
+        .collect()
 }
 
-fn bench_rle(c: &mut Criterion) {
-    let data = vec![b'A'; 1000];
+fn bench_compress(c: &mut Criterion) {
     let compressor = Compressor::new();
-    c.bench_function("rle_compress", |b| {
-        b.iter(|| compressor.compress_with_algorithm(&data, Some(CompressionAlgorithm::RunLength)))
-    });
+    for kind in AccountKind::ALL {
+        let data = Generator::new(SEED).account(kind);
+        let mut group = c.benchmark_group(format!("compress/{}", kind.as_str()));
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        for algo in available_algorithms() {
+            group.bench_with_input(
+                BenchmarkId::from_parameter(algo.as_str()),
+                &data,
+                |b, data| b.iter(|| compressor.compress_with_algorithm(data, Some(algo))),
+            );
+        }
+        group.bench_with_input(BenchmarkId::from_parameter("auto"), &data, |b, data| {
+            b.iter(|| compressor.compress(data))
+        });
+        group.finish();
+    }
 }
 
-fn bench_dictionary(c: &mut Criterion) {
-    let data = b"test data test data test data test data";
+fn bench_decompress(c: &mut Criterion) {
     let compressor = Compressor::new();
-    c.bench_function("dictionary_compress", |b| {
-        b.iter(|| compressor.compress_with_algorithm(data, Some(CompressionAlgorithm::Dictionary)))
-    });
+    let decompressor = Decompressor::new();
+    for kind in AccountKind::ALL {
+        let data = Generator::new(SEED).account(kind);
+        let mut group = c.benchmark_group(format!("decompress/{}", kind.as_str()));
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        for algo in available_algorithms() {
+            let result = compressor
+                .compress_with_algorithm(&data, Some(algo))
+                .unwrap();
+            group.bench_with_input(
+                BenchmarkId::from_parameter(algo.as_str()),
+                &result,
+                |b, result| b.iter(|| decompressor.decompress(&result.data, &result.metadata)),
+            );
+        }
+        group.finish();
+    }
 }
 
-criterion_group!(benches, bench_huffman, bench_rle, bench_dictionary);
+criterion_group!(benches, bench_compress, bench_decompress);
 criterion_main!(benches);
@@ -1,5 +1,10 @@
-use crate::error::Result;
-use std::collections::{hash_map::Entry, HashMap};
+use crate::error::{CompressionError, DecodeError, Result};
+use crate::metadata::CompressionAlgorithm;
+use crate::utils::read_u32_le;
+use alloc::collections::{btree_map::Entry, BTreeMap};
+use alloc::vec::Vec;
+#[cfg(feature = "serde")]
+use serde::Serialize;
 
 const DICT_MARKER: u8 = 0xFF;
 const MIN_MATCH_LEN: usize = 3; // Only match 3+ bytes
@@ -11,9 +16,12 @@ pub fn compress(data: &[u8]) -> Result<Vec<u8>> {
         return Ok(Vec::new());
     }
 
-    let mut dictionary: HashMap<Vec<u8>, u16> = HashMap::new();
+    let mut dictionary: BTreeMap<Vec<u8>, u16> = BTreeMap::new();
     let mut result = Vec::new();
-    let mut next_id = 0u16;
+    // Ids start at 1: a reference to id 0 would encode as [marker][0x00][0x00],
+    // which the decoder reads as an escaped marker. The table stores each id,
+    // so the decoder still reads version 1 streams that number from 0.
+    let mut next_id = 1u16;
     let mut i = 0;
 
     // Build dictionary as we scan
@@ -78,65 +86,178 @@ pub fn compress(data: &[u8]) -> Result<Vec<u8>> {
 }
 
 pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
-    if data.len() < 4 {
-        return Ok(Vec::new());
-    }
+    let mut result = Vec::new();
+    decode_with(data, |_, bytes| {
+        result.extend_from_slice(bytes);
+        Ok(())
+    })
+    .map_err(|err| CompressionError::corrupted(CompressionAlgorithm::Dictionary, err))?;
+    tracing::trace!(output_len = result.len(), "dictionary decoded");
+    Ok(result)
+}
+
+/// Decode into a caller-supplied buffer without allocating, returning the
+/// number of bytes written
+pub fn decompress_into(data: &[u8], out: &mut [u8]) -> core::result::Result<usize, DecodeError> {
+    let mut written = 0;
+    decode_with(data, |_, bytes| {
+        let end = written + bytes.len();
+        out.get_mut(written..end)
+            .ok_or(DecodeError::OutputOverflow)?
+            .copy_from_slice(bytes);
+        written = end;
+        Ok(())
+    })?;
+    Ok(written)
+}
+
+/// Entry table of an encoded stream: the start offset of each entry's bytes
+/// by id, and the offset of the token stream after it.
+///
+/// Offsets are `u32` (a full table ends within 66KB of the start) to keep
+/// the array at 1KB, since it sits on the 4KB stack frame of an SBF program.
+fn read_entries(
+    data: &[u8],
+) -> core::result::Result<([u32; MAX_DICT_SIZE as usize], usize), DecodeError> {
     // Read dictionary size
-    let dict_size = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
-    let mut dictionary: HashMap<u16, Vec<u8>> = HashMap::new();
+    let dict_size = read_u32_le(data, 0).ok_or(DecodeError::Truncated { offset: 0 })?;
+    // 0 marks a missing entry since entry bytes always follow the 4-byte size
+    // and 3-byte entry header
+    let mut entries = [0u32; MAX_DICT_SIZE as usize];
     let mut pos = 4;
     // Rebuild dictionary
     for _ in 0..dict_size {
-        if pos + 3 > data.len() {
-            break;
-        }
-        let id = u16::from_le_bytes([data[pos], data[pos + 1]]);
-        let len = data[pos + 2] as usize;
+        let Some(&[lo, hi, len]) = data
+            .get(pos..pos + 3)
+            .and_then(|h| <&[u8; 3]>::try_from(h).ok())
+        else {
+            return Err(DecodeError::Truncated { offset: pos });
+        };
+        let id = u16::from_le_bytes([lo, hi]);
+        let len = len as usize;
         pos += 3;
         if pos + len > data.len() {
-            break;
+            return Err(DecodeError::Truncated { offset: pos });
         }
-        dictionary.insert(id, data[pos..pos + len].to_vec());
+        *entries
+            .get_mut(id as usize)
+            .ok_or(DecodeError::InvalidEntry { id })? = pos as u32;
         pos += len;
     }
+    Ok((entries, pos))
+}
+
+/// Walk the encoded stream, calling `emit` with each literal or dictionary
+/// entry, and the entry's id for the latter
+fn decode_with(
+    data: &[u8],
+    mut emit: impl FnMut(Option<u16>, &[u8]) -> core::result::Result<(), DecodeError>,
+) -> core::result::Result<(), DecodeError> {
+    // Empty input is the encoding of empty data
+    if data.is_empty() {
+        return Ok(());
+    }
+    let (entries, mut pos) = read_entries(data)?;
     // Decompress data
-    let mut result = Vec::new();
-    while pos < data.len() {
-        if data[pos] == DICT_MARKER {
-            if pos + 1 < data.len() && data[pos + 1] == 0x00 {
+    while let Some(&byte) = data.get(pos) {
+        if byte != DICT_MARKER {
+            // Literal byte
+            emit(None, &[byte])?;
+            pos += 1;
+            continue;
+        }
+
+        match (data.get(pos + 1), data.get(pos + 2)) {
+            (Some(0x00), _) => {
                 // Escaped marker
-                result.push(DICT_MARKER);
+                emit(None, &[DICT_MARKER])?;
                 pos += 2;
-            } else if pos + 2 < data.len() {
+            }
+            (Some(&lo), Some(&hi)) => {
                 // Dictionary reference
-                let id = u16::from_le_bytes([data[pos + 1], data[pos + 2]]);
-                match dictionary.get(&id) {
-                    Some(sequence) => result.extend_from_slice(sequence),
+                let id = u16::from_le_bytes([lo, hi]);
+                let sequence = entries
+                    .get(id as usize)
+                    .filter(|&&start| start != 0)
+                    .and_then(|&start| {
+                        let start = start as usize;
+                        let len = *data.get(start - 1)? as usize;
+                        data.get(start..start + len)
+                    });
+                match sequence {
+                    Some(sequence) => emit(Some(id), sequence)?,
                     None => {
-                        println!(
-                            "[DICT DECOMPRESS] Missing dictionary entry for id {} at pos {}",
-                            id, pos
-                        );
-                        return Err(crate::error::CompressionError::CorruptedData(format!(
-                            "Missing dictionary entry for id {} at pos {}",
-                            id, pos
-                        )));
+                        tracing::debug!(id, offset = pos, "dictionary: missing entry");
+                        return Err(DecodeError::MissingEntry { id, offset: pos });
                     }
                 }
                 pos += 3;
-            } else {
-                // Incomplete marker, treat as literal
-                result.push(data[pos]);
-                pos += 1;
             }
-        } else {
-            // Literal byte
-            result.push(data[pos]);
-            pos += 1;
+            // A marker is always followed by an escape or a two-byte id
+            _ => return Err(DecodeError::Truncated { offset: pos }),
         }
     }
-    println!("[DICT DECOMPRESS] Output length: {}", result.len());
-    Ok(result)
+
+    Ok(())
+}
+
+/// A dictionary entry and how many times the stream references it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DictionaryEntry {
+    pub id: u16,
+    pub bytes: Vec<u8>,
+    pub uses: usize,
+}
+
+/// Entry table and token counts of an encoded stream
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DictionaryStats {
+    /// Every entry in the table, by id, including unused ones
+    pub entries: Vec<DictionaryEntry>,
+    /// Literal bytes, not counting escaped markers
+    pub literals: usize,
+    pub escaped_markers: usize,
+    pub references: usize,
+}
+
+/// Read the entry table and count how the stream uses it
+pub fn stats(data: &[u8]) -> core::result::Result<DictionaryStats, DecodeError> {
+    let mut stats = DictionaryStats::default();
+    if data.is_empty() {
+        return Ok(stats);
+    }
+
+    let (entries, _) = read_entries(data)?;
+    let mut uses = [0usize; MAX_DICT_SIZE as usize];
+    decode_with(data, |id, bytes| {
+        match (id, bytes) {
+            (Some(id), _) => {
+                uses[id as usize] += 1;
+                stats.references += 1;
+            }
+            (None, [DICT_MARKER]) => stats.escaped_markers += 1,
+            (None, _) => stats.literals += 1,
+        }
+        Ok(())
+    })?;
+
+    stats.entries = entries
+        .iter()
+        .enumerate()
+        .filter(|(_, &start)| start != 0)
+        .map(|(id, &start)| {
+            let start = start as usize;
+            let len = data[start - 1] as usize;
+            DictionaryEntry {
+                id: id as u16,
+                bytes: data[start..start + len].to_vec(),
+                uses: uses[id],
+            }
+        })
+        .collect();
+    Ok(stats)
 }
 
 #[cfg(test)]
@@ -190,6 +311,84 @@ mod tests {
         assert_eq!(data.to_vec(), decompressed);
     }
 
+    #[test]
+    fn test_decompress_into() {
+        let data = b"owl owl owl owl owl";
+        let compressed = compress(data).unwrap();
+
+        let mut out = [0u8; 32];
+        let written = decompress_into(&compressed, &mut out).unwrap();
+        assert_eq!(&out[..written], data);
+
+        let mut small = [0u8; 8];
+        assert_eq!(
+            decompress_into(&compressed, &mut small),
+            Err(DecodeError::OutputOverflow)
+        );
+    }
+
+    #[test]
+    fn test_missing_entry() {
+        // Empty dictionary followed by a reference to id 7
+        let data = [0, 0, 0, 0, DICT_MARKER, 7, 0];
+        assert_eq!(
+            decompress_into(&data, &mut [0u8; 8]),
+            Err(DecodeError::MissingEntry { id: 7, offset: 4 })
+        );
+        assert!(decompress(&data).is_err());
+    }
+
+    #[test]
+    fn test_truncated_input() {
+        let compressed = compress(b"owl owl owl \xFF").unwrap();
+        let mut out = [0u8; 32];
+        // Inside the dictionary size, then inside the first entry header
+        for (len, offset) in [(3, 0), (6, 4)] {
+            assert_eq!(
+                decompress_into(&compressed[..len], &mut out),
+                Err(DecodeError::Truncated { offset })
+            );
+        }
+        // Dangling marker left by cutting the escaped 0xFF in half
+        assert_eq!(
+            decompress_into(&compressed[..compressed.len() - 1], &mut out),
+            Err(DecodeError::Truncated {
+                offset: compressed.len() - 2
+            })
+        );
+        assert!(decompress(&compressed[..3]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_entry_id() {
+        // One entry with id 300, beyond MAX_DICT_SIZE
+        let data = [1, 0, 0, 0, 0x2C, 0x01, 3, b'o', b'w', b'l'];
+        assert_eq!(
+            decompress_into(&data, &mut [0u8; 8]),
+            Err(DecodeError::InvalidEntry { id: 300 })
+        );
+    }
+
+    #[test]
+    fn test_zero_runs() {
+        // The first entry is all zeros, so its reference must not look like
+        // an escaped marker
+        let data = vec![0u8; 156];
+        let compressed = compress(&data).unwrap();
+        assert_eq!(decompress(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_ids_from_zero() {
+        // Version 1 encoders numbered entries from 0; only a reference to id
+        // 0 itself was ambiguous
+        let mut data = 2u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&[0, 0, 3, b'a', b'b', b'c']);
+        data.extend_from_slice(&[1, 0, 3, b'x', b'y', b'z']);
+        data.extend_from_slice(&[b'q', DICT_MARKER, 1, 0, DICT_MARKER, 0x00]);
+        assert_eq!(decompress(&data).unwrap(), b"qxyz\xFF");
+    }
+
     #[test]
     fn test_long_sequences() {
         let sequence = b"ABCDEFGH";
@@ -199,4 +398,21 @@ mod tests {
         assert_eq!(data, decompressed);
         assert!(compressed.len() < data.len());
     }
+
+    #[test]
+    fn test_stats() {
+        let data = b"abcabcabc\xff";
+        let compressed = compress(data).unwrap();
+        let stats = stats(&compressed).unwrap();
+
+        assert_eq!((stats.literals, stats.escaped_markers), (3, 1));
+        // The longest match wins: one reference to "abcabc"
+        assert_eq!(stats.references, 1);
+        let used = stats.entries.iter().find(|e| e.uses > 0).unwrap();
+        assert_eq!(used.bytes, b"abcabc");
+        assert_eq!(
+            stats.entries.iter().map(|e| e.uses).sum::<usize>(),
+            stats.references
+        );
+    }
 }
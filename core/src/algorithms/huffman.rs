@@ -1,7 +1,11 @@
-use crate::error::{CompressionError, Result};
+use crate::error::{CompressionError, DecodeError, Result};
+use crate::metadata::CompressionAlgorithm;
 use crate::utils::bitstream::{BitReader, BitWriter};
-use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap};
+use crate::utils::read_u32_le;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BinaryHeap};
+use alloc::{format, vec::Vec};
+use core::cmp::Ordering;
 
 #[derive(Debug, Clone)]
 struct HuffmanNode {
@@ -57,14 +61,14 @@ impl Ord for HuffmanNode {
 }
 
 pub struct HuffmanCodec {
-    codes: HashMap<u8, Vec<bool>>,
+    codes: BTreeMap<u8, Vec<bool>>,
     tree: Option<Box<HuffmanNode>>,
 }
 
 impl HuffmanCodec {
     pub fn new() -> Self {
         Self {
-            codes: HashMap::new(),
+            codes: BTreeMap::new(),
             tree: None,
         }
     }
@@ -75,7 +79,7 @@ impl HuffmanCodec {
         }
 
         // Calculate frequency distribution
-        let mut freqs = HashMap::new();
+        let mut freqs = BTreeMap::new();
         for &byte in data {
             *freqs.entry(byte).or_insert(0u64) += 1;
         }
@@ -92,7 +96,7 @@ impl HuffmanCodec {
         Ok(())
     }
 
-    fn build_tree(freqs: HashMap<u8, u64>) -> Result<Box<HuffmanNode>> {
+    fn build_tree(freqs: BTreeMap<u8, u64>) -> Result<Box<HuffmanNode>> {
         let mut heap = BinaryHeap::new();
 
         for (byte, freq) in freqs {
@@ -118,7 +122,7 @@ impl HuffmanCodec {
         Ok(Box::new(heap.pop().unwrap()))
     }
 
-    fn generate_codes(node: &HuffmanNode, prefix: Vec<bool>, codes: &mut HashMap<u8, Vec<bool>>) {
+    fn generate_codes(node: &HuffmanNode, prefix: Vec<bool>, codes: &mut BTreeMap<u8, Vec<bool>>) {
         if node.is_leaf() {
             if let Some(value) = node.value {
                 codes.insert(value, prefix);
@@ -142,7 +146,10 @@ impl HuffmanCodec {
 
         for &byte in data {
             let code = self.codes.get(&byte).ok_or_else(|| {
-                CompressionError::compression_failed(format!("No code for byte {}", byte))
+                CompressionError::compression_failed(
+                    CompressionAlgorithm::Huffman,
+                    format!("No code for byte {}", byte),
+                )
             })?;
 
             writer.write_bits(code);
@@ -152,13 +159,17 @@ impl HuffmanCodec {
     }
 
     pub fn decode(&self, encoded: &[u8], original_len: usize) -> Result<Vec<u8>> {
-        let tree = self
-            .tree
-            .as_ref()
-            .ok_or_else(|| CompressionError::decompression_failed("No Huffman tree available"))?;
+        let tree = self.tree.as_ref().ok_or_else(|| {
+            CompressionError::decompression_failed(
+                CompressionAlgorithm::Huffman,
+                "No Huffman tree available",
+            )
+        })?;
 
         let mut reader = BitReader::new(encoded);
-        let mut decoded = Vec::with_capacity(original_len);
+        // Every symbol but a lone root leaf costs at least one bit, so don't
+        // trust `original_len` for the allocation
+        let mut decoded = Vec::with_capacity(original_len.min(encoded.len().saturating_mul(8)));
 
         while decoded.len() < original_len {
             let mut current = tree.as_ref();
@@ -178,38 +189,41 @@ impl HuffmanCodec {
                         } else {
                             current.left.as_ref()
                         }
-                        .ok_or_else(|| {
-                            CompressionError::decompression_failed("Invalid tree structure")
-                        })?;
+                        .ok_or(CompressionError::corrupted(
+                            CompressionAlgorithm::Huffman,
+                            DecodeError::InvalidTree,
+                        ))?;
                     }
                     None => {
-                        println!(
-                            "[HUFFMAN DECODE] Unexpected end of data at decoded len {}",
-                            decoded.len()
+                        tracing::debug!(
+                            decoded_len = decoded.len(),
+                            "huffman: unexpected end of data"
                         );
-                        return Err(CompressionError::decompression_failed(
-                            "Unexpected end of data",
+                        return Err(CompressionError::corrupted(
+                            CompressionAlgorithm::Huffman,
+                            DecodeError::Truncated {
+                                offset: encoded.len(),
+                            },
                         ));
                     }
                 }
             }
             if decoded.len() == start_len {
-                println!(
-                    "[HUFFMAN DECODE] No progress at decoded len {}",
-                    decoded.len()
-                );
+                tracing::debug!(decoded_len = decoded.len(), "huffman: no progress");
                 break;
             }
         }
-        println!("[HUFFMAN DECODE] Output length: {}", decoded.len());
+        tracing::trace!(output_len = decoded.len(), "huffman decoded");
         Ok(decoded)
     }
 
     pub fn serialize_tree(&self) -> Result<Vec<u8>> {
-        let tree = self
-            .tree
-            .as_ref()
-            .ok_or_else(|| CompressionError::compression_failed("No tree to serialize"))?;
+        let tree = self.tree.as_ref().ok_or_else(|| {
+            CompressionError::compression_failed(
+                CompressionAlgorithm::Huffman,
+                "No tree to serialize",
+            )
+        })?;
 
         let mut bytes = Vec::new();
         Self::serialize_node(tree, &mut bytes);
@@ -235,7 +249,10 @@ impl HuffmanCodec {
 
     pub fn deserialize_tree(&mut self, data: &[u8]) -> Result<()> {
         let mut pos = 0;
-        self.tree = Some(Box::new(Self::deserialize_node(data, &mut pos)?));
+        let mut nodes = 0;
+        self.tree = Some(Box::new(Self::deserialize_node(
+            data, &mut pos, 0, &mut nodes,
+        )?));
         self.codes.clear();
         if let Some(ref tree) = self.tree {
             Self::generate_codes(tree, Vec::new(), &mut self.codes);
@@ -243,26 +260,35 @@ impl HuffmanCodec {
         Ok(())
     }
 
-    fn deserialize_node(data: &[u8], pos: &mut usize) -> Result<HuffmanNode> {
-        if *pos >= data.len() {
-            return Err(CompressionError::decompression_failed(
-                "Incomplete tree data",
+    fn deserialize_node(
+        data: &[u8],
+        pos: &mut usize,
+        depth: usize,
+        nodes: &mut usize,
+    ) -> Result<HuffmanNode> {
+        // Bound the recursion by what a real tree over 256 symbols can need
+        *nodes += 1;
+        if depth > MAX_DEPTH || *nodes > MAX_NODES {
+            return Err(CompressionError::corrupted(
+                CompressionAlgorithm::Huffman,
+                DecodeError::InvalidTree,
             ));
         }
-        let is_leaf = data[*pos];
+        let is_leaf = *data.get(*pos).ok_or(CompressionError::corrupted(
+            CompressionAlgorithm::Huffman,
+            DecodeError::Truncated { offset: *pos },
+        ))?;
         *pos += 1;
         if is_leaf == 1 {
-            if *pos >= data.len() {
-                return Err(CompressionError::decompression_failed(
-                    "Incomplete leaf node",
-                ));
-            }
-            let value = data[*pos];
+            let value = *data.get(*pos).ok_or(CompressionError::corrupted(
+                CompressionAlgorithm::Huffman,
+                DecodeError::Truncated { offset: *pos },
+            ))?;
             *pos += 1;
             Ok(HuffmanNode::leaf(value, 0))
         } else {
-            let left = Box::new(Self::deserialize_node(data, pos)?);
-            let right = Box::new(Self::deserialize_node(data, pos)?);
+            let left = Box::new(Self::deserialize_node(data, pos, depth + 1, nodes)?);
+            let right = Box::new(Self::deserialize_node(data, pos, depth + 1, nodes)?);
             Ok(HuffmanNode::internal(left, right))
         }
     }
@@ -271,6 +297,13 @@ impl HuffmanCodec {
         self.codes.len()
     }
 
+    /// Code length in bits of every symbol in the tree
+    pub fn code_lengths(&self) -> impl Iterator<Item = (u8, usize)> + '_ {
+        self.codes
+            .iter()
+            .map(|(&symbol, code)| (symbol, code.len()))
+    }
+
     pub fn get_avg_code_length(&self) -> f64 {
         if self.codes.is_empty() {
             return 0.0;
@@ -286,6 +319,127 @@ impl Default for HuffmanCodec {
     }
 }
 
+/// Upper bound on nodes in a tree over 256 symbols
+const MAX_NODES: usize = 2 * 256 - 1;
+/// Deepest leaf in a tree over 256 symbols
+const MAX_DEPTH: usize = 255;
+/// Marks a leaf in `HuffmanTable::nodes`; the low byte holds the symbol
+const LEAF: u16 = 0x8000;
+/// End of the pending-node chain while parsing
+const NO_NODE: u16 = u16::MAX;
+
+/// Flattened Huffman tree for allocation-free decoding.
+///
+/// Nodes are kept in the serialized pre-order, so a left child always follows
+/// its parent and only the right child's index is stored: each node is that
+/// index, or `LEAF | symbol` for a leaf. At one `u16` per node the table stays
+/// around 1KB, well inside the 4KB stack frame of an SBF program. Parsing is
+/// iterative, so untrusted trees cannot overflow the stack.
+pub struct HuffmanTable {
+    nodes: [u16; MAX_NODES],
+    len: usize,
+}
+
+impl HuffmanTable {
+    /// Parse a tree written by `HuffmanCodec::serialize_tree`
+    pub fn parse(tree: &[u8]) -> core::result::Result<Self, DecodeError> {
+        let mut table = Self {
+            nodes: [0; MAX_NODES],
+            len: 0,
+        };
+        // Internal nodes still waiting for their right child, chained through
+        // their own slot
+        let mut pending = NO_NODE;
+        let mut pos = 0;
+
+        loop {
+            let index = table.len;
+            let marker = *tree
+                .get(pos)
+                .ok_or(DecodeError::Truncated { offset: pos })?;
+            pos += 1;
+
+            let node = table.nodes.get_mut(index).ok_or(DecodeError::InvalidTree)?;
+            table.len += 1;
+
+            if marker != 1 {
+                *node = pending;
+                pending = index as u16;
+                continue;
+            }
+
+            let value = *tree
+                .get(pos)
+                .ok_or(DecodeError::Truncated { offset: pos })?;
+            pos += 1;
+            *node = LEAF | value as u16;
+
+            // The next node is the right child of the innermost pending node
+            if pending == NO_NODE {
+                return Ok(table);
+            }
+            let parent = table
+                .nodes
+                .get_mut(pending as usize)
+                .ok_or(DecodeError::InvalidTree)?;
+            pending = *parent;
+            *parent = table.len as u16;
+        }
+    }
+
+    /// Decode exactly `out.len()` symbols from `encoded`
+    pub fn decode_into(
+        &self,
+        encoded: &[u8],
+        out: &mut [u8],
+    ) -> core::result::Result<(), DecodeError> {
+        let mut bit_pos = 0usize;
+
+        for slot in out.iter_mut() {
+            let mut node = 0usize;
+            loop {
+                let entry = *self
+                    .nodes
+                    .get(node)
+                    .filter(|_| node < self.len)
+                    .ok_or(DecodeError::InvalidTree)?;
+                if entry & LEAF != 0 {
+                    *slot = entry as u8;
+                    break;
+                }
+
+                let byte = *encoded.get(bit_pos / 8).ok_or(DecodeError::Truncated {
+                    offset: bit_pos / 8,
+                })?;
+                let bit = (byte >> (7 - bit_pos % 8)) & 1 == 1;
+                bit_pos += 1;
+                node = if bit { entry as usize } else { node + 1 };
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Decode a `[tree_size(4)][tree][encoded]` payload into a caller-supplied
+/// buffer holding exactly the original data
+pub fn decompress_into(data: &[u8], out: &mut [u8]) -> core::result::Result<(), DecodeError> {
+    let tree_size = read_u32_le(data, 0).ok_or(DecodeError::Truncated { offset: 0 })? as usize;
+    let tree_end = 4usize
+        .checked_add(tree_size)
+        .ok_or(DecodeError::Truncated { offset: 4 })?;
+    let tree = data
+        .get(4..tree_end)
+        .ok_or(DecodeError::Truncated { offset: 4 })?;
+    let encoded = &data[tree_end..];
+
+    // Report offsets relative to the whole payload
+    HuffmanTable::parse(tree)
+        .map_err(|err| err.shifted(4))?
+        .decode_into(encoded, out)
+        .map_err(|err| err.shifted(tree_end))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -314,6 +468,67 @@ mod tests {
         assert_eq!(data, decoded.as_slice());
     }
 
+    #[test]
+    fn test_table_decode() {
+        let data = b"the quick brown fox jumps over the lazy dog";
+        let mut codec = HuffmanCodec::new();
+        codec.build_from_data(data).unwrap();
+
+        let tree = codec.serialize_tree().unwrap();
+        let encoded = codec.encode(data).unwrap();
+        let mut out = [0u8; 43];
+        HuffmanTable::parse(&tree)
+            .unwrap()
+            .decode_into(&encoded, &mut out)
+            .unwrap();
+        assert_eq!(&out[..], data);
+
+        let mut longer = [0u8; 64];
+        assert_eq!(
+            HuffmanTable::parse(&tree)
+                .unwrap()
+                .decode_into(&encoded, &mut longer),
+            Err(DecodeError::Truncated {
+                offset: encoded.len()
+            })
+        );
+    }
+
+    #[test]
+    fn test_table_rejects_bad_trees() {
+        assert_eq!(
+            HuffmanTable::parse(&[0, 1]).err(),
+            Some(DecodeError::Truncated { offset: 2 })
+        );
+        // A chain of internal nodes deeper than any valid tree
+        let deep = vec![0u8; 4096];
+        assert_eq!(
+            HuffmanTable::parse(&deep).err(),
+            Some(DecodeError::InvalidTree)
+        );
+    }
+
+    #[test]
+    fn test_table_fits_sbf_stack() {
+        // SBF frames are 4KB, and the table lives in the decoder's frame
+        assert!(core::mem::size_of::<HuffmanTable>() <= 1024 + 8);
+    }
+
+    #[test]
+    fn test_deserialize_rejects_deep_trees() {
+        // A chain of internal nodes far deeper than any real tree
+        let mut codec = HuffmanCodec::new();
+        assert!(codec.deserialize_tree(&vec![0u8; 1 << 20]).is_err());
+
+        // 256 internal nodes down the left spine with a leaf at each right
+        let mut spine = vec![0u8; 256];
+        for _ in 0..257 {
+            spine.extend_from_slice(&[1, b'x']);
+        }
+        assert!(codec.deserialize_tree(&spine).is_err());
+        assert!(codec.deserialize_tree(&spine[255..]).is_ok());
+    }
+
     #[test]
     fn test_tree_serialization() {
         let data = b"test data";
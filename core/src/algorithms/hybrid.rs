@@ -0,0 +1,195 @@
+use crate::error::{CompressionError, DecodeError, Result};
+use crate::metadata::CompressionAlgorithm;
+use crate::utils::read_u32_le;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Largest block of dense data that is compressed as a single segment
+pub const MAX_SEGMENT_SIZE: usize = 4096;
+/// Runs of a single byte at least this long get a segment of their own
+pub const MIN_RUN_SEGMENT: usize = 64;
+/// Size of one segment table entry: [algorithm(1)][original_len(4)][compressed_len(4)]
+pub const SEGMENT_ENTRY_SIZE: usize = 9;
+
+/// One entry of the hybrid segment table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    pub algorithm: CompressionAlgorithm,
+    pub original_len: u32,
+    pub compressed_len: u32,
+}
+
+/// Split data into segments at content boundaries.
+///
+/// Long single-byte runs (such as zero-filled account tails) become their own
+/// segments; everything in between is cut into blocks of at most
+/// `MAX_SEGMENT_SIZE` bytes.
+pub fn split(data: &[u8]) -> Vec<Range<usize>> {
+    let mut segments = Vec::new();
+    let mut dense_start = 0;
+    let mut i = 0;
+
+    while i < data.len() {
+        let mut run = 1;
+        while i + run < data.len() && data[i + run] == data[i] {
+            run += 1;
+        }
+
+        if run >= MIN_RUN_SEGMENT {
+            push_dense(&mut segments, dense_start..i);
+            segments.push(i..i + run);
+            dense_start = i + run;
+        }
+        i += run;
+    }
+    push_dense(&mut segments, dense_start..data.len());
+
+    segments
+}
+
+fn push_dense(segments: &mut Vec<Range<usize>>, range: Range<usize>) {
+    let mut start = range.start;
+    while start < range.end {
+        let end = (start + MAX_SEGMENT_SIZE).min(range.end);
+        segments.push(start..end);
+        start = end;
+    }
+}
+
+/// Serialize compressed segments.
+///
+/// Format: [segment_count(4)][segment table][payload 0][payload 1]...
+pub fn encode(segments: &[(CompressionAlgorithm, usize, Vec<u8>)]) -> Result<Vec<u8>> {
+    let payload_len: usize = segments.iter().map(|(_, _, payload)| payload.len()).sum();
+    let mut result = Vec::with_capacity(4 + segments.len() * SEGMENT_ENTRY_SIZE + payload_len);
+
+    result.extend_from_slice(&(segments.len() as u32).to_le_bytes());
+    for (algorithm, original_len, payload) in segments {
+        if *algorithm == CompressionAlgorithm::Hybrid {
+            return Err(CompressionError::invalid_input(
+                "Hybrid segments cannot be nested",
+            ));
+        }
+        result.push(algorithm.to_u8());
+        result.extend_from_slice(&(*original_len as u32).to_le_bytes());
+        result.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    }
+    for (_, _, payload) in segments {
+        result.extend_from_slice(payload);
+    }
+
+    Ok(result)
+}
+
+/// Parse the segment table, returning the entries and the offset of the first payload
+pub fn read_table(data: &[u8]) -> Result<(Vec<Segment>, usize)> {
+    let segments = Segments::parse(data)
+        .map_err(|err| CompressionError::corrupted(CompressionAlgorithm::Hybrid, err))?;
+    let offset = data.len() - segments.payloads.len();
+    Ok((segments.map(|(segment, _)| segment).collect(), offset))
+}
+
+/// Allocation-free iterator over validated segments and their payloads
+#[derive(Debug, Clone)]
+pub struct Segments<'a> {
+    table: &'a [u8],
+    payloads: &'a [u8],
+}
+
+impl<'a> Segments<'a> {
+    /// Validate the segment table against the payload area
+    pub fn parse(data: &'a [u8]) -> core::result::Result<Self, DecodeError> {
+        let count = read_u32_le(data, 0).ok_or(DecodeError::Truncated { offset: 0 })? as usize;
+        let table_end = count
+            .checked_mul(SEGMENT_ENTRY_SIZE)
+            .and_then(|len| len.checked_add(4))
+            .ok_or(DecodeError::Truncated { offset: 4 })?;
+        let table = data
+            .get(4..table_end)
+            .ok_or(DecodeError::Truncated { offset: 4 })?;
+        let payloads = data.get(table_end..).unwrap_or_default();
+
+        let mut payload_len = 0usize;
+        for (index, entry) in table.chunks_exact(SEGMENT_ENTRY_SIZE).enumerate() {
+            let segment = Self::entry(entry).ok_or(DecodeError::InvalidSegment { index })?;
+            payload_len = payload_len.saturating_add(segment.compressed_len as usize);
+        }
+        if payload_len != payloads.len() {
+            return Err(DecodeError::InvalidSegmentTable);
+        }
+
+        Ok(Self { table, payloads })
+    }
+
+    fn entry(entry: &[u8]) -> Option<Segment> {
+        let algorithm = CompressionAlgorithm::from_u8(*entry.first()?)
+            .filter(|algo| *algo != CompressionAlgorithm::Hybrid)?;
+        Some(Segment {
+            algorithm,
+            original_len: read_u32_le(entry, 1)?,
+            compressed_len: read_u32_le(entry, 5)?,
+        })
+    }
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = (Segment, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (entry, table) = self.table.split_at_checked(SEGMENT_ENTRY_SIZE)?;
+        let segment = Self::entry(entry)?;
+        let (payload, payloads) = self
+            .payloads
+            .split_at_checked(segment.compressed_len as usize)?;
+
+        self.table = table;
+        self.payloads = payloads;
+        Some((segment, payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_header_and_zero_tail() {
+        let mut data = b"header bytes with some variety 0123456789".to_vec();
+        data.extend(vec![0u8; 1000]);
+
+        let segments = split(&data);
+        assert_eq!(segments, vec![0..41, 41..1041]);
+    }
+
+    #[test]
+    fn test_split_large_dense_block() {
+        let data: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+        let segments = split(&data);
+
+        assert_eq!(segments.len(), 3);
+        assert!(segments.iter().all(|r| r.len() <= MAX_SEGMENT_SIZE));
+        assert_eq!(segments.last().unwrap().end, data.len());
+    }
+
+    #[test]
+    fn test_table_roundtrip() {
+        let segments = vec![
+            (CompressionAlgorithm::Huffman, 10, vec![1, 2, 3]),
+            (CompressionAlgorithm::RunLength, 500, vec![0xFF, 0, 255]),
+        ];
+        let encoded = encode(&segments).unwrap();
+        let (table, offset) = read_table(&encoded).unwrap();
+
+        assert_eq!(offset, 4 + 2 * SEGMENT_ENTRY_SIZE);
+        assert_eq!(table[0].algorithm, CompressionAlgorithm::Huffman);
+        assert_eq!(table[1].original_len, 500);
+        assert_eq!(table[1].compressed_len, 3);
+    }
+
+    #[test]
+    fn test_truncated_table() {
+        let encoded = encode(&[(CompressionAlgorithm::None, 3, vec![1, 2, 3])]).unwrap();
+        assert!(read_table(&encoded[..encoded.len() - 1]).is_err());
+        assert!(read_table(&encoded[..6]).is_err());
+    }
+}
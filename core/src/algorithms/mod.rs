@@ -1,5 +1,6 @@
 pub mod dictionary;
 pub mod huffman;
+pub mod hybrid;
 pub mod rle;
 
 pub use dictionary::{compress as dict_compress, decompress as dict_decompress};
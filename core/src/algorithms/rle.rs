@@ -1,4 +1,8 @@
-use crate::error::Result;
+use crate::error::{CompressionError, DecodeError, Result};
+use crate::metadata::CompressionAlgorithm;
+use alloc::vec::Vec;
+#[cfg(feature = "serde")]
+use serde::Serialize;
 
 const RLE_MARKER: u8 = 0xFF;
 const RLE_ESCAPE: u8 = 0x00;
@@ -21,11 +25,14 @@ pub fn compress(data: &[u8]) -> Result<Vec<u8>> {
             count += 1;
         }
 
-        // Use RLE encoding if run is long enough
+        // Use RLE encoding if run is long enough.
+        // Format: [marker][count][byte]; count is never RLE_ESCAPE. Format
+        // version 1 wrote [marker][byte][count], which made a run of zeros
+        // read back as an escaped marker.
         if count >= MIN_RUN_LENGTH {
             result.push(RLE_MARKER);
-            result.push(current);
             result.push(count as u8);
+            result.push(current);
             i += count;
         } else {
             // Handle marker byte specially
@@ -44,34 +51,109 @@ pub fn compress(data: &[u8]) -> Result<Vec<u8>> {
 
 pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
     let mut result = Vec::with_capacity(data.len() * 2);
+    decode_runs(data, false, |byte, count| {
+        // More concise and efficient than repeat().take()
+        let new_len = result.len() + count;
+        result.resize(new_len, byte);
+        Ok(())
+    })
+    .map_err(|err| CompressionError::corrupted(CompressionAlgorithm::RunLength, err))?;
+    Ok(result)
+}
+
+/// Decode into a caller-supplied buffer without allocating, returning the
+/// number of bytes written
+pub fn decompress_into(data: &[u8], out: &mut [u8]) -> core::result::Result<usize, DecodeError> {
+    fill_runs(data, false, out)
+}
+
+/// [`decompress_into`] for payloads written with format version 1, whose
+/// runs are `[marker][byte][count]`
+pub fn decompress_v1_into(data: &[u8], out: &mut [u8]) -> core::result::Result<usize, DecodeError> {
+    fill_runs(data, true, out)
+}
+
+fn fill_runs(data: &[u8], v1: bool, out: &mut [u8]) -> core::result::Result<usize, DecodeError> {
+    let mut written = 0;
+    decode_runs(data, v1, |byte, count| {
+        let end = written + count;
+        out.get_mut(written..end)
+            .ok_or(DecodeError::OutputOverflow)?
+            .fill(byte);
+        written = end;
+        Ok(())
+    })?;
+    Ok(written)
+}
+
+/// Walk the encoded tokens, calling `emit(byte, count)` for each literal or
+/// run; `v1` reads runs in the version 1 order
+fn decode_runs(
+    data: &[u8],
+    v1: bool,
+    mut emit: impl FnMut(u8, usize) -> core::result::Result<(), DecodeError>,
+) -> core::result::Result<(), DecodeError> {
     let mut i = 0;
 
-    while i < data.len() {
-        if data[i] == RLE_MARKER {
-            if i + 1 < data.len() && data[i + 1] == RLE_ESCAPE {
+    while let Some(&byte) = data.get(i) {
+        if byte != RLE_MARKER {
+            emit(byte, 1)?;
+            i += 1;
+            continue;
+        }
+
+        match (data.get(i + 1), data.get(i + 2)) {
+            (Some(&RLE_ESCAPE), _) => {
                 // Escaped marker
-                result.push(RLE_MARKER);
+                emit(RLE_MARKER, 1)?;
                 i += 2;
-            } else if i + 2 < data.len() {
+            }
+            (Some(&first), Some(&second)) => {
                 // RLE sequence
-                let byte = data[i + 1];
-                let count = data[i + 2] as usize;
-                // More concise and efficient than repeat().take()
-                let new_len = result.len() + count;
-                result.resize(new_len, byte);
+                let (count, value) = if v1 { (second, first) } else { (first, second) };
+                emit(value, count as usize)?;
                 i += 3;
-            } else {
-                // Incomplete sequence, treat as literal
-                result.push(data[i]);
-                i += 1;
             }
-        } else {
-            result.push(data[i]);
-            i += 1;
+            // The encoder always follows a marker with an escape or a full
+            // run, so a dangling marker means the input was cut short
+            _ => return Err(DecodeError::Truncated { offset: i }),
         }
     }
 
-    Ok(result)
+    Ok(())
+}
+
+/// Token counts of an encoded stream
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct RleStats {
+    /// Literal bytes, not counting escaped markers
+    pub literals: usize,
+    pub escaped_markers: usize,
+    pub runs: usize,
+    /// Bytes produced by runs
+    pub run_bytes: usize,
+    pub longest_run: usize,
+}
+
+/// Count the literals, escapes and runs in an encoded stream
+pub fn stats(data: &[u8]) -> core::result::Result<RleStats, DecodeError> {
+    let mut stats = RleStats::default();
+    // The encoder only emits runs of MIN_RUN_LENGTH or more, so a single
+    // marker byte is an escape
+    decode_runs(data, false, |byte, count| {
+        match (byte, count) {
+            (RLE_MARKER, 1) => stats.escaped_markers += 1,
+            (_, 1) => stats.literals += 1,
+            _ => {
+                stats.runs += 1;
+                stats.run_bytes += count;
+                stats.longest_run = stats.longest_run.max(count);
+            }
+        }
+        Ok(())
+    })?;
+    Ok(stats)
 }
 
 #[cfg(test)]
@@ -103,6 +185,56 @@ mod tests {
         assert_eq!(data, decompressed.as_slice());
     }
 
+    #[test]
+    fn test_zero_runs() {
+        let mut data = vec![0u8; 600];
+        data.extend_from_slice(b"\x00\xFF\x00\x00\x00\x01");
+        let compressed = compress(&data).unwrap();
+        let decompressed = decompress(&compressed).unwrap();
+        assert_eq!(data, decompressed);
+    }
+
+    #[test]
+    fn test_v1_layout() {
+        // "aaaa" as format version 1 wrote it, then an escaped marker
+        let v1 = [RLE_MARKER, b'a', 4, RLE_MARKER, RLE_ESCAPE, b'b'];
+        let mut out = [0u8; 6];
+        assert_eq!(decompress_v1_into(&v1, &mut out), Ok(6));
+        assert_eq!(&out, b"aaaa\xFFb");
+
+        let data = b"bbbbbbc";
+        let compressed = compress(data).unwrap();
+        assert_eq!(&compressed[..3], &[RLE_MARKER, 6, b'b']);
+    }
+
+    #[test]
+    fn test_decompress_into() {
+        let data = b"xxxxxxxxyz\xFFabc";
+        let compressed = compress(data).unwrap();
+
+        let mut out = [0u8; 14];
+        assert_eq!(decompress_into(&compressed, &mut out), Ok(data.len()));
+        assert_eq!(&out[..], data);
+
+        let mut small = [0u8; 4];
+        assert_eq!(
+            decompress_into(&compressed, &mut small),
+            Err(DecodeError::OutputOverflow)
+        );
+    }
+
+    #[test]
+    fn test_truncated_run() {
+        let compressed = compress(&[5u8; 10]).unwrap();
+        for len in 1..compressed.len() {
+            assert_eq!(
+                decompress_into(&compressed[..len], &mut [0u8; 16]),
+                Err(DecodeError::Truncated { offset: 0 })
+            );
+            assert!(decompress(&compressed[..len]).is_err());
+        }
+    }
+
     #[test]
     fn test_long_run() {
         let data = vec![b'A'; 200];
@@ -111,4 +243,20 @@ mod tests {
         assert_eq!(data, decompressed);
         assert!(compressed.len() < 10);
     }
+
+    #[test]
+    fn test_stats() {
+        let data = [b"ab".as_slice(), &[0u8; 10], &[RLE_MARKER], &[7u8; 300]].concat();
+        let stats = stats(&compress(&data).unwrap()).unwrap();
+        assert_eq!(
+            stats,
+            RleStats {
+                literals: 2,
+                escaped_markers: 1,
+                runs: 3,
+                run_bytes: 310,
+                longest_run: 255,
+            }
+        );
+    }
 }
@@ -0,0 +1,410 @@
+//! `.owlpack` multi-entry archives
+//!
+//! Each entry is compressed on its own and written back to back; a central
+//! index at the end records every entry's name, offset and metadata, so a
+//! single entry can be extracted by seeking straight to it.
+//!
+//! ```text
+//! [magic "OWLP"][archive version]
+//! [entry payloads...]
+//! [index: entry count u32, then per entry
+//!   name length u16, name (UTF-8), offset u64, algorithm, metadata version,
+//!   original_size u64, compressed_size u64, checksum u32, timestamp i64]
+//! [index offset u64][index length u32][index checksum u32][magic "OWLP"]
+//! ```
+
+use crate::compressor::Compressor;
+use crate::decompressor::{DecompressOptions, Decompressor};
+use crate::error::{CompressionError, Result};
+use crate::metadata::{CompressionAlgorithm, CompressionMetadata, CompressionResult};
+use crate::utils::calculate_checksum;
+use std::collections::HashSet;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+pub const ARCHIVE_MAGIC: [u8; 4] = *b"OWLP";
+pub const ARCHIVE_VERSION: u8 = 1;
+
+const HEADER_LEN: u64 = 5;
+const TRAILER_LEN: u64 = 20;
+/// Fixed part of an index record, after the name
+const RECORD_LEN: usize = 8 + 1 + 1 + 8 + 8 + 4 + 8;
+
+/// One entry of the central index
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    pub name: String,
+    /// Position of the compressed bytes from the start of the archive
+    pub offset: u64,
+    pub metadata: CompressionMetadata,
+}
+
+/// Writes an archive entry by entry; nothing is usable until [`finish`]
+/// writes the index
+///
+/// [`finish`]: ArchiveWriter::finish
+pub struct ArchiveWriter<W: Write> {
+    inner: W,
+    position: u64,
+    entries: Vec<ArchiveEntry>,
+    names: HashSet<String>,
+    compressor: Compressor,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    pub fn new(mut inner: W) -> Result<Self> {
+        inner.write_all(&ARCHIVE_MAGIC)?;
+        inner.write_all(&[ARCHIVE_VERSION])?;
+        Ok(Self {
+            inner,
+            position: HEADER_LEN,
+            entries: Vec::new(),
+            names: HashSet::new(),
+            compressor: Compressor::new(),
+        })
+    }
+
+    /// Compress entries with `compressor` instead of the default one
+    pub fn with_compressor(mut self, compressor: Compressor) -> Self {
+        self.compressor = compressor;
+        self
+    }
+
+    /// Compress `data` (auto-selecting when `algorithm` is `None`) and add it.
+    /// Empty data is stored as an empty entry.
+    pub fn compress(
+        &mut self,
+        name: &str,
+        data: &[u8],
+        algorithm: Option<CompressionAlgorithm>,
+    ) -> Result<&ArchiveEntry> {
+        if data.is_empty() {
+            let metadata = CompressionMetadata::new(CompressionAlgorithm::None, 0, 0);
+            return self.push(name, &[], metadata);
+        }
+        let result = self.compressor.compress_with_algorithm(data, algorithm)?;
+        self.add(name, &result)
+    }
+
+    /// Add an already compressed result
+    pub fn add(&mut self, name: &str, result: &CompressionResult) -> Result<&ArchiveEntry> {
+        self.push(name, &result.data, result.metadata.clone())
+    }
+
+    fn push(
+        &mut self,
+        name: &str,
+        data: &[u8],
+        metadata: CompressionMetadata,
+    ) -> Result<&ArchiveEntry> {
+        if name.is_empty() || name.len() > u16::MAX as usize {
+            return Err(CompressionError::invalid_input(format!(
+                "Entry names must be 1 to {} bytes",
+                u16::MAX
+            )));
+        }
+        if !self.names.insert(name.to_string()) {
+            return Err(CompressionError::invalid_input(format!(
+                "Duplicate entry name: {}",
+                name
+            )));
+        }
+
+        self.inner.write_all(data)?;
+        self.entries.push(ArchiveEntry {
+            name: name.to_string(),
+            offset: self.position,
+            metadata,
+        });
+        self.position += data.len() as u64;
+        Ok(self.entries.last().unwrap())
+    }
+
+    /// Write the index and trailer, returning the underlying writer
+    pub fn finish(mut self) -> Result<W> {
+        let index = encode_index(&self.entries);
+        self.inner.write_all(&index)?;
+        self.inner.write_all(&self.position.to_le_bytes())?;
+        self.inner.write_all(&(index.len() as u32).to_le_bytes())?;
+        self.inner
+            .write_all(&calculate_checksum(&index).to_le_bytes())?;
+        self.inner.write_all(&ARCHIVE_MAGIC)?;
+        self.inner.flush()?;
+        tracing::debug!(entries = self.entries.len(), "archive written");
+        Ok(self.inner)
+    }
+}
+
+/// Reads the index on open; entries are only read when extracted
+pub struct ArchiveReader<R: Read + Seek> {
+    inner: R,
+    entries: Vec<ArchiveEntry>,
+}
+
+impl<R: Read + Seek> ArchiveReader<R> {
+    pub fn new(mut inner: R) -> Result<Self> {
+        let len = inner.seek(SeekFrom::End(0))?;
+        if len < HEADER_LEN + TRAILER_LEN {
+            return Err(invalid("Archive is truncated"));
+        }
+
+        let mut header = [0u8; HEADER_LEN as usize];
+        inner.seek(SeekFrom::Start(0))?;
+        inner.read_exact(&mut header)?;
+        if header[..4] != ARCHIVE_MAGIC {
+            return Err(invalid("Not an OWLSOL archive"));
+        }
+        if header[4] != ARCHIVE_VERSION {
+            return Err(invalid(&format!(
+                "Unsupported archive version {}",
+                header[4]
+            )));
+        }
+
+        let mut trailer = [0u8; TRAILER_LEN as usize];
+        inner.seek(SeekFrom::Start(len - TRAILER_LEN))?;
+        inner.read_exact(&mut trailer)?;
+        if trailer[16..] != ARCHIVE_MAGIC {
+            return Err(invalid("Archive trailer is missing"));
+        }
+        let index_offset = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+        let index_len = u32::from_le_bytes(trailer[8..12].try_into().unwrap()) as u64;
+        let checksum = u32::from_le_bytes(trailer[12..16].try_into().unwrap());
+        if index_offset < HEADER_LEN
+            || index_offset.checked_add(index_len) != Some(len - TRAILER_LEN)
+        {
+            return Err(invalid("Archive index is out of bounds"));
+        }
+
+        let mut index = vec![0u8; index_len as usize];
+        inner.seek(SeekFrom::Start(index_offset))?;
+        inner.read_exact(&mut index)?;
+        let actual = calculate_checksum(&index);
+        if actual != checksum {
+            return Err(CompressionError::ChecksumMismatch {
+                expected: checksum,
+                actual,
+            });
+        }
+
+        let entries = decode_index(&index, index_offset)?;
+        Ok(Self { inner, entries })
+    }
+
+    pub fn entries(&self) -> &[ArchiveEntry] {
+        &self.entries
+    }
+
+    pub fn find(&self, name: &str) -> Option<&ArchiveEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// Compressed bytes of `entry`, read without touching any other entry
+    pub fn read_compressed(&mut self, entry: &ArchiveEntry) -> Result<Vec<u8>> {
+        let mut data = vec![0u8; entry.metadata.compressed_size as usize];
+        self.inner.seek(SeekFrom::Start(entry.offset))?;
+        self.inner.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    /// Decompress the entry called `name`
+    pub fn extract(&mut self, name: &str) -> Result<Vec<u8>> {
+        let entry = self
+            .find(name)
+            .cloned()
+            .ok_or_else(|| CompressionError::invalid_input(format!("No entry named {}", name)))?;
+        self.extract_entry(&entry, &DecompressOptions::default())
+    }
+
+    /// Decompress `entry` under the given limits
+    pub fn extract_entry(
+        &mut self,
+        entry: &ArchiveEntry,
+        options: &DecompressOptions,
+    ) -> Result<Vec<u8>> {
+        if entry.metadata.original_size == 0 {
+            return Ok(Vec::new());
+        }
+        let data = self.read_compressed(entry)?;
+        Decompressor::new().decompress_with(&data, &entry.metadata, options)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+fn invalid(message: &str) -> CompressionError {
+    CompressionError::InvalidMetadata(message.to_string())
+}
+
+fn encode_index(entries: &[ArchiveEntry]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    for entry in entries {
+        let metadata = &entry.metadata;
+        out.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+        out.extend_from_slice(entry.name.as_bytes());
+        out.extend_from_slice(&entry.offset.to_le_bytes());
+        out.push(metadata.algorithm.to_u8());
+        out.push(metadata.version);
+        out.extend_from_slice(&metadata.original_size.to_le_bytes());
+        out.extend_from_slice(&metadata.compressed_size.to_le_bytes());
+        out.extend_from_slice(&metadata.checksum.to_le_bytes());
+        out.extend_from_slice(&metadata.timestamp.to_le_bytes());
+    }
+    out
+}
+
+/// Parse the index, checking every entry lies between the header and the
+/// index itself
+fn decode_index(index: &[u8], index_offset: u64) -> Result<Vec<ArchiveEntry>> {
+    let truncated = || invalid("Archive index is truncated");
+    let count = u32::from_le_bytes(index.get(..4).ok_or_else(truncated)?.try_into().unwrap());
+
+    let mut pos = 4;
+    // Every record takes at least RECORD_LEN + 3 bytes, which bounds the
+    // allocation by the index size
+    let mut entries = Vec::with_capacity((count as usize).min(index.len() / (RECORD_LEN + 3)));
+    for _ in 0..count {
+        let name_len = u16::from_le_bytes(
+            index
+                .get(pos..pos + 2)
+                .ok_or_else(truncated)?
+                .try_into()
+                .unwrap(),
+        ) as usize;
+        pos += 2;
+        let name = index.get(pos..pos + name_len).ok_or_else(truncated)?;
+        let name = std::str::from_utf8(name)
+            .map_err(|_| invalid("Archive entry name is not UTF-8"))?
+            .to_string();
+        pos += name_len;
+
+        let record = index.get(pos..pos + RECORD_LEN).ok_or_else(truncated)?;
+        pos += RECORD_LEN;
+        let u64_at = |at: usize| u64::from_le_bytes(record[at..at + 8].try_into().unwrap());
+        let offset = u64_at(0);
+        let algorithm = CompressionAlgorithm::from_u8(record[8])
+            .ok_or_else(|| invalid(&format!("Unknown algorithm id {}", record[8])))?;
+        let original_size = u64_at(10);
+        let compressed_size = u64_at(18);
+        let checksum = u32::from_le_bytes(record[26..30].try_into().unwrap());
+
+        if offset < HEADER_LEN
+            || offset
+                .checked_add(compressed_size)
+                .is_none_or(|end| end > index_offset)
+        {
+            return Err(invalid(&format!("Entry {} is out of bounds", name)));
+        }
+
+        let mut metadata = CompressionMetadata::new(algorithm, original_size, compressed_size)
+            .with_checksum(checksum);
+        metadata.version = record[9];
+        metadata.timestamp = u64_at(30) as i64;
+        entries.push(ArchiveEntry {
+            name,
+            offset,
+            metadata,
+        });
+    }
+    if pos != index.len() {
+        return Err(invalid("Archive index has trailing bytes"));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Counts the bytes read through it
+    struct Counting<R> {
+        inner: R,
+        read: usize,
+    }
+
+    impl<R: Read> Read for Counting<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.inner.read(buf)?;
+            self.read += n;
+            Ok(n)
+        }
+    }
+
+    impl<R: Seek> Seek for Counting<R> {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    fn sample_archive() -> (Vec<(String, Vec<u8>)>, Vec<u8>) {
+        let files = vec![
+            ("accounts/a.bin".to_string(), vec![0u8; 4096]),
+            ("accounts/b.bin".to_string(), b"mint authority ".repeat(200)),
+            ("empty".to_string(), Vec::new()),
+            ("notes.txt".to_string(), b"owl".repeat(500)),
+        ];
+        let mut writer = ArchiveWriter::new(Vec::new()).unwrap();
+        for (name, data) in &files {
+            writer.compress(name, data, None).unwrap();
+        }
+        (files, writer.finish().unwrap())
+    }
+
+    #[test]
+    fn test_archive_roundtrip() {
+        let (files, bytes) = sample_archive();
+        let mut reader = ArchiveReader::new(Cursor::new(bytes)).unwrap();
+
+        let names: Vec<_> = reader.entries().iter().map(|e| e.name.clone()).collect();
+        assert_eq!(names.len(), files.len());
+        for (name, data) in &files {
+            assert_eq!(&reader.extract(name).unwrap(), data, "{}", name);
+        }
+        assert!(reader.extract("missing").is_err());
+
+        let mut writer = ArchiveWriter::new(Vec::new()).unwrap();
+        writer.compress("a", b"x", None).unwrap();
+        assert!(writer.compress("a", b"y", None).is_err());
+    }
+
+    #[test]
+    fn test_single_entry_extraction_skips_others() {
+        let (files, bytes) = sample_archive();
+        let total = bytes.len();
+        let mut reader = ArchiveReader::new(Counting {
+            inner: Cursor::new(bytes),
+            read: 0,
+        })
+        .unwrap();
+        let index_read = reader.inner.read;
+
+        let entry = reader.find("notes.txt").unwrap().clone();
+        assert_eq!(reader.extract("notes.txt").unwrap(), files[3].1);
+        let entry_read = reader.into_inner().read - index_read;
+        assert_eq!(entry_read as u64, entry.metadata.compressed_size);
+        assert!(index_read + entry_read < total);
+    }
+
+    #[test]
+    fn test_rejects_damaged_archives() {
+        let (_, bytes) = sample_archive();
+        let open = |bytes: Vec<u8>| ArchiveReader::new(Cursor::new(bytes)).map(|_| ());
+
+        assert!(open(bytes[..10].to_vec()).is_err());
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(open(bad_magic).is_err());
+        // Flipping an index byte breaks its checksum
+        let mut bad_index = bytes.clone();
+        let at = bytes.len() - TRAILER_LEN as usize - 3;
+        bad_index[at] ^= 0x40;
+        assert_eq!(open(bad_index).unwrap_err().code(), 301);
+        let mut bad_offset = bytes.clone();
+        let at = bytes.len() - TRAILER_LEN as usize;
+        bad_offset[at] ^= 1;
+        assert_eq!(open(bad_offset).unwrap_err().code(), 300);
+    }
+}
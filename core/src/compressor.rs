@@ -1,5 +1,6 @@
-use crate::algorithms::{dict_compress, rle_compress, HuffmanCodec};
+use crate::algorithms::{dict_compress, hybrid, rle_compress, HuffmanCodec};
 use crate::analyzer::DataAnalyzer;
+use crate::cost::CostWeights;
 use crate::error::{CompressionError, Result};
 use crate::metadata::{
     CompressionAlgorithm, CompressionMetadata, CompressionResult, MAX_DATA_SIZE,
@@ -10,38 +11,168 @@ use crate::utils::calculate_checksum;
 pub struct Compressor {
     analyzer: DataAnalyzer,
     selector: AlgorithmSelector,
+    onchain_only: bool,
+    cost_weights: Option<CostWeights>,
+    level: Option<u32>,
 }
 
 impl Compressor {
+    #[cfg(feature = "lz4")]
     fn compress_lz4(&self, data: &[u8]) -> Result<(Vec<u8>, CompressionAlgorithm)> {
         use lz4::block::compress;
-        let compressed = compress(data, None, false)
-            .map_err(|e| CompressionError::compression_failed(format!("LZ4: {}", e)))?;
+        let compressed = compress(data, None, false).map_err(|e| {
+            CompressionError::compression_failed(CompressionAlgorithm::Lz4, e.to_string())
+        })?;
         Ok((compressed, CompressionAlgorithm::Lz4))
     }
 
+    #[cfg(feature = "zstd")]
     fn compress_zstd(&self, data: &[u8]) -> Result<(Vec<u8>, CompressionAlgorithm)> {
         use std::io::Cursor;
         use zstd::stream::encode_all;
-        let compressed = encode_all(Cursor::new(data), 1)
-            .map_err(|e| CompressionError::compression_failed(format!("Zstd: {}", e)))?;
+        let compressed =
+            encode_all(Cursor::new(data), self.level_in(1, 22, 1) as i32).map_err(|e| {
+                CompressionError::compression_failed(CompressionAlgorithm::Zstd, e.to_string())
+            })?;
         Ok((compressed, CompressionAlgorithm::Zstd))
     }
+
+    #[cfg(feature = "brotli")]
+    fn compress_brotli(&self, data: &[u8]) -> Result<(Vec<u8>, CompressionAlgorithm)> {
+        use brotli::enc::BrotliEncoderParams;
+        use std::io::Cursor;
+        let mut compressed = Vec::new();
+        let mut params = BrotliEncoderParams::default();
+        params.quality = self.level_in(0, 11, params.quality as u32) as i32;
+        brotli::BrotliCompress(&mut Cursor::new(data), &mut compressed, &params).map_err(|e| {
+            CompressionError::compression_failed(CompressionAlgorithm::Brotli, e.to_string())
+        })?;
+        Ok((compressed, CompressionAlgorithm::Brotli))
+    }
+
+    #[cfg(feature = "deflate")]
+    fn compress_deflate(&self, data: &[u8]) -> Result<(Vec<u8>, CompressionAlgorithm)> {
+        use flate2::{write::DeflateEncoder, Compression};
+        use std::io::Write;
+        let level = Compression::new(self.level_in(0, 9, Compression::default().level()));
+        let mut encoder = DeflateEncoder::new(Vec::new(), level);
+        let compressed = encoder
+            .write_all(data)
+            .and_then(|_| encoder.finish())
+            .map_err(|e| {
+                CompressionError::compression_failed(CompressionAlgorithm::Deflate, e.to_string())
+            })?;
+        Ok((compressed, CompressionAlgorithm::Deflate))
+    }
+
+    #[cfg(feature = "deflate")]
+    fn compress_gzip(&self, data: &[u8]) -> Result<(Vec<u8>, CompressionAlgorithm)> {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+        let level = Compression::new(self.level_in(0, 9, Compression::default().level()));
+        let mut encoder = GzEncoder::new(Vec::new(), level);
+        let compressed = encoder
+            .write_all(data)
+            .and_then(|_| encoder.finish())
+            .map_err(|e| {
+                CompressionError::compression_failed(CompressionAlgorithm::Gzip, e.to_string())
+            })?;
+        Ok((compressed, CompressionAlgorithm::Gzip))
+    }
+
+    #[cfg(feature = "snappy")]
+    fn compress_snappy(&self, data: &[u8]) -> Result<(Vec<u8>, CompressionAlgorithm)> {
+        let compressed = snap::raw::Encoder::new().compress_vec(data).map_err(|e| {
+            CompressionError::compression_failed(CompressionAlgorithm::Snappy, e.to_string())
+        })?;
+        Ok((compressed, CompressionAlgorithm::Snappy))
+    }
+
+    #[cfg(feature = "lzma")]
+    fn compress_lzma(&self, data: &[u8]) -> Result<(Vec<u8>, CompressionAlgorithm)> {
+        use std::io::Read;
+        let mut compressed = Vec::new();
+        xz2::read::XzEncoder::new(data, self.level_in(0, 9, 6))
+            .read_to_end(&mut compressed)
+            .map_err(|e| {
+                CompressionError::compression_failed(CompressionAlgorithm::Lzma, e.to_string())
+            })?;
+        Ok((compressed, CompressionAlgorithm::Lzma))
+    }
     pub fn new() -> Self {
         Self {
             analyzer: DataAnalyzer::new(),
             selector: AlgorithmSelector::new(),
+            onchain_only: false,
+            cost_weights: None,
+            level: None,
         }
     }
 
+    /// Compressor whose automatic choices (auto mode and Hybrid segments) are
+    /// limited to algorithms `owlsol-onchain` can decode
+    pub fn onchain() -> Self {
+        Self {
+            onchain_only: true,
+            ..Self::new()
+        }
+    }
+
+    /// Make auto mode minimize rent plus estimated decode compute cost instead
+    /// of size. Implies on-chain only, since only those decodes cost CUs.
+    pub fn with_cost_weights(mut self, weights: CostWeights) -> Self {
+        self.onchain_only = true;
+        self.cost_weights = Some(weights);
+        self
+    }
+
+    /// Compression level for the backends that have one: zstd (1-22),
+    /// Brotli (0-11), Deflate and Gzip (0-9) and LZMA (0-9). Each clamps it
+    /// to its own range; the other algorithms ignore it.
+    pub fn with_level(mut self, level: u32) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// The configured level clamped to `min..=max`, or `default` without one
+    #[cfg(any(
+        feature = "zstd",
+        feature = "brotli",
+        feature = "deflate",
+        feature = "lzma"
+    ))]
+    fn level_in(&self, min: u32, max: u32, default: u32) -> u32 {
+        self.level.map_or(default, |level| level.clamp(min, max))
+    }
+
     pub fn compress(&self, data: &[u8]) -> Result<CompressionResult> {
         self.compress_with_algorithm(data, None)
     }
 
+    #[tracing::instrument(
+        name = "compress",
+        skip_all,
+        fields(original_size = data.len(), requested = ?algorithm)
+    )]
     pub fn compress_with_algorithm(
         &self,
         data: &[u8],
         algorithm: Option<CompressionAlgorithm>,
+    ) -> Result<CompressionResult> {
+        let result = self.compress_inner(data, algorithm)?;
+        tracing::debug!(
+            algorithm = result.metadata.algorithm.as_str(),
+            compressed_size = result.data.len(),
+            decode_cu = result.decode_cu,
+            "compressed"
+        );
+        Ok(result)
+    }
+
+    fn compress_inner(
+        &self,
+        data: &[u8],
+        algorithm: Option<CompressionAlgorithm>,
     ) -> Result<CompressionResult> {
         // Validation
         if data.is_empty() {
@@ -49,7 +180,10 @@ impl Compressor {
         }
 
         if data.len() > MAX_DATA_SIZE {
-            return Err(CompressionError::DataTooLarge(data.len(), MAX_DATA_SIZE));
+            return Err(CompressionError::DataTooLarge {
+                size: data.len(),
+                max: MAX_DATA_SIZE,
+            });
         }
 
         let original_size = data.len() as u64;
@@ -61,9 +195,23 @@ impl Compressor {
                 CompressionAlgorithm::Huffman => self.compress_huffman(data)?,
                 CompressionAlgorithm::Dictionary => self.compress_dictionary(data)?,
                 CompressionAlgorithm::RunLength => self.compress_rle(data)?,
+                #[cfg(feature = "lz4")]
                 CompressionAlgorithm::Lz4 => self.compress_lz4(data)?,
+                #[cfg(feature = "zstd")]
                 CompressionAlgorithm::Zstd => self.compress_zstd(data)?,
                 CompressionAlgorithm::Hybrid => self.compress_hybrid(data)?,
+                #[cfg(feature = "brotli")]
+                CompressionAlgorithm::Brotli => self.compress_brotli(data)?,
+                #[cfg(feature = "deflate")]
+                CompressionAlgorithm::Deflate => self.compress_deflate(data)?,
+                #[cfg(feature = "deflate")]
+                CompressionAlgorithm::Gzip => self.compress_gzip(data)?,
+                #[cfg(feature = "snappy")]
+                CompressionAlgorithm::Snappy => self.compress_snappy(data)?,
+                #[cfg(feature = "lzma")]
+                CompressionAlgorithm::Lzma => self.compress_lzma(data)?,
+                #[allow(unreachable_patterns)]
+                other => return Err(CompressionError::UnsupportedAlgorithm { algorithm: other }),
             };
 
             let compressed_size = compressed_data.len() as u64;
@@ -74,47 +222,49 @@ impl Compressor {
                 (compressed_data, actual_algo)
             };
 
-            let checksum = calculate_checksum(&final_data);
-            let metadata =
-                CompressionMetadata::new(final_algo, original_size, final_data.len() as u64)
-                    .with_checksum(checksum);
-
-            return Ok(CompressionResult::new(final_data, metadata));
+            return Ok(Self::finish(final_data, final_algo, original_size));
         }
 
         // Auto mode: Try all algorithms and pick best
-        let mut best_result: Option<(Vec<u8>, CompressionAlgorithm, u64)> = None;
-
-        // Try all algorithms
-        let results = vec![
-            self.compress_huffman(data).ok(),
-            self.compress_dictionary(data).ok(),
-            self.compress_rle(data).ok(),
-            self.compress_lz4(data).ok(),
-            self.compress_zstd(data).ok(),
-        ];
-
-        for result in results.into_iter().flatten() {
-            let compressed_size = result.0.len() as u64;
-            if compressed_size < original_size
-                && (best_result.is_none() || compressed_size < best_result.as_ref().unwrap().2)
-            {
-                best_result = Some((result.0, result.1, compressed_size));
-            }
+        let mut results = self.compress_all(data);
+        results.push(self.compress_hybrid(data).ok());
+
+        if let Some(weights) = &self.cost_weights {
+            let mut candidates: Vec<CompressionResult> = results
+                .into_iter()
+                .flatten()
+                .map(|(compressed, algo)| Self::finish(compressed, algo, original_size))
+                .collect();
+            candidates.push(Self::finish(
+                data.to_vec(),
+                CompressionAlgorithm::None,
+                original_size,
+            ));
+
+            return self
+                .selector
+                .select_cheapest(&candidates, weights)
+                .cloned()
+                .ok_or(CompressionError::ComputeBudgetExceeded {
+                    max_compute_units: weights.max_compute_units,
+                });
         }
 
         // Use best result or original data
-        let (final_data, final_algo) = if let Some((data, algo, _)) = best_result {
-            (data, algo)
-        } else {
-            (data.to_vec(), CompressionAlgorithm::None)
-        };
+        let (final_data, final_algo) = Self::pick_smallest(data, results);
 
-        let checksum = calculate_checksum(&final_data);
-        let metadata = CompressionMetadata::new(final_algo, original_size, final_data.len() as u64)
-            .with_checksum(checksum);
+        Ok(Self::finish(final_data, final_algo, original_size))
+    }
 
-        Ok(CompressionResult::new(final_data, metadata))
+    fn finish(
+        compressed: Vec<u8>,
+        algorithm: CompressionAlgorithm,
+        original_size: u64,
+    ) -> CompressionResult {
+        let checksum = calculate_checksum(&compressed);
+        let metadata = CompressionMetadata::new(algorithm, original_size, compressed.len() as u64)
+            .with_checksum(checksum);
+        CompressionResult::new(compressed, metadata)
     }
 
     fn compress_huffman(&self, data: &[u8]) -> Result<(Vec<u8>, CompressionAlgorithm)> {
@@ -130,21 +280,11 @@ impl Compressor {
         result.extend_from_slice(&tree_size.to_le_bytes());
         result.extend_from_slice(&tree);
         result.extend_from_slice(&encoded);
-        println!(
-            "[COMPRESS HUFFMAN] Original len: {}, Compressed len: {}",
-            data.len(),
-            result.len()
-        );
         Ok((result, CompressionAlgorithm::Huffman))
     }
 
     fn compress_dictionary(&self, data: &[u8]) -> Result<(Vec<u8>, CompressionAlgorithm)> {
         let compressed = dict_compress(data)?;
-        println!(
-            "[COMPRESS DICT] Original len: {}, Compressed len: {}",
-            data.len(),
-            compressed.len()
-        );
         Ok((compressed, CompressionAlgorithm::Dictionary))
     }
 
@@ -154,24 +294,71 @@ impl Compressor {
     }
 
     fn compress_hybrid(&self, data: &[u8]) -> Result<(Vec<u8>, CompressionAlgorithm)> {
-        // Try all algorithms and pick the best
-        let results = vec![
+        // Compress each segment with whichever algorithm suits it best
+        let segments: Vec<_> = hybrid::split(data)
+            .into_iter()
+            .map(|range| {
+                let block = &data[range];
+                let (payload, algo) = Self::pick_smallest(block, self.compress_all(block));
+                (algo, block.len(), payload)
+            })
+            .collect();
+
+        Ok((hybrid::encode(&segments)?, CompressionAlgorithm::Hybrid))
+    }
+
+    /// Run every compiled-in (or on-chain decodable) algorithm except Hybrid
+    fn compress_all(&self, data: &[u8]) -> Vec<Option<(Vec<u8>, CompressionAlgorithm)>> {
+        #[allow(unused_mut)]
+        let mut results = vec![
             self.compress_huffman(data).ok(),
             self.compress_dictionary(data).ok(),
             self.compress_rle(data).ok(),
         ];
 
-        let mut best = (data.to_vec(), CompressionAlgorithm::None);
-        let mut best_size = data.len();
+        if !self.onchain_only {
+            #[cfg(feature = "lz4")]
+            results.push(self.compress_lz4(data).ok());
+            #[cfg(feature = "zstd")]
+            results.push(self.compress_zstd(data).ok());
+            #[cfg(feature = "brotli")]
+            results.push(self.compress_brotli(data).ok());
+            #[cfg(feature = "deflate")]
+            results.push(self.compress_deflate(data).ok());
+            #[cfg(feature = "deflate")]
+            results.push(self.compress_gzip(data).ok());
+            #[cfg(feature = "snappy")]
+            results.push(self.compress_snappy(data).ok());
+            #[cfg(feature = "lzma")]
+            results.push(self.compress_lzma(data).ok());
+        }
+
+        for (compressed, algo) in results.iter().flatten() {
+            tracing::trace!(
+                algorithm = algo.as_str(),
+                compressed_size = compressed.len(),
+                "candidate"
+            );
+        }
+
+        results
+    }
+
+    /// Pick the smallest candidate, falling back to the raw data if none is smaller
+    fn pick_smallest(
+        data: &[u8],
+        results: Vec<Option<(Vec<u8>, CompressionAlgorithm)>>,
+    ) -> (Vec<u8>, CompressionAlgorithm) {
+        let mut best: Option<(Vec<u8>, CompressionAlgorithm)> = None;
 
         for result in results.into_iter().flatten() {
+            let best_size = best.as_ref().map_or(data.len(), |b| b.0.len());
             if result.0.len() < best_size {
-                best = result;
-                best_size = best.0.len();
+                best = Some(result);
             }
         }
 
-        Ok(best)
+        best.unwrap_or_else(|| (data.to_vec(), CompressionAlgorithm::None))
     }
 
     pub fn estimate_ratio(&self, data: &[u8]) -> f64 {
@@ -224,8 +411,68 @@ mod tests {
         // RLE-friendly data
         let data = vec![b'A'; 1000];
         let result = compressor.compress(&data).unwrap();
-        assert_eq!(result.metadata.algorithm, CompressionAlgorithm::RunLength);
         assert!(result.metadata.compression_percentage() > 90.0);
+
+        // Optional backends such as Brotli can beat RLE on a single long run
+        let rle = compressor
+            .compress_with_algorithm(&data, Some(CompressionAlgorithm::RunLength))
+            .unwrap();
+        assert!(result.metadata.compressed_size <= rle.metadata.compressed_size);
+        if !cfg!(any(
+            feature = "brotli",
+            feature = "deflate",
+            feature = "snappy",
+            feature = "lzma"
+        )) {
+            assert_eq!(result.metadata.algorithm, CompressionAlgorithm::RunLength);
+        }
+    }
+
+    #[test]
+    fn test_onchain_compressor() {
+        let compressor = Compressor::onchain();
+        let mut data = b"owner mint amount delegate state".repeat(8);
+        data.extend(vec![0u8; 512]);
+
+        let result = compressor.compress(&data).unwrap();
+        assert!(result.metadata.algorithm.is_onchain());
+
+        let hybrid = compressor
+            .compress_with_algorithm(&data, Some(CompressionAlgorithm::Hybrid))
+            .unwrap();
+        let (segments, _) = hybrid::read_table(&hybrid.data).unwrap();
+        assert!(segments.iter().all(|s| s.algorithm.is_onchain()));
+    }
+
+    #[test]
+    fn test_cost_weighted_selection() {
+        let mut data = b"owner mint amount delegate state".repeat(8);
+        data.extend(vec![0u8; 512]);
+
+        let result = Compressor::new()
+            .with_cost_weights(CostWeights::default())
+            .compress(&data)
+            .unwrap();
+        assert!(result.metadata.algorithm.is_onchain());
+        assert!(result.decode_cu.is_some());
+        assert!(result.data.len() < data.len());
+
+        // When every decode is expensive, storing the raw bytes is cheapest
+        let result = Compressor::new()
+            .with_cost_weights(CostWeights {
+                micro_lamports_per_cu: 1_000_000,
+                expected_decodes: 1_000_000,
+                ..CostWeights::default()
+            })
+            .compress(&data)
+            .unwrap();
+        assert_eq!(result.metadata.algorithm, CompressionAlgorithm::None);
+
+        let result = Compressor::new().with_cost_weights(CostWeights {
+            max_compute_units: 0,
+            ..CostWeights::default()
+        });
+        assert!(result.compress(&data).is_err());
     }
 
     #[test]
@@ -233,11 +480,38 @@ mod tests {
         let compressor = Compressor::new();
         let data: Vec<u8> = (0..1000).map(|i| (i * 7919) as u8).collect();
         let result = compressor.compress(&data).unwrap();
-        // For random data, either None, Lz4, or Zstd may be chosen depending on output size
+        // For random data, either None or a general-purpose backend may be chosen depending on output size
         match result.metadata.algorithm {
-            CompressionAlgorithm::None | CompressionAlgorithm::Lz4 | CompressionAlgorithm::Zstd => {
-            }
+            CompressionAlgorithm::None
+            | CompressionAlgorithm::Lz4
+            | CompressionAlgorithm::Zstd
+            | CompressionAlgorithm::Brotli
+            | CompressionAlgorithm::Deflate
+            | CompressionAlgorithm::Gzip
+            | CompressionAlgorithm::Snappy
+            | CompressionAlgorithm::Lzma => {}
             other => panic!("Unexpected algorithm for incompressible data: {:?}", other),
         }
     }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_level() {
+        let data: Vec<u8> = (0..20_000u32)
+            .map(|i| (i % 251) as u8 ^ (i / 97) as u8)
+            .collect();
+        let size = |compressor: Compressor| {
+            compressor
+                .compress_with_algorithm(&data, Some(CompressionAlgorithm::Zstd))
+                .unwrap()
+                .data
+                .len()
+        };
+        // Out-of-range levels clamp rather than fail
+        assert!(size(Compressor::new().with_level(99)) <= size(Compressor::new().with_level(1)));
+        assert_eq!(
+            size(Compressor::new().with_level(0)),
+            size(Compressor::new())
+        );
+    }
 }
@@ -0,0 +1,423 @@
+//! Self-describing `.owlsol` container
+//!
+//! Carries the compression metadata in a fixed binary header in front of the
+//! payload, so a single file is enough to decompress it. Optional blocks
+//! (encryption parameters, signer) follow the fixed header and are announced
+//! by flag bits.
+//!
+//! ```text
+//! [magic "OWLS"][container version][flags][algorithm][metadata version]
+//! [original_size u64][compressed_size u64][checksum u32][timestamp i64]
+//! [encryption block, if FLAG_ENCRYPTED]
+//! [signer public key, if FLAG_SIGNED]
+//! [payload]
+//! [ed25519 signature over everything above, if FLAG_SIGNED]
+//! ```
+
+use crate::error::{CompressionError, Result};
+use crate::metadata::{CompressionAlgorithm, CompressionMetadata, CompressionResult};
+use alloc::{format, vec::Vec};
+
+pub const MAGIC: [u8; 4] = *b"OWLS";
+pub const CONTAINER_VERSION: u8 = 1;
+/// The payload is encrypted; an encryption block follows the fixed header
+pub const FLAG_ENCRYPTED: u8 = 0x01;
+/// The container is signed; the signer key follows the other header blocks
+/// and the signature trails the payload
+pub const FLAG_SIGNED: u8 = 0x02;
+
+const KNOWN_FLAGS: u8 = FLAG_ENCRYPTED | FLAG_SIGNED;
+/// Header in front of every payload; encryption and signer blocks add to it
+pub const FIXED_HEADER_LEN: usize = 36;
+
+pub const SALT_LEN: usize = 16;
+pub const NONCE_LEN: usize = 12;
+pub const SIGNER_LEN: usize = 32;
+pub const SIGNATURE_LEN: usize = 64;
+/// Cipher id, KDF id, salt and nonce; KDF parameters come on top
+const ENCRYPTION_BLOCK_BASE_LEN: usize = 2 + SALT_LEN + NONCE_LEN;
+
+/// AEAD cipher protecting an encrypted payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Cipher {
+    ChaCha20Poly1305 = 1,
+}
+
+impl Cipher {
+    pub fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            1 => Some(Self::ChaCha20Poly1305),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ChaCha20Poly1305 => "chacha20-poly1305",
+        }
+    }
+}
+
+/// How the cipher key was obtained
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kdf {
+    /// A 32-byte key supplied directly (e.g. from a key file)
+    RawKey,
+    /// Argon2id over a passphrase and the header salt; memory cost in KiB
+    Argon2id {
+        m_cost: u32,
+        t_cost: u32,
+        p_cost: u32,
+    },
+    /// X25519 agreement between an ephemeral key and the recipient's ed25519
+    /// (Solana) public key, converted to its Montgomery form
+    X25519 {
+        ephemeral: [u8; 32],
+        recipient: [u8; 32],
+    },
+}
+
+impl Kdf {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::RawKey => "raw-key",
+            Self::Argon2id { .. } => "argon2id",
+            Self::X25519 { .. } => "x25519",
+        }
+    }
+
+    fn id(&self) -> u8 {
+        match self {
+            Self::RawKey => 0,
+            Self::Argon2id { .. } => 1,
+            Self::X25519 { .. } => 2,
+        }
+    }
+
+    /// Length of the parameters stored for KDF `id`; the first two keep a
+    /// fixed 12-byte slot
+    fn params_len(id: u8) -> Option<usize> {
+        match id {
+            0 | 1 => Some(12),
+            2 => Some(64),
+            _ => None,
+        }
+    }
+}
+
+/// Parameters needed to decrypt the payload, stored in the clear
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncryptionHeader {
+    pub cipher: Cipher,
+    pub kdf: Kdf,
+    pub salt: [u8; SALT_LEN],
+    pub nonce: [u8; NONCE_LEN],
+}
+
+impl EncryptionHeader {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(self.cipher as u8);
+        out.push(self.kdf.id());
+        match self.kdf {
+            Kdf::RawKey => out.extend_from_slice(&[0; 12]),
+            Kdf::Argon2id {
+                m_cost,
+                t_cost,
+                p_cost,
+            } => {
+                for param in [m_cost, t_cost, p_cost] {
+                    out.extend_from_slice(&param.to_le_bytes());
+                }
+            }
+            Kdf::X25519 {
+                ephemeral,
+                recipient,
+            } => {
+                out.extend_from_slice(&ephemeral);
+                out.extend_from_slice(&recipient);
+            }
+        }
+        out.extend_from_slice(&self.salt);
+        out.extend_from_slice(&self.nonce);
+    }
+
+    /// Parse the block at the start of `data`, returning it and its length
+    fn read(data: &[u8]) -> Result<(Self, usize)> {
+        let truncated =
+            || CompressionError::InvalidMetadata("Encryption header is truncated".into());
+        if data.len() < 2 {
+            return Err(truncated());
+        }
+        let (cipher, kdf) = (data[0], data[1]);
+        let cipher = Cipher::from_u8(cipher).ok_or_else(|| {
+            CompressionError::InvalidMetadata(format!("Unknown cipher id {}", cipher))
+        })?;
+        let params_len = Kdf::params_len(kdf).ok_or_else(|| {
+            CompressionError::InvalidMetadata(format!("Unknown key derivation id {}", kdf))
+        })?;
+        let block_len = ENCRYPTION_BLOCK_BASE_LEN + params_len;
+        let block = data.get(..block_len).ok_or_else(truncated)?;
+
+        let params = &block[2..2 + params_len];
+        let param = |i: usize| u32::from_le_bytes(params[4 * i..4 * i + 4].try_into().unwrap());
+        let kdf = match kdf {
+            0 => Kdf::RawKey,
+            1 => Kdf::Argon2id {
+                m_cost: param(0),
+                t_cost: param(1),
+                p_cost: param(2),
+            },
+            _ => Kdf::X25519 {
+                ephemeral: params[..32].try_into().unwrap(),
+                recipient: params[32..].try_into().unwrap(),
+            },
+        };
+        let salt_start = 2 + params_len;
+        let nonce_start = salt_start + SALT_LEN;
+        let header = Self {
+            cipher,
+            kdf,
+            salt: block[salt_start..nonce_start].try_into().unwrap(),
+            nonce: block[nonce_start..].try_into().unwrap(),
+        };
+        Ok((header, block_len))
+    }
+}
+
+/// Ed25519 signer and signature of a signed container
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignatureBlock {
+    pub signer: [u8; SIGNER_LEN],
+    pub signature: [u8; SIGNATURE_LEN],
+}
+
+/// A parsed container: metadata, optional encryption parameters, payload and
+/// optional signature
+#[derive(Debug, Clone)]
+pub struct Container {
+    pub metadata: CompressionMetadata,
+    pub encryption: Option<EncryptionHeader>,
+    /// Compressed bytes, or their ciphertext when `encryption` is set
+    pub payload: Vec<u8>,
+    pub signature: Option<SignatureBlock>,
+}
+
+impl Container {
+    /// Wrap an unencrypted compression result
+    pub fn new(result: CompressionResult) -> Self {
+        Self {
+            metadata: result.metadata,
+            encryption: None,
+            payload: result.data,
+            signature: None,
+        }
+    }
+
+    /// Whether `data` starts with the container magic
+    pub fn is_container(data: &[u8]) -> bool {
+        data.starts_with(&MAGIC)
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.encryption.is_some()
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.signature.is_some()
+    }
+
+    /// Compressed payload ready for `Decompressor`, or `KeyRequired` if it is
+    /// still encrypted
+    pub fn compressed_payload(&self) -> Result<&[u8]> {
+        if self.is_encrypted() {
+            return Err(CompressionError::KeyRequired);
+        }
+        Ok(&self.payload)
+    }
+
+    /// Serialized header: everything in front of the payload
+    pub fn header_bytes(&self) -> Vec<u8> {
+        self.write_header(true)
+    }
+
+    /// Header without the signer block; encryption authenticates this so a
+    /// signature can be added to or stripped from an encrypted container
+    pub fn unsigned_header_bytes(&self) -> Vec<u8> {
+        self.write_header(false)
+    }
+
+    /// Bytes the signature covers: the header and the payload
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        let mut out = self.header_bytes();
+        out.extend_from_slice(&self.payload);
+        out
+    }
+
+    fn write_header(&self, with_signer: bool) -> Vec<u8> {
+        let signer = self.signature.filter(|_| with_signer);
+        let mut flags = 0;
+        if self.is_encrypted() {
+            flags |= FLAG_ENCRYPTED;
+        }
+        if signer.is_some() {
+            flags |= FLAG_SIGNED;
+        }
+
+        let mut out = Vec::with_capacity(FIXED_HEADER_LEN + ENCRYPTION_BLOCK_BASE_LEN + 96);
+        out.extend_from_slice(&MAGIC);
+        out.push(CONTAINER_VERSION);
+        out.push(flags);
+        out.push(self.metadata.algorithm.to_u8());
+        out.push(self.metadata.version);
+        out.extend_from_slice(&self.metadata.original_size.to_le_bytes());
+        out.extend_from_slice(&self.metadata.compressed_size.to_le_bytes());
+        out.extend_from_slice(&self.metadata.checksum.to_le_bytes());
+        out.extend_from_slice(&self.metadata.timestamp.to_le_bytes());
+        if let Some(encryption) = &self.encryption {
+            encryption.write(&mut out);
+        }
+        if let Some(signature) = &signer {
+            out.extend_from_slice(&signature.signer);
+        }
+        out
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.signed_bytes();
+        if let Some(signature) = &self.signature {
+            out.extend_from_slice(&signature.signature);
+        }
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let (container, _) = Self::parse(data)?;
+        Ok(container)
+    }
+
+    /// Parse a container, also returning the length of its header
+    pub fn parse(data: &[u8]) -> Result<(Self, usize)> {
+        let header = data.get(..FIXED_HEADER_LEN).ok_or_else(|| {
+            CompressionError::InvalidMetadata("Container header is truncated".into())
+        })?;
+        if header[..4] != MAGIC {
+            return Err(CompressionError::InvalidMetadata(
+                "Not an OWLSOL container".into(),
+            ));
+        }
+        if header[4] != CONTAINER_VERSION {
+            return Err(CompressionError::InvalidMetadata(format!(
+                "Unsupported container version {}",
+                header[4]
+            )));
+        }
+        let flags = header[5];
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(CompressionError::InvalidMetadata(format!(
+                "Unknown container flags {:#04x}",
+                flags
+            )));
+        }
+        let algorithm = CompressionAlgorithm::from_u8(header[6]).ok_or_else(|| {
+            CompressionError::InvalidMetadata(format!("Unknown algorithm id {}", header[6]))
+        })?;
+
+        let u64_at = |pos: usize| u64::from_le_bytes(header[pos..pos + 8].try_into().unwrap());
+        let original_size = u64_at(8);
+        let compressed_size = u64_at(16);
+        let checksum = u32::from_le_bytes(header[24..28].try_into().unwrap());
+        let timestamp = u64_at(28) as i64;
+
+        let mut header_len = FIXED_HEADER_LEN;
+        let encryption = if flags & FLAG_ENCRYPTED != 0 {
+            let (encryption, block_len) = EncryptionHeader::read(&data[header_len..])?;
+            header_len += block_len;
+            Some(encryption)
+        } else {
+            None
+        };
+
+        let mut payload_end = data.len();
+        let signature = if flags & FLAG_SIGNED != 0 {
+            let truncated =
+                || CompressionError::InvalidMetadata("Signature block is truncated".into());
+            let signer = data
+                .get(header_len..header_len + SIGNER_LEN)
+                .ok_or_else(truncated)?;
+            header_len += SIGNER_LEN;
+            payload_end = payload_end
+                .checked_sub(SIGNATURE_LEN)
+                .filter(|&end| end >= header_len)
+                .ok_or_else(truncated)?;
+            Some(SignatureBlock {
+                signer: signer.try_into().unwrap(),
+                signature: data[payload_end..].try_into().unwrap(),
+            })
+        } else {
+            None
+        };
+
+        let payload = &data[header_len..payload_end];
+        if encryption.is_none() && payload.len() as u64 != compressed_size {
+            return Err(CompressionError::InvalidMetadata(format!(
+                "Payload is {} bytes, header declares {}",
+                payload.len(),
+                compressed_size
+            )));
+        }
+
+        let mut metadata = CompressionMetadata::new(algorithm, original_size, compressed_size)
+            .with_checksum(checksum);
+        metadata.version = header[7];
+        metadata.timestamp = timestamp;
+
+        Ok((
+            Self {
+                metadata,
+                encryption,
+                payload: payload.to_vec(),
+                signature,
+            },
+            header_len,
+        ))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{Compressor, Decompressor};
+
+    #[test]
+    fn test_roundtrip() {
+        let data = b"owl owl owl owl owl owl owl owl".repeat(8);
+        let result = Compressor::new().compress(&data).unwrap();
+        let bytes = Container::new(result.clone()).to_bytes();
+        assert!(Container::is_container(&bytes));
+
+        let container = Container::from_bytes(&bytes).unwrap();
+        assert_eq!(container.metadata.algorithm, result.metadata.algorithm);
+        assert_eq!(container.metadata.checksum, result.metadata.checksum);
+        assert_eq!(container.metadata.timestamp, result.metadata.timestamp);
+        let decompressed = Decompressor::new()
+            .decompress(container.compressed_payload().unwrap(), &container.metadata)
+            .unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn test_rejects_bad_headers() {
+        let result = Compressor::new().compress(&[1u8; 64]).unwrap();
+        let bytes = Container::new(result).to_bytes();
+
+        assert!(Container::from_bytes(&bytes[..20]).is_err());
+        assert!(Container::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        for (pos, value) in [(0, b'X'), (4, 9), (5, 0x80), (6, 200)] {
+            let mut bad = bytes.clone();
+            bad[pos] = value;
+            let err = Container::from_bytes(&bad).unwrap_err();
+            assert_eq!(err.code(), 300, "byte {}", pos);
+        }
+    }
+}
@@ -0,0 +1,281 @@
+//! Compute-unit cost model for on-chain decoding
+//!
+//! Estimates the compute units `owlsol-onchain` spends decoding a payload from
+//! the algorithm, the payload and output sizes and, for Huffman, the number of
+//! code bits walked. The coefficients are starting estimates derived from the
+//! decoders' SBF instruction mix, not yet fitted to measurements.
+//!
+//! `onchain/sample/tests/calibration.rs` runs the SBF build of the sample
+//! program under `solana-program-test` and records the compute units each
+//! decode consumed in `core/tests/fixtures/decode_cu.csv`:
+//!
+//! ```text
+//! cargo build-sbf --manifest-path onchain/sample/Cargo.toml
+//! BPF_OUT_DIR=target/deploy cargo test -p owlsol-sample-program \
+//!     --test calibration -- --ignored --nocapture
+//! ```
+//!
+//! The unit tests check [`DecodeCost::for_algorithm`] against every recorded
+//! point, so the coefficients cannot drift from the measurements, and fail
+//! while the fixture holds none.
+
+use crate::algorithms::hybrid::Segments;
+use crate::metadata::CompressionAlgorithm;
+use crate::rent::Rent;
+use crate::utils::read_u32_le;
+
+pub use crate::rent::ACCOUNT_STORAGE_OVERHEAD;
+
+/// Maximum compute units a single transaction may request
+pub const MAX_COMPUTE_UNITS: u64 = 1_400_000;
+/// Largest relative error between an estimate and a measured decode before
+/// the coefficients need refitting
+pub const CALIBRATION_TOLERANCE: f64 = 0.5;
+
+/// Fixed cost of walking the Hybrid segment table
+const HYBRID_BASE: f64 = 120.0;
+/// Table validation and dispatch per Hybrid segment
+const HYBRID_PER_SEGMENT: f64 = 90.0;
+
+/// Linear compute-unit model for one decoder
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecodeCost {
+    /// Fixed setup cost (headers, lookup tables)
+    pub base: f64,
+    /// Per payload byte read; for Huffman only the serialized tree counts
+    pub per_input_byte: f64,
+    /// Per output byte written
+    pub per_output_byte: f64,
+    /// Huffman only: per encoded bit walked through the tree
+    pub per_code_bit: f64,
+}
+
+impl DecodeCost {
+    /// Coefficients for `algorithm`, or `None` if it cannot be decoded on-chain
+    pub const fn for_algorithm(algorithm: CompressionAlgorithm) -> Option<Self> {
+        let cost = match algorithm {
+            // copy_from_slice lowers to the sol_memcpy syscall
+            CompressionAlgorithm::None => Self::linear(60.0, 0.0, 0.01),
+            // One token per input byte; runs are filled with sol_memset
+            CompressionAlgorithm::RunLength => Self::linear(80.0, 14.0, 0.05),
+            // Entry index setup, then one literal or reference per input byte
+            CompressionAlgorithm::Dictionary => Self::linear(150.0, 22.0, 0.1),
+            CompressionAlgorithm::Huffman => Self {
+                base: 200.0,
+                per_input_byte: 25.0,
+                per_output_byte: 6.0,
+                per_code_bit: 9.0,
+            },
+            _ => return None,
+        };
+        Some(cost)
+    }
+
+    const fn linear(base: f64, per_input_byte: f64, per_output_byte: f64) -> Self {
+        Self {
+            base,
+            per_input_byte,
+            per_output_byte,
+            per_code_bit: 0.0,
+        }
+    }
+
+    fn apply(&self, input_bytes: usize, output_bytes: usize, code_bits: usize) -> f64 {
+        self.base
+            + self.per_input_byte * input_bytes as f64
+            + self.per_output_byte * output_bytes as f64
+            + self.per_code_bit * code_bits as f64
+    }
+}
+
+/// Estimate the compute units needed to decode `payload` back to
+/// `original_len` bytes on-chain
+///
+/// Returns `None` for algorithms `owlsol-onchain` cannot decode and for
+/// payloads whose headers do not parse.
+pub fn estimate_decode_cu(
+    algorithm: CompressionAlgorithm,
+    payload: &[u8],
+    original_len: usize,
+) -> Option<u64> {
+    estimate(algorithm, payload, original_len).map(|cu| (cu + 0.5) as u64)
+}
+
+fn estimate(algorithm: CompressionAlgorithm, payload: &[u8], original_len: usize) -> Option<f64> {
+    match algorithm {
+        CompressionAlgorithm::Huffman => {
+            // [tree_size(4)][tree][encoded]: the tree is parsed once, then
+            // every encoded bit is one step down the tree
+            let tree_size = read_u32_le(payload, 0)? as usize;
+            let encoded = payload.len().checked_sub(4 + tree_size)?;
+            let cost = DecodeCost::for_algorithm(algorithm)?;
+            Some(cost.apply(tree_size, original_len, encoded * 8))
+        }
+        CompressionAlgorithm::Hybrid => {
+            let mut total = HYBRID_BASE;
+            for (segment, data) in Segments::parse(payload).ok()? {
+                total += HYBRID_PER_SEGMENT
+                    + estimate(segment.algorithm, data, segment.original_len as usize)?;
+            }
+            Some(total)
+        }
+        _ => DecodeCost::for_algorithm(algorithm)
+            .map(|cost| cost.apply(payload.len(), original_len, 0)),
+    }
+}
+
+/// Prices used to compare candidates by rent plus compute cost
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CostWeights {
+    /// Lamports locked per stored byte
+    pub lamports_per_byte: u64,
+    /// Priority fee per compute unit, in micro-lamports
+    pub micro_lamports_per_cu: u64,
+    /// Number of times the account is expected to be decoded on-chain
+    pub expected_decodes: u64,
+    /// Candidates whose decode exceeds this budget are rejected
+    pub max_compute_units: u64,
+}
+
+impl CostWeights {
+    /// Weights that price storage at `rent`'s exemption balance per byte
+    pub fn from_rent(rent: &Rent) -> Self {
+        Self {
+            lamports_per_byte: rent.minimum_balance(1) - rent.minimum_balance(0),
+            micro_lamports_per_cu: 1_000,
+            expected_decodes: 1,
+            max_compute_units: MAX_COMPUTE_UNITS,
+        }
+    }
+
+    /// Total lamports for storing `stored_bytes` and decoding them, or `None`
+    /// if the decode does not fit in the compute budget
+    pub fn total_lamports(&self, stored_bytes: usize, decode_cu: u64) -> Option<u64> {
+        if decode_cu > self.max_compute_units {
+            return None;
+        }
+
+        let rent =
+            (stored_bytes as u64 + ACCOUNT_STORAGE_OVERHEAD).saturating_mul(self.lamports_per_byte);
+        let compute = decode_cu
+            .saturating_mul(self.micro_lamports_per_cu)
+            .saturating_mul(self.expected_decodes)
+            / 1_000_000;
+        Some(rent.saturating_add(compute))
+    }
+}
+
+impl Default for CostWeights {
+    fn default() -> Self {
+        Self::from_rent(&Rent::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::{hybrid, rle};
+    use alloc::vec;
+
+    #[test]
+    fn test_offchain_algorithms_have_no_estimate() {
+        assert_eq!(
+            estimate_decode_cu(CompressionAlgorithm::Zstd, &[0; 16], 64),
+            None
+        );
+        assert_eq!(
+            estimate_decode_cu(CompressionAlgorithm::Huffman, &[9, 0], 64),
+            None
+        );
+        assert!(estimate_decode_cu(CompressionAlgorithm::None, &[0; 16], 16).is_some());
+    }
+
+    #[test]
+    fn test_huffman_cost_follows_code_bits() {
+        // Same tree, twice the encoded bits
+        let short = [2, 0, 0, 0, 0, 0, 0xAA, 0xAA];
+        let long = [2, 0, 0, 0, 0, 0, 0xAA, 0xAA, 0xAA, 0xAA];
+        let short_cu = estimate_decode_cu(CompressionAlgorithm::Huffman, &short, 16).unwrap();
+        let long_cu = estimate_decode_cu(CompressionAlgorithm::Huffman, &long, 16).unwrap();
+        assert_eq!(long_cu - short_cu, 16 * 9);
+    }
+
+    #[test]
+    fn test_hybrid_sums_segments() {
+        let zeros = vec![0u8; 200];
+        let rle = rle::compress(&zeros).unwrap();
+        let payload = hybrid::encode(&[
+            (CompressionAlgorithm::RunLength, zeros.len(), rle.clone()),
+            (CompressionAlgorithm::None, 4, vec![1, 2, 3, 4]),
+        ])
+        .unwrap();
+
+        let parts = estimate_decode_cu(CompressionAlgorithm::RunLength, &rle, 200).unwrap()
+            + estimate_decode_cu(CompressionAlgorithm::None, &[1, 2, 3, 4], 4).unwrap();
+        let total = estimate_decode_cu(CompressionAlgorithm::Hybrid, &payload, 204).unwrap();
+        assert!(total > parts);
+        assert!(estimate_decode_cu(CompressionAlgorithm::Hybrid, &payload[..6], 204).is_none());
+    }
+
+    /// Every point recorded by the SBF calibration run
+    #[test]
+    fn test_coefficients_match_measurements() {
+        let fixture = include_str!("../tests/fixtures/decode_cu.csv");
+        let mut lines = fixture
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        assert_eq!(
+            lines.next(),
+            Some("corpus,algorithm,input_bytes,output_bytes,code_bits,measured_cu")
+        );
+
+        let rows: Vec<&str> = lines.collect();
+        assert!(
+            !rows.is_empty(),
+            "decode_cu.csv has no measurements; run the calibration in the module docs and commit its output"
+        );
+        for line in rows {
+            let fields: Vec<&str> = line.split(',').collect();
+            let [corpus, name, input, output, bits, measured] = fields[..] else {
+                panic!("malformed fixture row: {}", line);
+            };
+            let algorithm = (0..=u8::MAX)
+                .map_while(CompressionAlgorithm::from_u8)
+                .find(|algo| algo.as_str() == name)
+                .unwrap();
+            let number = |field: &str| field.parse::<usize>().unwrap();
+            let estimate = DecodeCost::for_algorithm(algorithm).unwrap().apply(
+                number(input),
+                number(output),
+                number(bits),
+            );
+            let measured = number(measured) as f64;
+            let error = (estimate - measured) / measured.max(1.0);
+            assert!(
+                error.abs() <= CALIBRATION_TOLERANCE,
+                "{}/{}: estimated {:.0} CU, measured {:.0}",
+                corpus,
+                name,
+                estimate,
+                measured
+            );
+        }
+    }
+
+    #[test]
+    fn test_weights() {
+        let weights = CostWeights::default();
+        assert_eq!(
+            weights.total_lamports(0, 0),
+            Some(Rent::default().minimum_balance(0))
+        );
+        assert_eq!(
+            weights.total_lamports(165, 0),
+            Some(Rent::default().minimum_balance(165))
+        );
+        let custom = CostWeights::from_rent(&Rent::new(1_000, 1.0));
+        assert_eq!(custom.lamports_per_byte, 1_000);
+        assert_eq!(weights.total_lamports(0, MAX_COMPUTE_UNITS + 1), None);
+        assert!(weights.total_lamports(100, 0) < weights.total_lamports(101, 0));
+    }
+}
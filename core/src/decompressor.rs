@@ -1,7 +1,61 @@
-use crate::algorithms::{dict_decompress, rle_decompress, HuffmanCodec};
+use crate::algorithms::{dictionary, huffman, hybrid, rle};
 use crate::error::{CompressionError, Result};
-use crate::metadata::{CompressionAlgorithm, CompressionMetadata};
-use crate::utils::verify_checksum;
+use crate::metadata::{CompressionAlgorithm, CompressionMetadata, MAX_DATA_SIZE};
+use crate::utils::{calculate_checksum, verify_checksum};
+
+/// Limits applied before and during decompression of untrusted input
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecompressOptions {
+    /// Largest output, in bytes, the caller is willing to allocate
+    pub max_output_size: usize,
+    /// Largest accepted ratio of output size to input size
+    pub max_ratio: Option<f64>,
+}
+
+impl DecompressOptions {
+    pub fn new() -> Self {
+        Self {
+            max_output_size: MAX_DATA_SIZE,
+            max_ratio: None,
+        }
+    }
+
+    pub fn with_max_output_size(mut self, max_output_size: usize) -> Self {
+        self.max_output_size = max_output_size;
+        self
+    }
+
+    pub fn with_max_ratio(mut self, max_ratio: f64) -> Self {
+        self.max_ratio = Some(max_ratio);
+        self
+    }
+
+    /// Reject metadata whose declared size breaks a limit, before decoding
+    fn check(&self, data: &[u8], metadata: &CompressionMetadata) -> Result<()> {
+        let original_size = usize::try_from(metadata.original_size).unwrap_or(usize::MAX);
+        if original_size > self.max_output_size {
+            return Err(CompressionError::DataTooLarge {
+                size: original_size,
+                max: self.max_output_size,
+            });
+        }
+
+        if let Some(limit) = self.max_ratio {
+            let ratio = original_size as f64 / data.len().max(1) as f64;
+            if ratio > limit {
+                return Err(CompressionError::ExpansionTooLarge { ratio, max: limit });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for DecompressOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 pub struct Decompressor;
 
@@ -10,80 +64,265 @@ impl Decompressor {
         Self
     }
 
+    /// Decompress with the default limits (`MAX_DATA_SIZE` output, no ratio cap)
     pub fn decompress(&self, data: &[u8], metadata: &CompressionMetadata) -> Result<Vec<u8>> {
+        self.decompress_with(data, metadata, &DecompressOptions::default())
+    }
+
+    /// Decompress, rejecting input whose declared size breaks `options` before
+    /// any decoding work. Every decoder writes at most the declared size, so
+    /// the limits also bound memory use.
+    #[tracing::instrument(
+        name = "decompress",
+        skip_all,
+        fields(
+            algorithm = metadata.algorithm.as_str(),
+            compressed_size = data.len(),
+            original_size = metadata.original_size
+        )
+    )]
+    pub fn decompress_with(
+        &self,
+        data: &[u8],
+        metadata: &CompressionMetadata,
+        options: &DecompressOptions,
+    ) -> Result<Vec<u8>> {
         if !metadata.validate() {
             return Err(CompressionError::InvalidMetadata(
                 "Invalid compression metadata".into(),
             ));
         }
 
+        options.check(data, metadata)?;
+
         if metadata.checksum != 0 && !verify_checksum(data, metadata.checksum) {
-            return Err(CompressionError::CorruptedData("Checksum mismatch".into()));
+            return Err(CompressionError::ChecksumMismatch {
+                expected: metadata.checksum,
+                actual: calculate_checksum(data),
+            });
         }
 
         let decompressed = match metadata.algorithm {
-            CompressionAlgorithm::None => data.to_vec(),
-            CompressionAlgorithm::Huffman => self.decompress_huffman(data, metadata)?,
-            CompressionAlgorithm::Dictionary => self.decompress_dictionary(data)?,
-            CompressionAlgorithm::RunLength => self.decompress_rle(data)?,
-            CompressionAlgorithm::Lz4 => self.decompress_lz4(data)?,
-            CompressionAlgorithm::Zstd => self.decompress_zstd(data)?,
             CompressionAlgorithm::Hybrid => {
-                return Err(CompressionError::UnsupportedAlgorithm(
-                    "Hybrid algorithm should be stored as specific algorithm".into(),
-                ))
+                self.decompress_hybrid(data, metadata.original_size as usize, metadata.version)?
             }
+            algorithm => self.decompress_block(
+                algorithm,
+                data,
+                metadata.original_size as usize,
+                metadata.version,
+            )?,
         };
 
         if decompressed.len() != metadata.original_size as usize {
-            return Err(CompressionError::CorruptedData(format!(
-                "Size mismatch: expected {}, got {}",
-                metadata.original_size,
-                decompressed.len()
-            )));
+            tracing::debug!(decoded_len = decompressed.len(), "size mismatch");
+            return Err(CompressionError::SizeMismatch {
+                algorithm: metadata.algorithm,
+                expected: metadata.original_size as usize,
+                actual: decompressed.len(),
+            });
         }
 
         Ok(decompressed)
     }
 
-    fn decompress_huffman(&self, data: &[u8], metadata: &CompressionMetadata) -> Result<Vec<u8>> {
-        if data.len() < 4 {
-            return Err(CompressionError::InsufficientData);
+    /// Decode one block written with format `version`
+    fn decompress_block(
+        &self,
+        algorithm: CompressionAlgorithm,
+        data: &[u8],
+        original_len: usize,
+        version: u8,
+    ) -> Result<Vec<u8>> {
+        match algorithm {
+            CompressionAlgorithm::None => Ok(data.to_vec()),
+            CompressionAlgorithm::Huffman => self.decompress_huffman(data, original_len),
+            CompressionAlgorithm::Dictionary => self.decompress_dictionary(data, original_len),
+            CompressionAlgorithm::RunLength => self.decompress_rle(data, original_len, version),
+            #[cfg(feature = "lz4")]
+            CompressionAlgorithm::Lz4 => self.decompress_lz4(data, original_len),
+            #[cfg(feature = "zstd")]
+            CompressionAlgorithm::Zstd => self.decompress_zstd(data, original_len),
+            // Segments::parse rejects nested Hybrid segments
+            CompressionAlgorithm::Hybrid => Err(CompressionError::UnsupportedAlgorithm {
+                algorithm: CompressionAlgorithm::Hybrid,
+            }),
+            #[cfg(feature = "brotli")]
+            CompressionAlgorithm::Brotli => self.decompress_brotli(data, original_len),
+            #[cfg(feature = "deflate")]
+            CompressionAlgorithm::Deflate => self.decompress_deflate(data, original_len),
+            #[cfg(feature = "deflate")]
+            CompressionAlgorithm::Gzip => self.decompress_gzip(data, original_len),
+            #[cfg(feature = "snappy")]
+            CompressionAlgorithm::Snappy => self.decompress_snappy(data, original_len),
+            #[cfg(feature = "lzma")]
+            CompressionAlgorithm::Lzma => self.decompress_lzma(data, original_len),
+            #[allow(unreachable_patterns)]
+            other => Err(CompressionError::UnsupportedAlgorithm { algorithm: other }),
         }
+    }
 
-        let tree_size = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+    fn decompress_hybrid(&self, data: &[u8], original_len: usize, version: u8) -> Result<Vec<u8>> {
+        let segments = hybrid::Segments::parse(data)
+            .map_err(|err| CompressionError::corrupted(CompressionAlgorithm::Hybrid, err))?;
 
-        if data.len() < 4 + tree_size {
-            return Err(CompressionError::decompression_failed(
-                "Truncated tree data",
-            ));
+        // Check the table against the declared size before decoding anything,
+        // so a forged segment length cannot drive a huge allocation
+        let total = segments.clone().try_fold(0usize, |total, (segment, _)| {
+            total.checked_add(segment.original_len as usize)
+        });
+        if total != Some(original_len) {
+            return Err(CompressionError::SizeMismatch {
+                algorithm: CompressionAlgorithm::Hybrid,
+                expected: original_len,
+                actual: total.unwrap_or(usize::MAX),
+            });
+        }
+
+        // Segment decoders report offsets into their own payload; rebase
+        // them onto the whole Hybrid payload
+        let mut offset = data.len() - segments.clone().map(|(_, p)| p.len()).sum::<usize>();
+        let mut result = Vec::with_capacity(original_len);
+        for (segment, payload) in segments {
+            let block = self
+                .decompress_block(
+                    segment.algorithm,
+                    payload,
+                    segment.original_len as usize,
+                    version,
+                )
+                .map_err(|err| err.shifted(offset))?;
+
+            if block.len() != segment.original_len as usize {
+                return Err(CompressionError::SizeMismatch {
+                    algorithm: segment.algorithm,
+                    expected: segment.original_len as usize,
+                    actual: block.len(),
+                });
+            }
+
+            offset += payload.len();
+            result.extend_from_slice(&block);
         }
 
-        let mut codec = HuffmanCodec::new();
-        codec.deserialize_tree(&data[4..4 + tree_size])?;
+        Ok(result)
+    }
 
-        let encoded = &data[4 + tree_size..];
-        codec.decode(encoded, metadata.original_size as usize)
+    fn decompress_huffman(&self, data: &[u8], original_len: usize) -> Result<Vec<u8>> {
+        let mut decompressed = vec![0; original_len];
+        huffman::decompress_into(data, &mut decompressed)
+            .map_err(|err| CompressionError::corrupted(CompressionAlgorithm::Huffman, err))?;
+        Ok(decompressed)
     }
 
-    fn decompress_dictionary(&self, data: &[u8]) -> Result<Vec<u8>> {
-        dict_decompress(data)
+    fn decompress_dictionary(&self, data: &[u8], original_len: usize) -> Result<Vec<u8>> {
+        let mut decompressed = vec![0; original_len];
+        let written = dictionary::decompress_into(data, &mut decompressed)
+            .map_err(|err| CompressionError::corrupted(CompressionAlgorithm::Dictionary, err))?;
+        decompressed.truncate(written);
+        Ok(decompressed)
     }
 
-    fn decompress_rle(&self, data: &[u8]) -> Result<Vec<u8>> {
-        rle_decompress(data)
+    fn decompress_rle(&self, data: &[u8], original_len: usize, version: u8) -> Result<Vec<u8>> {
+        let mut decompressed = vec![0; original_len];
+        let written = if version == 1 {
+            rle::decompress_v1_into(data, &mut decompressed)
+        } else {
+            rle::decompress_into(data, &mut decompressed)
+        }
+        .map_err(|err| CompressionError::corrupted(CompressionAlgorithm::RunLength, err))?;
+        decompressed.truncate(written);
+        Ok(decompressed)
     }
 
-    fn decompress_lz4(&self, data: &[u8]) -> Result<Vec<u8>> {
+    #[cfg(feature = "lz4")]
+    fn decompress_lz4(&self, data: &[u8], original_len: usize) -> Result<Vec<u8>> {
         use lz4::block::decompress;
-        decompress(data, None)
-            .map_err(|e| CompressionError::decompression_failed(format!("LZ4 error: {}", e)))
+        let size = i32::try_from(original_len).map_err(|_| CompressionError::DataTooLarge {
+            size: original_len,
+            max: i32::MAX as usize,
+        })?;
+        decompress(data, Some(size)).map_err(|e| {
+            CompressionError::decompression_failed(CompressionAlgorithm::Lz4, e.to_string())
+        })
     }
 
-    fn decompress_zstd(&self, data: &[u8]) -> Result<Vec<u8>> {
-        zstd::bulk::decompress(data, 10 * 1024 * 1024)
-            .map_err(|e| CompressionError::decompression_failed(format!("Zstd error: {}", e)))
+    #[cfg(feature = "zstd")]
+    fn decompress_zstd(&self, data: &[u8], original_len: usize) -> Result<Vec<u8>> {
+        zstd::bulk::decompress(data, original_len).map_err(|e| {
+            CompressionError::decompression_failed(CompressionAlgorithm::Zstd, e.to_string())
+        })
     }
+
+    #[cfg(feature = "brotli")]
+    fn decompress_brotli(&self, data: &[u8], original_len: usize) -> Result<Vec<u8>> {
+        let decoder = brotli::Decompressor::new(data, 4096);
+        read_bounded(decoder, original_len, CompressionAlgorithm::Brotli)
+    }
+
+    #[cfg(feature = "deflate")]
+    fn decompress_deflate(&self, data: &[u8], original_len: usize) -> Result<Vec<u8>> {
+        read_bounded(
+            flate2::read::DeflateDecoder::new(data),
+            original_len,
+            CompressionAlgorithm::Deflate,
+        )
+    }
+
+    #[cfg(feature = "deflate")]
+    fn decompress_gzip(&self, data: &[u8], original_len: usize) -> Result<Vec<u8>> {
+        read_bounded(
+            flate2::read::GzDecoder::new(data),
+            original_len,
+            CompressionAlgorithm::Gzip,
+        )
+    }
+
+    #[cfg(feature = "snappy")]
+    fn decompress_snappy(&self, data: &[u8], original_len: usize) -> Result<Vec<u8>> {
+        // The raw format leads with the decoded length; check it before the
+        // decoder allocates that much
+        let snappy_error = |e: snap::Error| {
+            CompressionError::decompression_failed(CompressionAlgorithm::Snappy, e.to_string())
+        };
+        let declared = snap::raw::decompress_len(data).map_err(snappy_error)?;
+        if declared != original_len {
+            return Err(CompressionError::SizeMismatch {
+                algorithm: CompressionAlgorithm::Snappy,
+                expected: original_len,
+                actual: declared,
+            });
+        }
+        snap::raw::Decoder::new()
+            .decompress_vec(data)
+            .map_err(snappy_error)
+    }
+
+    #[cfg(feature = "lzma")]
+    fn decompress_lzma(&self, data: &[u8], original_len: usize) -> Result<Vec<u8>> {
+        read_bounded(
+            xz2::read::XzDecoder::new(data),
+            original_len,
+            CompressionAlgorithm::Lzma,
+        )
+    }
+}
+
+/// Drain a streaming decoder, reading at most one byte past `original_len` so
+/// oversized output shows up as a size mismatch instead of unbounded growth
+#[cfg(any(feature = "brotli", feature = "deflate", feature = "lzma"))]
+fn read_bounded(
+    reader: impl std::io::Read,
+    original_len: usize,
+    algorithm: CompressionAlgorithm,
+) -> Result<Vec<u8>> {
+    use std::io::Read;
+    let mut decompressed = Vec::with_capacity(original_len);
+    reader
+        .take(original_len as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| CompressionError::decompression_failed(algorithm, e.to_string()))?;
+    Ok(decompressed)
 }
 
 impl Default for Decompressor {
@@ -95,6 +334,8 @@ impl Default for Decompressor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::DecodeError;
+    use crate::metadata::COMPRESSION_VERSION;
     use crate::Compressor;
 
     #[test]
@@ -145,6 +386,236 @@ mod tests {
         assert_eq!(data, decompressed.as_slice());
     }
 
+    #[test]
+    #[cfg(feature = "lz4")]
+    fn test_roundtrip_lz4() {
+        let compressor = Compressor::new();
+        let decompressor = Decompressor::new();
+        let data = b"lz4 block lz4 block lz4 block lz4 block".repeat(8);
+
+        let result = compressor
+            .compress_with_algorithm(&data, Some(CompressionAlgorithm::Lz4))
+            .unwrap();
+        let decompressed = decompressor
+            .decompress(&result.data, &result.metadata)
+            .unwrap();
+
+        assert_eq!(data, decompressed);
+    }
+
+    #[test]
+    fn test_roundtrip_optional_backends() {
+        let compressor = Compressor::new();
+        let decompressor = Decompressor::new();
+        let data =
+            br#"{"name":"Owl #1","symbol":"OWL","uri":"https://example.com/1.json"}"#.repeat(4);
+
+        for algo in [
+            CompressionAlgorithm::Brotli,
+            CompressionAlgorithm::Deflate,
+            CompressionAlgorithm::Gzip,
+            CompressionAlgorithm::Snappy,
+            CompressionAlgorithm::Lzma,
+        ] {
+            let result = compressor.compress_with_algorithm(&data, Some(algo));
+            if !algo.is_available() {
+                assert!(matches!(
+                    result,
+                    Err(CompressionError::UnsupportedAlgorithm { .. })
+                ));
+                continue;
+            }
+
+            let result = result.unwrap();
+            assert_eq!(result.metadata.algorithm, algo);
+            let decompressed = decompressor
+                .decompress(&result.data, &result.metadata)
+                .unwrap();
+            assert_eq!(data, decompressed);
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_hybrid() {
+        let compressor = Compressor::new();
+        let decompressor = Decompressor::new();
+        let mut data = b"account header: owner, mint, amount, delegate".repeat(4);
+        data.extend(vec![0u8; 2000]);
+
+        let result = compressor
+            .compress_with_algorithm(&data, Some(CompressionAlgorithm::Hybrid))
+            .unwrap();
+        assert_eq!(result.metadata.algorithm, CompressionAlgorithm::Hybrid);
+
+        let (segments, _) = hybrid::read_table(&result.data).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[1].original_len, 2000);
+        assert!(segments[1].compressed_len < 32);
+
+        let decompressed = decompressor
+            .decompress(&result.data, &result.metadata)
+            .unwrap();
+        assert_eq!(data, decompressed);
+    }
+
+    #[test]
+    fn test_corrupted_hybrid_table() {
+        let decompressor = Decompressor::new();
+        let data = hybrid::encode(&[(CompressionAlgorithm::None, 8, b"abcd".to_vec())]).unwrap();
+        let metadata = CompressionMetadata::new(CompressionAlgorithm::Hybrid, 8, data.len() as u64);
+
+        assert!(decompressor.decompress(&data, &metadata).is_err());
+    }
+
+    #[test]
+    fn test_hybrid_table_must_match_original_size() {
+        let decompressor = Decompressor::new();
+        // A Huffman segment claiming 4 GiB of output
+        let data =
+            hybrid::encode(&[(CompressionAlgorithm::Huffman, u32::MAX as usize, vec![0; 8])])
+                .unwrap();
+        let metadata =
+            CompressionMetadata::new(CompressionAlgorithm::Hybrid, 64, data.len() as u64);
+
+        assert!(matches!(
+            decompressor.decompress(&data, &metadata),
+            Err(CompressionError::SizeMismatch {
+                algorithm: CompressionAlgorithm::Hybrid,
+                expected: 64,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_structured_errors() {
+        let decompressor = Decompressor::new();
+        let result = Compressor::new()
+            .compress_with_algorithm(&[3u8; 64], Some(CompressionAlgorithm::RunLength))
+            .unwrap();
+        let mut metadata = result.metadata.clone();
+        metadata.checksum ^= 1;
+        assert!(matches!(
+            decompressor.decompress(&result.data, &metadata),
+            Err(CompressionError::ChecksumMismatch { expected, actual })
+                if expected == metadata.checksum && actual == result.metadata.checksum
+        ));
+
+        // A run cut short in the second segment is reported at its offset in
+        // the whole payload: 4-byte count, two 9-byte entries, 4-byte literal
+        let data = hybrid::encode(&[
+            (CompressionAlgorithm::None, 4, b"abcd".to_vec()),
+            (CompressionAlgorithm::RunLength, 5, vec![0xFF, 5]),
+        ])
+        .unwrap();
+        let metadata = CompressionMetadata::new(CompressionAlgorithm::Hybrid, 9, data.len() as u64);
+        let err = decompressor.decompress(&data, &metadata).unwrap_err();
+        assert_eq!(err.code(), 311);
+        assert_eq!(err.algorithm(), Some(CompressionAlgorithm::RunLength));
+        assert_eq!(err.offset(), Some(26));
+    }
+
+    #[test]
+    fn test_backend_output_beyond_declared_size() {
+        let compressor = Compressor::new();
+        let decompressor = Decompressor::new();
+        let data = vec![b'x'; 4096];
+
+        for algo in [
+            CompressionAlgorithm::Deflate,
+            CompressionAlgorithm::Snappy,
+            CompressionAlgorithm::Lzma,
+        ] {
+            if !algo.is_available() {
+                continue;
+            }
+            let result = compressor
+                .compress_with_algorithm(&data, Some(algo))
+                .unwrap();
+            let mut metadata = result.metadata.clone();
+            metadata.original_size = 16;
+
+            assert!(decompressor.decompress(&result.data, &metadata).is_err());
+        }
+    }
+
+    #[test]
+    fn test_decompress_limits() {
+        let decompressor = Decompressor::new();
+
+        // Declared size is rejected before the (garbage) payload is looked at
+        let metadata = CompressionMetadata::new(CompressionAlgorithm::RunLength, 1000, 2);
+        let options = DecompressOptions::new().with_max_output_size(100);
+        assert!(matches!(
+            decompressor.decompress_with(b"xx", &metadata, &options),
+            Err(CompressionError::DataTooLarge {
+                size: 1000,
+                max: 100
+            })
+        ));
+
+        let data = vec![0u8; 10_000];
+        let result = Compressor::new()
+            .compress_with_algorithm(&data, Some(CompressionAlgorithm::RunLength))
+            .unwrap();
+        let strict = DecompressOptions::new().with_max_ratio(10.0);
+        assert!(matches!(
+            decompressor.decompress_with(&result.data, &result.metadata, &strict),
+            Err(CompressionError::ExpansionTooLarge { .. })
+        ));
+        let relaxed = DecompressOptions::new().with_max_ratio(100.0);
+        assert_eq!(
+            decompressor
+                .decompress_with(&result.data, &result.metadata, &relaxed)
+                .unwrap(),
+            data
+        );
+    }
+
+    #[test]
+    fn test_rle_output_bounded_by_declared_size() {
+        let decompressor = Decompressor::new();
+        // Three max-length runs claiming to be 16 bytes
+        let data = [0xFF, 255, 1, 0xFF, 255, 2, 0xFF, 255, 3];
+        let metadata = CompressionMetadata::new(CompressionAlgorithm::RunLength, 16, 9);
+
+        assert!(matches!(
+            decompressor.decompress(&data, &metadata),
+            Err(CompressionError::Corrupted {
+                algorithm: CompressionAlgorithm::RunLength,
+                source: DecodeError::OutputOverflow,
+            })
+        ));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_capacity_follows_limit() {
+        let decompressor = Decompressor::new();
+        let data = vec![7u8; MAX_DATA_SIZE + 1024];
+        let compressed = zstd::encode_all(data.as_slice(), 1).unwrap();
+        let metadata = CompressionMetadata::new(
+            CompressionAlgorithm::Zstd,
+            data.len() as u64,
+            compressed.len() as u64,
+        );
+
+        assert!(matches!(
+            decompressor.decompress(&compressed, &metadata),
+            Err(CompressionError::DataTooLarge {
+                max: MAX_DATA_SIZE,
+                ..
+            })
+        ));
+        let options = DecompressOptions::new().with_max_output_size(2 * MAX_DATA_SIZE);
+        assert_eq!(
+            decompressor
+                .decompress_with(&compressed, &metadata, &options)
+                .unwrap(),
+            data
+        );
+    }
+
     #[test]
     fn test_invalid_metadata() {
         let decompressor = Decompressor::new();
@@ -154,4 +625,21 @@ mod tests {
         let result = decompressor.decompress(b"data", &metadata);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_v1_rle() {
+        // [marker][byte][count], as format version 1 wrote runs
+        let payload = [0xFF, b'a', 5, b'b'];
+        let mut metadata = CompressionMetadata::new(CompressionAlgorithm::RunLength, 6, 4);
+        metadata.version = 1;
+        let decompressor = Decompressor::new();
+        assert_eq!(
+            decompressor.decompress(&payload, &metadata).unwrap(),
+            b"aaaaab"
+        );
+
+        // The same bytes under the current version are a 97-byte run
+        metadata.version = COMPRESSION_VERSION;
+        assert!(decompressor.decompress(&payload, &metadata).is_err());
+    }
 }
@@ -0,0 +1,273 @@
+//! Authenticated encryption of compressed payloads
+//!
+//! Encrypts the compressed bytes with ChaCha20-Poly1305, keyed either by a raw
+//! 32-byte key or by Argon2id over a passphrase. The salt, nonce and KDF
+//! parameters live in the container header, and the whole header (apart from
+//! any signature block added later) is bound as associated data so none of it
+//! can be altered without failing authentication.
+//!
+//! Key agreement schemes that live outside this crate (such as sealing to a
+//! Solana wallet) use [`encrypt_with_key`] and [`decrypt_with_key`] with a key
+//! they derived themselves.
+
+use crate::container::{Cipher, Container, EncryptionHeader, Kdf, NONCE_LEN, SALT_LEN};
+use crate::error::{CompressionError, Result};
+use crate::metadata::CompressionResult;
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+
+pub const KEY_LEN: usize = 32;
+
+/// Largest Argon2 memory cost accepted from a header (1 GiB), so a forged
+/// header cannot make decryption allocate without bound
+const MAX_M_COST: u32 = 1 << 20;
+/// Largest Argon2 iteration count accepted from a header
+const MAX_T_COST: u32 = 64;
+/// Largest Argon2 parallelism accepted from a header
+const MAX_P_COST: u32 = 16;
+
+/// Key material for encrypting or decrypting a container
+pub enum Key {
+    Raw([u8; KEY_LEN]),
+    Passphrase(String),
+}
+
+impl Key {
+    /// A raw key, which must be exactly 32 bytes
+    pub fn raw(bytes: &[u8]) -> Result<Self> {
+        let key = bytes.try_into().map_err(|_| {
+            CompressionError::invalid_input(format!(
+                "Key must be {} bytes, got {}",
+                KEY_LEN,
+                bytes.len()
+            ))
+        })?;
+        Ok(Self::Raw(key))
+    }
+
+    pub fn passphrase<S: Into<String>>(passphrase: S) -> Self {
+        Self::Passphrase(passphrase.into())
+    }
+}
+
+/// Argon2id cost parameters used when encrypting with a passphrase
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argon2Params {
+    /// Memory in KiB
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl Default for Argon2Params {
+    fn default() -> Self {
+        Self {
+            m_cost: argon2::Params::DEFAULT_M_COST,
+            t_cost: argon2::Params::DEFAULT_T_COST,
+            p_cost: argon2::Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// Encrypt a compression result with the default Argon2 parameters
+pub fn encrypt(result: &CompressionResult, key: &Key) -> Result<Container> {
+    encrypt_with(result, key, Argon2Params::default())
+}
+
+/// Encrypt a compression result into a container; `params` only apply to
+/// passphrase keys
+pub fn encrypt_with(
+    result: &CompressionResult,
+    key: &Key,
+    params: Argon2Params,
+) -> Result<Container> {
+    let salt = random_bytes()?;
+    let kdf = match key {
+        Key::Raw(_) => Kdf::RawKey,
+        Key::Passphrase(_) => Kdf::Argon2id {
+            m_cost: params.m_cost,
+            t_cost: params.t_cost,
+            p_cost: params.p_cost,
+        },
+    };
+    encrypt_with_key(result, &derive_key(key, &kdf, &salt)?, kdf, salt)
+}
+
+/// Encrypt with a key the caller derived itself, recording `kdf` and `salt`
+/// in the header so the reader can derive it again
+pub fn encrypt_with_key(
+    result: &CompressionResult,
+    key: &[u8; KEY_LEN],
+    kdf: Kdf,
+    salt: [u8; SALT_LEN],
+) -> Result<Container> {
+    let nonce = random_bytes::<NONCE_LEN>()?;
+    let mut container = Container {
+        metadata: result.metadata.clone(),
+        encryption: Some(EncryptionHeader {
+            cipher: Cipher::ChaCha20Poly1305,
+            kdf,
+            salt,
+            nonce,
+        }),
+        payload: Vec::new(),
+        signature: None,
+    };
+
+    let cipher = ChaCha20Poly1305::new(key.into());
+    let aad = container.unsigned_header_bytes();
+    container.payload = cipher
+        .encrypt(
+            &nonce.into(),
+            Payload {
+                msg: &result.data,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| CompressionError::invalid_input("Payload too large to encrypt"))?;
+
+    tracing::debug!(kdf = kdf.as_str(), "payload encrypted");
+    Ok(container)
+}
+
+/// Decrypt a container's payload back to the compressed bytes
+///
+/// Fails with [`CompressionError::WrongKey`] if the key is wrong or the header
+/// or payload were modified.
+pub fn decrypt(container: &Container, key: &Key) -> Result<Vec<u8>> {
+    let Some(encryption) = &container.encryption else {
+        return Ok(container.payload.clone());
+    };
+    decrypt_with_key(
+        container,
+        &derive_key(key, &encryption.kdf, &encryption.salt)?,
+    )
+}
+
+/// Decrypt with a key the caller derived from the header's KDF parameters
+pub fn decrypt_with_key(container: &Container, key: &[u8; KEY_LEN]) -> Result<Vec<u8>> {
+    let Some(encryption) = &container.encryption else {
+        return Ok(container.payload.clone());
+    };
+
+    let cipher = match encryption.cipher {
+        Cipher::ChaCha20Poly1305 => ChaCha20Poly1305::new(key.into()),
+    };
+    let aad = container.unsigned_header_bytes();
+    cipher
+        .decrypt(
+            &encryption.nonce.into(),
+            Payload {
+                msg: &container.payload,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| CompressionError::WrongKey)
+}
+
+/// `N` bytes from the operating system's random number generator
+pub fn random_bytes<const N: usize>() -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes).map_err(std::io::Error::from)?;
+    Ok(bytes)
+}
+
+fn derive_key(key: &Key, kdf: &Kdf, salt: &[u8; SALT_LEN]) -> Result<[u8; KEY_LEN]> {
+    match (key, kdf) {
+        (Key::Raw(raw), Kdf::RawKey) => Ok(*raw),
+        (
+            Key::Passphrase(passphrase),
+            Kdf::Argon2id {
+                m_cost,
+                t_cost,
+                p_cost,
+            },
+        ) => {
+            if *m_cost > MAX_M_COST || *t_cost > MAX_T_COST || *p_cost > MAX_P_COST {
+                return Err(CompressionError::InvalidMetadata(format!(
+                    "Argon2 parameters m={} t={} p={} exceed the accepted limits",
+                    m_cost, t_cost, p_cost
+                )));
+            }
+            let params = argon2::Params::new(*m_cost, *t_cost, *p_cost, Some(KEY_LEN))
+                .map_err(|e| CompressionError::InvalidMetadata(format!("Argon2: {}", e)))?;
+            let mut derived = [0u8; KEY_LEN];
+            argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+                .hash_password_into(passphrase.as_bytes(), salt, &mut derived)
+                .map_err(|e| CompressionError::invalid_input(format!("Argon2: {}", e)))?;
+            Ok(derived)
+        }
+        (Key::Raw(_), Kdf::Argon2id { .. }) => Err(CompressionError::invalid_input(
+            "Data was encrypted with a passphrase, not a key file",
+        )),
+        (Key::Passphrase(_), Kdf::RawKey) => Err(CompressionError::invalid_input(
+            "Data was encrypted with a key file, not a passphrase",
+        )),
+        (_, Kdf::X25519 { .. }) => Err(CompressionError::invalid_input(
+            "Data was sealed to a Solana public key; open it with the recipient keypair",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Compressor, Decompressor};
+
+    /// Cheap parameters so the tests don't spend their time in Argon2
+    const FAST: Argon2Params = Argon2Params {
+        m_cost: 64,
+        t_cost: 1,
+        p_cost: 1,
+    };
+
+    fn sample() -> (Vec<u8>, CompressionResult) {
+        let data = b"secret account data, secret account data".repeat(16);
+        let result = Compressor::new().compress(&data).unwrap();
+        (data, result)
+    }
+
+    #[test]
+    fn test_passphrase_roundtrip() {
+        let (data, result) = sample();
+        let key = Key::passphrase("correct horse battery staple");
+        let bytes = encrypt_with(&result, &key, FAST).unwrap().to_bytes();
+
+        let container = Container::from_bytes(&bytes).unwrap();
+        assert!(matches!(
+            container.compressed_payload(),
+            Err(CompressionError::KeyRequired)
+        ));
+        let compressed = decrypt(&container, &key).unwrap();
+        let decompressed = Decompressor::new()
+            .decompress(&compressed, &container.metadata)
+            .unwrap();
+        assert_eq!(decompressed, data);
+
+        assert!(matches!(
+            decrypt(&container, &Key::passphrase("wrong")),
+            Err(CompressionError::WrongKey)
+        ));
+    }
+
+    #[test]
+    fn test_raw_key_and_tampering() {
+        let (_, result) = sample();
+        let key = Key::raw(&[7u8; KEY_LEN]).unwrap();
+        let container = encrypt(&result, &key).unwrap();
+        assert_eq!(decrypt(&container, &key).unwrap(), result.data);
+
+        assert!(matches!(
+            decrypt(&container, &Key::Raw([8u8; KEY_LEN])),
+            Err(CompressionError::WrongKey)
+        ));
+        // The header is authenticated along with the payload
+        let mut tampered = container.clone();
+        tampered.metadata.original_size += 1;
+        assert!(matches!(
+            decrypt(&tampered, &key),
+            Err(CompressionError::WrongKey)
+        ));
+        assert!(Key::raw(&[0u8; 16]).is_err());
+    }
+}
@@ -1,38 +1,170 @@
+use crate::metadata::CompressionAlgorithm;
+use alloc::string::String;
 use thiserror::Error;
 
-pub type Result<T> = std::result::Result<T, CompressionError>;
+pub type Result<T> = core::result::Result<T, CompressionError>;
 
+/// Every error `owlsol-core` returns. Each variant has a stable numeric
+/// [`code`](CompressionError::code) whose hundreds digit is its
+/// [`ErrorKind`]; codes are never reused or renumbered.
 #[derive(Error, Debug)]
 pub enum CompressionError {
     #[error("Invalid input: {0}")]
     InvalidInput(String),
 
-    #[error("Compression failed: {0}")]
-    CompressionFailed(String),
+    #[error("Data too large: {size} bytes (max: {max} bytes)")]
+    DataTooLarge { size: usize, max: usize },
 
-    #[error("Decompression failed: {0}")]
-    DecompressionFailed(String),
+    #[error("Expansion ratio too large: {ratio:.1}x (max: {max:.1}x)")]
+    ExpansionTooLarge { ratio: f64, max: f64 },
 
-    #[error("Unsupported algorithm: {0}")]
-    UnsupportedAlgorithm(String),
+    #[cfg(feature = "serde")]
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+
+    #[error("Unsupported algorithm: {} support is not compiled in", algorithm.as_str())]
+    UnsupportedAlgorithm { algorithm: CompressionAlgorithm },
+
+    #[error("No candidate fits the compute unit budget of {max_compute_units}")]
+    ComputeBudgetExceeded { max_compute_units: u64 },
 
     #[error("Invalid metadata: {0}")]
     InvalidMetadata(String),
 
-    #[error("Data too large: {0} bytes (max: {1} bytes)")]
-    DataTooLarge(usize, usize),
+    #[error("Checksum mismatch: expected {expected:08x}, got {actual:08x}")]
+    ChecksumMismatch { expected: u32, actual: u32 },
+
+    #[error("{} size mismatch: expected {expected} bytes, got {actual}", algorithm.as_str())]
+    SizeMismatch {
+        algorithm: CompressionAlgorithm,
+        expected: usize,
+        actual: usize,
+    },
+
+    #[error("Corrupted {} data: {source}", algorithm.as_str())]
+    Corrupted {
+        algorithm: CompressionAlgorithm,
+        source: DecodeError,
+    },
 
+    #[error("{} compression failed: {message}", algorithm.as_str())]
+    CompressionFailed {
+        algorithm: CompressionAlgorithm,
+        message: String,
+    },
+
+    #[error("{} decompression failed: {message}", algorithm.as_str())]
+    DecompressionFailed {
+        algorithm: CompressionAlgorithm,
+        message: String,
+    },
+
+    #[cfg(feature = "std")]
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
-    #[error("Serialization error: {0}")]
-    SerializationError(#[from] serde_json::Error),
+    #[error("Decryption failed: wrong key or tampered data")]
+    WrongKey,
+
+    #[error("Payload is encrypted and no key was given")]
+    KeyRequired,
+
+    #[error(
+        "Signature verification failed: the container was modified or the signature is invalid"
+    )]
+    InvalidSignature,
 
-    #[error("Insufficient data for decompression")]
-    InsufficientData,
+    #[error("Container is not signed")]
+    NotSigned,
 
-    #[error("Corrupted data: {0}")]
-    CorruptedData(String),
+    #[error("Container is signed by a different key")]
+    UnexpectedSigner {
+        expected: [u8; 32],
+        actual: [u8; 32],
+    },
+}
+
+/// Broad class of a [`CompressionError`], for callers that only need to
+/// know what went wrong rather than exactly where
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The caller passed data or limits the library refuses (1xx)
+    InvalidInput,
+    /// The algorithm is not compiled in or cannot meet the constraints (2xx)
+    Unsupported,
+    /// The compressed data or its metadata is damaged (3xx)
+    Corrupted,
+    /// A third-party codec reported an error (4xx)
+    Backend,
+    /// Reading or writing failed (5xx)
+    Io,
+    /// A key or signature is missing or does not match the data (6xx)
+    Authentication,
+}
+
+/// Allocation-free error returned by the bounded decoders (`decompress_into`
+/// and friends) used by on-chain programs. Offsets are byte positions in the
+/// input passed to the decoder.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    #[error("Unexpected end of data at offset {offset}")]
+    Truncated { offset: usize },
+
+    #[error("Decoded data does not fit the output buffer")]
+    OutputOverflow,
+
+    #[error("Missing dictionary entry for id {id} at offset {offset}")]
+    MissingEntry { id: u16, offset: usize },
+
+    #[error("Invalid Huffman tree")]
+    InvalidTree,
+
+    #[error("Invalid hybrid segment {index}")]
+    InvalidSegment { index: usize },
+
+    #[error("Hybrid segment payloads do not match the segment table")]
+    InvalidSegmentTable,
+
+    #[error("Invalid dictionary entry id {id}")]
+    InvalidEntry { id: u16 },
+}
+
+impl DecodeError {
+    /// Stable code, also used (offset by 9) for on-chain program errors
+    pub const fn code(&self) -> u32 {
+        match self {
+            Self::Truncated { .. } => 1,
+            Self::OutputOverflow => 2,
+            Self::MissingEntry { .. } => 3,
+            Self::InvalidTree => 4,
+            Self::InvalidSegment { .. } => 5,
+            Self::InvalidSegmentTable => 6,
+            Self::InvalidEntry { .. } => 7,
+        }
+    }
+
+    /// Byte offset in the input where decoding failed, if known
+    pub const fn offset(&self) -> Option<usize> {
+        match self {
+            Self::Truncated { offset } | Self::MissingEntry { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// Rebase the offset onto an enclosing buffer the input starts `base`
+    /// bytes into
+    pub(crate) const fn shifted(self, base: usize) -> Self {
+        match self {
+            Self::Truncated { offset } => Self::Truncated {
+                offset: offset + base,
+            },
+            Self::MissingEntry { id, offset } => Self::MissingEntry {
+                id,
+                offset: offset + base,
+            },
+            other => other,
+        }
+    }
 }
 
 impl CompressionError {
@@ -40,11 +172,141 @@ impl CompressionError {
         Self::InvalidInput(msg.into())
     }
 
-    pub fn compression_failed<S: Into<String>>(msg: S) -> Self {
-        Self::CompressionFailed(msg.into())
+    pub fn compression_failed<S: Into<String>>(algorithm: CompressionAlgorithm, msg: S) -> Self {
+        Self::CompressionFailed {
+            algorithm,
+            message: msg.into(),
+        }
+    }
+
+    pub fn decompression_failed<S: Into<String>>(algorithm: CompressionAlgorithm, msg: S) -> Self {
+        Self::DecompressionFailed {
+            algorithm,
+            message: msg.into(),
+        }
+    }
+
+    pub fn corrupted(algorithm: CompressionAlgorithm, source: DecodeError) -> Self {
+        Self::Corrupted { algorithm, source }
+    }
+
+    /// Stable numeric code identifying the variant
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::InvalidInput(_) => 100,
+            Self::DataTooLarge { .. } => 101,
+            Self::ExpansionTooLarge { .. } => 102,
+            #[cfg(feature = "serde")]
+            Self::SerializationError(_) => 103,
+            Self::UnsupportedAlgorithm { .. } => 200,
+            Self::ComputeBudgetExceeded { .. } => 201,
+            Self::InvalidMetadata(_) => 300,
+            Self::ChecksumMismatch { .. } => 301,
+            Self::SizeMismatch { .. } => 302,
+            Self::Corrupted { source, .. } => 310 + source.code(),
+            Self::CompressionFailed { .. } => 400,
+            Self::DecompressionFailed { .. } => 401,
+            #[cfg(feature = "std")]
+            Self::IoError(_) => 500,
+            Self::WrongKey => 600,
+            Self::KeyRequired => 601,
+            Self::InvalidSignature => 602,
+            Self::NotSigned => 603,
+            Self::UnexpectedSigner { .. } => 604,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self.code() / 100 {
+            1 => ErrorKind::InvalidInput,
+            2 => ErrorKind::Unsupported,
+            3 => ErrorKind::Corrupted,
+            4 => ErrorKind::Backend,
+            6 => ErrorKind::Authentication,
+            _ => ErrorKind::Io,
+        }
+    }
+
+    /// Algorithm whose encoder or decoder failed, if the error concerns one
+    pub fn algorithm(&self) -> Option<CompressionAlgorithm> {
+        match self {
+            Self::UnsupportedAlgorithm { algorithm }
+            | Self::SizeMismatch { algorithm, .. }
+            | Self::Corrupted { algorithm, .. }
+            | Self::CompressionFailed { algorithm, .. }
+            | Self::DecompressionFailed { algorithm, .. } => Some(*algorithm),
+            _ => None,
+        }
+    }
+
+    /// Byte offset in the compressed data where decoding failed, if known
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Self::Corrupted { source, .. } => source.offset(),
+            _ => None,
+        }
+    }
+
+    /// Rebase a decode offset onto an enclosing buffer
+    #[cfg(feature = "std")]
+    pub(crate) fn shifted(self, base: usize) -> Self {
+        match self {
+            Self::Corrupted { algorithm, source } => Self::Corrupted {
+                algorithm,
+                source: source.shifted(base),
+            },
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_match_kinds() {
+        let errors = [
+            CompressionError::invalid_input("empty"),
+            CompressionError::UnsupportedAlgorithm {
+                algorithm: CompressionAlgorithm::Zstd,
+            },
+            CompressionError::ChecksumMismatch {
+                expected: 1,
+                actual: 2,
+            },
+            CompressionError::corrupted(
+                CompressionAlgorithm::RunLength,
+                DecodeError::Truncated { offset: 7 },
+            ),
+            CompressionError::decompression_failed(CompressionAlgorithm::Lz4, "bad block"),
+        ];
+        let kinds = [
+            ErrorKind::InvalidInput,
+            ErrorKind::Unsupported,
+            ErrorKind::Corrupted,
+            ErrorKind::Corrupted,
+            ErrorKind::Backend,
+        ];
+        for (error, kind) in errors.iter().zip(kinds) {
+            assert_eq!(error.kind(), kind, "{}", error);
+        }
+        assert_eq!(errors[3].code(), 311);
+        assert_eq!(errors[3].offset(), Some(7));
+        assert_eq!(errors[3].algorithm(), Some(CompressionAlgorithm::RunLength));
     }
 
-    pub fn decompression_failed<S: Into<String>>(msg: S) -> Self {
-        Self::DecompressionFailed(msg.into())
+    #[test]
+    fn test_shifted_offsets() {
+        let error = CompressionError::corrupted(
+            CompressionAlgorithm::Dictionary,
+            DecodeError::MissingEntry { id: 3, offset: 10 },
+        )
+        .shifted(32);
+        assert_eq!(error.offset(), Some(42));
+        assert_eq!(
+            DecodeError::OutputOverflow.shifted(32),
+            DecodeError::OutputOverflow
+        );
     }
 }
@@ -0,0 +1,421 @@
+//! Look inside compressed payloads
+//!
+//! Breaks a payload down the way its codec sees it: the Huffman code lengths,
+//! the dictionary table with how often each entry is referenced, RLE token
+//! counts, LZ4 block sequences and Zstandard frame headers. Hybrid payloads
+//! get one section per segment. Nothing here decodes the data itself, so a
+//! payload that fails to decompress can still be inspected up to the point
+//! where it breaks.
+
+use crate::algorithms::dictionary::{self, DictionaryStats};
+use crate::algorithms::huffman::HuffmanCodec;
+use crate::algorithms::hybrid::Segments;
+use crate::algorithms::rle::{self, RleStats};
+use crate::error::{CompressionError, DecodeError, Result};
+use crate::metadata::{CompressionAlgorithm, CompressionMetadata};
+use crate::utils::read_u32_le;
+use alloc::format;
+use alloc::vec::Vec;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+/// Standard Zstandard frame magic
+const ZSTD_MAGIC: u32 = 0xFD2F_B528;
+/// Skippable frames use magics 0x184D2A50..=0x184D2A5F
+const ZSTD_SKIPPABLE_MAGIC: u32 = 0x184D_2A50;
+
+/// One codec's share of a payload: the whole payload, or one hybrid segment
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Section {
+    pub algorithm: CompressionAlgorithm,
+    pub original_len: u64,
+    pub compressed_len: u64,
+    pub details: Details,
+}
+
+/// Codec-specific internals of a section
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize),
+    serde(tag = "kind", rename_all = "snake_case")
+)]
+pub enum Details {
+    Huffman(HuffmanInfo),
+    Dictionary(DictionaryStats),
+    RunLength(RleStats),
+    Lz4(Lz4Info),
+    Zstd(ZstdInfo),
+    /// Stored data, or a backend whose format is not broken down
+    Opaque,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct HuffmanInfo {
+    /// Serialized tree size in bytes
+    pub tree_len: usize,
+    /// Encoded bitstream size in bytes
+    pub encoded_len: usize,
+    /// Code length in bits of each symbol in the tree, by symbol
+    pub code_lengths: Vec<CodeLength>,
+    /// Mean length over the symbols in the tree, unweighted
+    pub avg_code_length: f64,
+    /// Bitstream bits spent per decoded byte
+    pub bits_per_symbol: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CodeLength {
+    pub symbol: u8,
+    pub bits: usize,
+}
+
+/// Sequences of a raw LZ4 block
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Lz4Info {
+    pub sequences: usize,
+    pub literal_bytes: usize,
+    pub match_bytes: usize,
+    pub longest_match: usize,
+    pub max_offset: u16,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ZstdInfo {
+    pub frames: Vec<ZstdFrame>,
+    pub skippable_frames: usize,
+}
+
+/// Header fields and block counts of one Zstandard frame
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ZstdFrame {
+    pub window_size: Option<u64>,
+    pub content_size: Option<u64>,
+    pub dictionary_id: Option<u32>,
+    pub single_segment: bool,
+    pub checksum: bool,
+    pub raw_blocks: usize,
+    pub rle_blocks: usize,
+    pub compressed_blocks: usize,
+}
+
+/// Break `data`, compressed as described by `metadata`, into sections
+pub fn inspect(data: &[u8], metadata: &CompressionMetadata) -> Result<Vec<Section>> {
+    if metadata.algorithm != CompressionAlgorithm::Hybrid {
+        return Ok(alloc::vec![section(
+            metadata.algorithm,
+            metadata.original_size,
+            data
+        )?]);
+    }
+
+    Segments::parse(data)
+        .map_err(|err| CompressionError::corrupted(CompressionAlgorithm::Hybrid, err))?
+        .map(|(segment, payload)| section(segment.algorithm, segment.original_len as u64, payload))
+        .collect()
+}
+
+fn section(algorithm: CompressionAlgorithm, original_len: u64, data: &[u8]) -> Result<Section> {
+    let corrupted = |err| CompressionError::corrupted(algorithm, err);
+    let details = match algorithm {
+        CompressionAlgorithm::Huffman => Details::Huffman(huffman(data, original_len)?),
+        CompressionAlgorithm::Dictionary => {
+            Details::Dictionary(dictionary::stats(data).map_err(corrupted)?)
+        }
+        CompressionAlgorithm::RunLength => Details::RunLength(rle::stats(data).map_err(corrupted)?),
+        CompressionAlgorithm::Lz4 => Details::Lz4(lz4(data).map_err(corrupted)?),
+        CompressionAlgorithm::Zstd => Details::Zstd(zstd(data)?),
+        _ => Details::Opaque,
+    };
+    Ok(Section {
+        algorithm,
+        original_len,
+        compressed_len: data.len() as u64,
+        details,
+    })
+}
+
+/// `[tree_size(4)][tree][encoded]`, as written by the compressor
+fn huffman(data: &[u8], original_len: u64) -> Result<HuffmanInfo> {
+    let truncated = |offset| {
+        CompressionError::corrupted(
+            CompressionAlgorithm::Huffman,
+            DecodeError::Truncated { offset },
+        )
+    };
+    let tree_len = read_u32_le(data, 0).ok_or_else(|| truncated(0))? as usize;
+    let tree_end = 4usize.saturating_add(tree_len);
+    let tree = data.get(4..tree_end).ok_or_else(|| truncated(4))?;
+
+    let mut codec = HuffmanCodec::new();
+    codec.deserialize_tree(tree)?;
+    let encoded_len = data.len() - tree_end;
+    Ok(HuffmanInfo {
+        tree_len,
+        encoded_len,
+        code_lengths: codec
+            .code_lengths()
+            .map(|(symbol, bits)| CodeLength { symbol, bits })
+            .collect(),
+        avg_code_length: codec.get_avg_code_length(),
+        bits_per_symbol: if original_len > 0 {
+            (encoded_len * 8) as f64 / original_len as f64
+        } else {
+            0.0
+        },
+    })
+}
+
+/// Walk the sequences of a raw LZ4 block: a token, literals, then a 2-byte
+/// offset and match length for every sequence but the last
+fn lz4(data: &[u8]) -> core::result::Result<Lz4Info, DecodeError> {
+    let mut info = Lz4Info::default();
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let token = data[pos];
+        pos += 1;
+        let literals = lz4_length(data, &mut pos, (token >> 4) as usize)?;
+        pos = pos
+            .checked_add(literals)
+            .filter(|&end| end <= data.len())
+            .ok_or(DecodeError::Truncated { offset: pos })?;
+        info.sequences += 1;
+        info.literal_bytes += literals;
+        if pos == data.len() {
+            break;
+        }
+
+        let offset = data
+            .get(pos..pos + 2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+            .ok_or(DecodeError::Truncated { offset: pos })?;
+        pos += 2;
+        let matched = lz4_length(data, &mut pos, (token & 0x0F) as usize)? + 4;
+        info.match_bytes += matched;
+        info.longest_match = info.longest_match.max(matched);
+        info.max_offset = info.max_offset.max(offset);
+    }
+
+    Ok(info)
+}
+
+/// A 4-bit length, extended by following bytes while they are 255
+fn lz4_length(
+    data: &[u8],
+    pos: &mut usize,
+    nibble: usize,
+) -> core::result::Result<usize, DecodeError> {
+    let mut len = nibble;
+    if nibble == 15 {
+        loop {
+            let byte = *data
+                .get(*pos)
+                .ok_or(DecodeError::Truncated { offset: *pos })?;
+            *pos += 1;
+            len += byte as usize;
+            if byte != 255 {
+                break;
+            }
+        }
+    }
+    Ok(len)
+}
+
+/// Read the header and block headers of every frame, skipping block contents
+fn zstd(data: &[u8]) -> Result<ZstdInfo> {
+    let mut info = ZstdInfo::default();
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let magic = read_u32_le(data, pos).ok_or_else(|| zstd_truncated(pos))?;
+        if magic & 0xFFFF_FFF0 == ZSTD_SKIPPABLE_MAGIC {
+            let len = read_u32_le(data, pos + 4).ok_or_else(|| zstd_truncated(pos + 4))?;
+            pos = skip(data, pos + 8, len as usize)?;
+            info.skippable_frames += 1;
+            continue;
+        }
+        if magic != ZSTD_MAGIC {
+            return Err(CompressionError::decompression_failed(
+                CompressionAlgorithm::Zstd,
+                format!("No Zstandard frame at offset {}", pos),
+            ));
+        }
+        pos += 4;
+
+        let descriptor = *data.get(pos).ok_or_else(|| zstd_truncated(pos))?;
+        pos += 1;
+        let mut frame = ZstdFrame {
+            single_segment: descriptor & 0x20 != 0,
+            checksum: descriptor & 0x04 != 0,
+            ..ZstdFrame::default()
+        };
+
+        if !frame.single_segment {
+            let window = *data.get(pos).ok_or_else(|| zstd_truncated(pos))?;
+            pos += 1;
+            let base = 1u64 << (10 + (window >> 3));
+            frame.window_size = Some(base + base / 8 * (window & 0x07) as u64);
+        }
+
+        let dictionary_len = [0, 1, 2, 4][(descriptor & 0x03) as usize];
+        if dictionary_len > 0 {
+            frame.dictionary_id = Some(read_le(data, pos, dictionary_len)? as u32);
+            pos += dictionary_len;
+        }
+
+        let content_len = match descriptor >> 6 {
+            0 if frame.single_segment => 1,
+            0 => 0,
+            1 => 2,
+            2 => 4,
+            _ => 8,
+        };
+        if content_len > 0 {
+            let size = read_le(data, pos, content_len)?;
+            // Two-byte sizes are stored minus 256
+            frame.content_size = Some(if content_len == 2 { size + 256 } else { size });
+            pos += content_len;
+        }
+        if frame.single_segment {
+            frame.window_size = frame.content_size;
+        }
+
+        loop {
+            let header = read_le(data, pos, 3)?;
+            pos += 3;
+            let size = (header >> 3) as usize;
+            let body = match (header >> 1) & 0x03 {
+                0 => {
+                    frame.raw_blocks += 1;
+                    size
+                }
+                1 => {
+                    frame.rle_blocks += 1;
+                    1
+                }
+                2 => {
+                    frame.compressed_blocks += 1;
+                    size
+                }
+                _ => {
+                    return Err(CompressionError::decompression_failed(
+                        CompressionAlgorithm::Zstd,
+                        format!("Reserved block type at offset {}", pos - 3),
+                    ))
+                }
+            };
+            pos = skip(data, pos, body)?;
+            if header & 1 == 1 {
+                break;
+            }
+        }
+        if frame.checksum {
+            pos = skip(data, pos, 4)?;
+        }
+        info.frames.push(frame);
+    }
+
+    Ok(info)
+}
+
+fn read_le(data: &[u8], pos: usize, len: usize) -> Result<u64> {
+    let bytes = data
+        .get(pos..pos + len)
+        .ok_or_else(|| zstd_truncated(pos))?;
+    Ok(bytes
+        .iter()
+        .rev()
+        .fold(0u64, |value, &byte| value << 8 | byte as u64))
+}
+
+fn skip(data: &[u8], pos: usize, len: usize) -> Result<usize> {
+    pos.checked_add(len)
+        .filter(|&end| end <= data.len())
+        .ok_or_else(|| zstd_truncated(pos))
+}
+
+fn zstd_truncated(offset: usize) -> CompressionError {
+    CompressionError::corrupted(
+        CompressionAlgorithm::Zstd,
+        DecodeError::Truncated { offset },
+    )
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::Compressor;
+
+    fn sections(data: &[u8], algorithm: CompressionAlgorithm) -> Vec<Section> {
+        let result = Compressor::new()
+            .compress_with_algorithm(data, Some(algorithm))
+            .unwrap();
+        inspect(&result.data, &result.metadata).unwrap()
+    }
+
+    #[test]
+    fn test_huffman_and_hybrid_sections() {
+        let data = b"aaaaaaaabbbbccd".repeat(4);
+        let [section] = &sections(&data, CompressionAlgorithm::Huffman)[..] else {
+            panic!("expected one section");
+        };
+        let Details::Huffman(info) = &section.details else {
+            panic!("expected Huffman details");
+        };
+        let bits = |symbol| {
+            info.code_lengths
+                .iter()
+                .find(|code| code.symbol == symbol)
+                .unwrap()
+                .bits
+        };
+        assert_eq!(info.code_lengths.len(), 4);
+        assert!(bits(b'a') < bits(b'd'));
+        assert!(info.bits_per_symbol < 8.0);
+
+        // A header followed by a zero tail splits into two segments
+        let mut account = b"mint authority and supply fields".to_vec();
+        account.extend(vec![0u8; 600]);
+        let sections = sections(&account, CompressionAlgorithm::Hybrid);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(
+            sections.iter().map(|s| s.original_len).sum::<u64>(),
+            account.len() as u64
+        );
+    }
+
+    #[cfg(all(feature = "lz4", feature = "zstd"))]
+    #[test]
+    fn test_backend_frames() {
+        let data = b"order book slab order book slab ".repeat(40);
+
+        let Details::Lz4(lz4) = &sections(&data, CompressionAlgorithm::Lz4)[0].details else {
+            panic!("expected LZ4 details");
+        };
+        assert_eq!(lz4.literal_bytes + lz4.match_bytes, data.len());
+        assert!(lz4.max_offset > 0);
+
+        let Details::Zstd(info) = &sections(&data, CompressionAlgorithm::Zstd)[0].details else {
+            panic!("expected Zstd details");
+        };
+        let [frame] = &info.frames[..] else {
+            panic!("expected one frame");
+        };
+        // The streaming encoder does not know the size up front
+        assert_eq!(frame.content_size, None);
+        assert!(frame.window_size.is_some());
+        assert!(frame.raw_blocks + frame.rle_blocks + frame.compressed_blocks > 0);
+
+        let bulk = zstd::bulk::compress(&data, 3).unwrap();
+        let info = zstd(&bulk).unwrap();
+        assert_eq!(info.frames[0].content_size, Some(data.len() as u64));
+        assert!(zstd(&bulk[..bulk.len() - 1]).is_err());
+    }
+}
@@ -1,30 +1,59 @@
 //! OWLSOL Core - Compression Engine
 //!
 //! Provides hybrid compression algorithms optimized for Solana account storage.
+//!
+//! Without the default `std` feature the crate is `no_std` + `alloc` and only
+//! exposes the pure-Rust codecs, bitstream and checksum utilities.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
 
 pub mod algorithms;
+#[cfg(feature = "std")]
 pub mod analyzer;
+#[cfg(feature = "std")]
+pub mod archive;
+#[cfg(feature = "std")]
 pub mod compressor;
+pub mod container;
+pub mod cost;
+#[cfg(feature = "std")]
 pub mod decompressor;
+#[cfg(feature = "encryption")]
+pub mod encryption;
 pub mod error;
+pub mod inspect;
 pub mod metadata;
+pub mod rent;
+#[cfg(feature = "std")]
 pub mod selector;
+#[cfg(feature = "signing")]
+pub mod signing;
+#[cfg(feature = "synthetic")]
+pub mod synthetic;
 pub mod utils;
 
+#[cfg(feature = "std")]
+pub use archive::{ArchiveEntry, ArchiveReader, ArchiveWriter};
+#[cfg(feature = "std")]
 pub use compressor::Compressor;
-pub use decompressor::Decompressor;
-pub use error::{CompressionError, Result};
+pub use container::Container;
+#[cfg(feature = "std")]
+pub use decompressor::{DecompressOptions, Decompressor};
+pub use error::{CompressionError, DecodeError, ErrorKind, Result};
 pub use metadata::{CompressionAlgorithm, CompressionMetadata, CompressionResult};
 
 /// Prelude module for convenient imports
 pub mod prelude {
     pub use crate::{
-        CompressionAlgorithm, CompressionError, CompressionMetadata, CompressionResult, Compressor,
-        Decompressor, Result,
+        CompressionAlgorithm, CompressionError, CompressionMetadata, CompressionResult, Result,
     };
+    #[cfg(feature = "std")]
+    pub use crate::{Compressor, DecompressOptions, Decompressor};
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
 
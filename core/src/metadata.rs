@@ -1,10 +1,17 @@
+use alloc::vec::Vec;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-pub const COMPRESSION_VERSION: u8 = 1;
+/// Format version written into new metadata. Version 2 changed RLE runs
+/// from `[marker][byte][count]` to `[marker][count][byte]`.
+pub const COMPRESSION_VERSION: u8 = 2;
+/// Oldest format version that still decodes
+pub const MIN_COMPRESSION_VERSION: u8 = 1;
 pub const MAX_DATA_SIZE: usize = 10 * 1024 * 1024; // 10MB
 pub const MIN_COMPRESSION_THRESHOLD: f64 = 0.95; // Only compress if saves 5%+
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[repr(u8)]
 pub enum CompressionAlgorithm {
     None = 0,
@@ -14,6 +21,11 @@ pub enum CompressionAlgorithm {
     Hybrid = 4,
     Lz4 = 5,
     Zstd = 6,
+    Brotli = 7,
+    Deflate = 8,
+    Gzip = 9,
+    Snappy = 10,
+    Lzma = 11,
 }
 
 impl CompressionAlgorithm {
@@ -26,6 +38,11 @@ impl CompressionAlgorithm {
             4 => Some(Self::Hybrid),
             5 => Some(Self::Lz4),
             6 => Some(Self::Zstd),
+            7 => Some(Self::Brotli),
+            8 => Some(Self::Deflate),
+            9 => Some(Self::Gzip),
+            10 => Some(Self::Snappy),
+            11 => Some(Self::Lzma),
             _ => None,
         }
     }
@@ -39,6 +56,35 @@ impl CompressionAlgorithm {
             Self::Hybrid => "Hybrid",
             Self::Lz4 => "LZ4",
             Self::Zstd => "Zstd",
+            Self::Brotli => "Brotli",
+            Self::Deflate => "Deflate",
+            Self::Gzip => "Gzip",
+            Self::Snappy => "Snappy",
+            Self::Lzma => "LZMA",
+        }
+    }
+
+    /// Whether `owlsol-onchain` can decode this algorithm inside a Solana program
+    pub fn is_onchain(&self) -> bool {
+        matches!(
+            self,
+            Self::None | Self::Huffman | Self::Dictionary | Self::RunLength | Self::Hybrid
+        )
+    }
+
+    /// Whether support for this algorithm was compiled in (standard backends
+    /// are behind the `lz4`, `zstd`, `brotli`, `deflate`, `snappy` and `lzma`
+    /// features; the built-in codecs need `std` for `Compressor`)
+    #[allow(clippy::match_like_matches_macro)]
+    pub fn is_available(&self) -> bool {
+        match self {
+            Self::Lz4 => cfg!(feature = "lz4"),
+            Self::Zstd => cfg!(feature = "zstd"),
+            Self::Brotli => cfg!(feature = "brotli"),
+            Self::Deflate | Self::Gzip => cfg!(feature = "deflate"),
+            Self::Snappy => cfg!(feature = "snappy"),
+            Self::Lzma => cfg!(feature = "lzma"),
+            _ => true,
         }
     }
 
@@ -47,7 +93,8 @@ impl CompressionAlgorithm {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CompressionMetadata {
     pub version: u8,
     pub algorithm: CompressionAlgorithm,
@@ -70,11 +117,22 @@ impl CompressionMetadata {
             } else {
                 0.0
             },
-            timestamp: chrono::Utc::now().timestamp(),
+            timestamp: Self::now(),
             checksum: 0, // Will be calculated separately
         }
     }
 
+    #[cfg(feature = "std")]
+    fn now() -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+
+    /// No clock without `std`; callers can set `timestamp` themselves
+    #[cfg(not(feature = "std"))]
+    fn now() -> i64 {
+        0
+    }
+
     pub fn with_checksum(mut self, checksum: u32) -> Self {
         self.checksum = checksum;
         self
@@ -92,16 +150,18 @@ impl CompressionMetadata {
         self.compression_ratio < MIN_COMPRESSION_THRESHOLD
     }
 
+    #[cfg(feature = "serde")]
     pub fn to_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
         serde_json::to_vec(self)
     }
 
+    #[cfg(feature = "serde")]
     pub fn from_bytes(b: &[u8]) -> Result<Self, serde_json::Error> {
         serde_json::from_slice(b)
     }
 
     pub fn validate(&self) -> bool {
-        self.version == COMPRESSION_VERSION
+        (MIN_COMPRESSION_VERSION..=COMPRESSION_VERSION).contains(&self.version)
             && self.original_size > 0
             && self.compressed_size > 0
             && self.compression_ratio >= 0.0
@@ -112,14 +172,26 @@ impl CompressionMetadata {
 pub struct CompressionResult {
     pub data: Vec<u8>,
     pub metadata: CompressionMetadata,
+    /// Estimated compute units to decode on-chain, `None` if the algorithm
+    /// cannot be decoded by `owlsol-onchain`
+    pub decode_cu: Option<u64>,
 }
 
 impl CompressionResult {
     pub fn new(data: Vec<u8>, metadata: CompressionMetadata) -> Self {
-        Self { data, metadata }
+        let decode_cu = crate::cost::estimate_decode_cu(
+            metadata.algorithm,
+            &data,
+            metadata.original_size as usize,
+        );
+        Self {
+            data,
+            metadata,
+            decode_cu,
+        }
     }
 
     pub fn total_size(&self) -> usize {
-        self.data.len() + std::mem::size_of::<CompressionMetadata>()
+        self.data.len() + core::mem::size_of::<CompressionMetadata>()
     }
 }
@@ -0,0 +1,175 @@
+//! Offline rent-exemption calculator
+//!
+//! Follows the runtime's formula, so no RPC call is needed to price an
+//! account: an account is rent exempt once it holds
+//! `(ACCOUNT_STORAGE_OVERHEAD + data_len) * lamports_per_byte_year *
+//! exemption_threshold` lamports. Because of the fixed overhead, rent is not
+//! proportional to data size, and the header stored in front of a compressed
+//! payload can turn a small saving into a loss.
+//!
+//! ```
+//! use owlsol_core::rent::Rent;
+//!
+//! let rent = Rent::default();
+//! assert_eq!(rent.minimum_balance(0), 890_880);
+//! // 100 bytes compressed to 70, behind a 36-byte header, costs more
+//! assert!(rent.compare(100, 70, 36).is_loss());
+//! ```
+
+/// Lamports charged per byte-year of account data
+pub const LAMPORTS_PER_BYTE_YEAR: u64 = 3_480;
+/// Years of rent an account must hold to be exempt
+pub const EXEMPTION_THRESHOLD: f64 = 2.0;
+/// Bytes of account overhead charged for rent on top of the data
+pub const ACCOUNT_STORAGE_OVERHEAD: u64 = 128;
+pub const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+
+/// Rent parameters; `Default` has the current mainnet values
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rent {
+    pub lamports_per_byte_year: u64,
+    /// Years of rent an account must hold to be exempt
+    pub exemption_threshold: f64,
+}
+
+impl Default for Rent {
+    fn default() -> Self {
+        Self {
+            lamports_per_byte_year: LAMPORTS_PER_BYTE_YEAR,
+            exemption_threshold: EXEMPTION_THRESHOLD,
+        }
+    }
+}
+
+impl Rent {
+    /// Override the parameters, e.g. for a cluster with different rent
+    pub fn new(lamports_per_byte_year: u64, exemption_threshold: f64) -> Self {
+        Self {
+            lamports_per_byte_year,
+            exemption_threshold,
+        }
+    }
+
+    /// Lamports an account with `data_len` bytes must hold to be rent exempt
+    pub fn minimum_balance(&self, data_len: usize) -> u64 {
+        let bytes = ACCOUNT_STORAGE_OVERHEAD + data_len as u64;
+        (bytes.saturating_mul(self.lamports_per_byte_year) as f64 * self.exemption_threshold) as u64
+    }
+
+    /// Rent of storing `original_size` bytes raw versus a
+    /// `compressed_size`-byte payload behind a `header_len`-byte header
+    pub fn compare(
+        &self,
+        original_size: usize,
+        compressed_size: usize,
+        header_len: usize,
+    ) -> RentComparison {
+        let stored_size = compressed_size + header_len;
+        RentComparison {
+            original_size,
+            stored_size,
+            header_len,
+            original_lamports: self.minimum_balance(original_size),
+            stored_lamports: self.minimum_balance(stored_size),
+        }
+    }
+}
+
+/// Rent-exempt balances of one account stored raw and compressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RentComparison {
+    pub original_size: usize,
+    /// Compressed payload plus header
+    pub stored_size: usize,
+    pub header_len: usize,
+    pub original_lamports: u64,
+    pub stored_lamports: u64,
+}
+
+impl RentComparison {
+    /// Lamports freed by compressing; negative when it costs more
+    pub fn saved_lamports(&self) -> i64 {
+        self.original_lamports as i64 - self.stored_lamports as i64
+    }
+
+    /// Share of the raw account's rent that compression frees, in percent,
+    /// or 0 when rent is free. The fixed overhead keeps this below the share
+    /// of bytes saved.
+    pub fn saved_percent(&self) -> f64 {
+        if self.original_lamports == 0 {
+            return 0.0;
+        }
+        self.saved_lamports() as f64 / self.original_lamports as f64 * 100.0
+    }
+
+    /// Compression costs more rent than storing the data raw
+    pub fn is_loss(&self) -> bool {
+        self.stored_lamports > self.original_lamports
+    }
+
+    /// Largest payload that still saves rent, or `None` when the header
+    /// alone is as large as the data
+    pub fn break_even_payload(&self) -> Option<usize> {
+        self.original_size.checked_sub(self.header_len + 1)
+    }
+}
+
+/// Smallest account that compression at `ratio` (compressed/original size)
+/// saves rent on behind a `header_len`-byte header, or `None` when it never
+/// does
+pub fn break_even_size(ratio: f64, header_len: usize) -> Option<usize> {
+    // Saves when size * ratio + header < size, i.e. size > header / (1 - ratio)
+    if !(0.0..1.0).contains(&ratio) {
+        return None;
+    }
+    Some((header_len as f64 / (1.0 - ratio)) as usize + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minimum_balance() {
+        let rent = Rent::default();
+        // The runtime's figures for an empty and a token account
+        assert_eq!(rent.minimum_balance(0), 890_880);
+        assert_eq!(rent.minimum_balance(165), 2_039_280);
+
+        let custom = Rent::new(1_000, 1.0);
+        assert_eq!(custom.minimum_balance(72), 200_000);
+    }
+
+    #[test]
+    fn test_compare() {
+        let rent = Rent::default();
+        let comparison = rent.compare(1_000, 400, 36);
+        assert_eq!(comparison.stored_size, 436);
+        assert_eq!(comparison.saved_lamports(), 564 * 6_960);
+        // 564 of 1128 charged bytes
+        assert!((comparison.saved_percent() - 50.0).abs() < 1e-9);
+        assert_eq!(comparison.break_even_payload(), Some(963));
+        assert!(!comparison.is_loss());
+
+        let small = rent.compare(40, 10, 36);
+        assert!(small.is_loss());
+        assert_eq!(small.saved_lamports(), -6 * 6_960);
+        assert_eq!(small.break_even_payload(), Some(3));
+        assert_eq!(rent.compare(30, 1, 36).break_even_payload(), None);
+
+        // A cluster without rent saves nothing, rather than NaN percent
+        for free in [Rent::new(0, 2.0), Rent::new(3_480, 0.0)] {
+            let comparison = free.compare(1_000, 400, 36);
+            assert_eq!(comparison.saved_lamports(), 0);
+            assert_eq!(comparison.saved_percent(), 0.0);
+        }
+    }
+
+    #[test]
+    fn test_break_even_size() {
+        assert_eq!(break_even_size(0.5, 36), Some(73));
+        assert_eq!(break_even_size(0.0, 36), Some(37));
+        assert_eq!(break_even_size(1.0, 36), None);
+        assert_eq!(break_even_size(1.2, 36), None);
+    }
+}
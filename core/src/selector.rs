@@ -1,5 +1,6 @@
 use crate::analyzer::DataAnalysis;
-use crate::metadata::CompressionAlgorithm;
+use crate::cost::CostWeights;
+use crate::metadata::{CompressionAlgorithm, CompressionResult};
 
 pub struct AlgorithmSelector;
 
@@ -63,6 +64,10 @@ impl AlgorithmSelector {
             CompressionAlgorithm::Hybrid => 0.4,
             CompressionAlgorithm::Lz4 => 0.5,
             CompressionAlgorithm::Zstd => 0.4,
+            CompressionAlgorithm::Brotli => 0.35,
+            CompressionAlgorithm::Deflate | CompressionAlgorithm::Gzip => 0.45,
+            CompressionAlgorithm::Snappy => 0.6,
+            CompressionAlgorithm::Lzma => 0.35,
         }
     }
 
@@ -70,6 +75,23 @@ impl AlgorithmSelector {
         let ratio = self.estimate_compression_ratio(analysis);
         ratio < 0.95 // Only compress if saves at least 5%
     }
+
+    /// Pick the candidate with the lowest rent plus on-chain decode cost,
+    /// skipping those that cannot be decoded on-chain or exceed the CU budget
+    pub fn select_cheapest<'a>(
+        &self,
+        candidates: &'a [CompressionResult],
+        weights: &CostWeights,
+    ) -> Option<&'a CompressionResult> {
+        candidates
+            .iter()
+            .filter_map(|c| {
+                let total = weights.total_lamports(c.data.len(), c.decode_cu?)?;
+                Some((total, c))
+            })
+            .min_by_key(|(total, _)| *total)
+            .map(|(_, c)| c)
+    }
 }
 
 impl Default for AlgorithmSelector {
@@ -116,4 +138,34 @@ mod tests {
             CompressionAlgorithm::RunLength
         );
     }
+
+    #[test]
+    fn test_select_cheapest() {
+        use crate::metadata::CompressionMetadata;
+
+        let selector = AlgorithmSelector::new();
+        let candidate = |algo, data: Vec<u8>| {
+            let metadata = CompressionMetadata::new(algo, 100, data.len() as u64);
+            CompressionResult::new(data, metadata)
+        };
+        let candidates = [
+            candidate(CompressionAlgorithm::None, vec![7; 100]),
+            candidate(CompressionAlgorithm::RunLength, vec![0xFF, 100, 7]),
+            candidate(CompressionAlgorithm::Zstd, vec![1]),
+        ];
+
+        // Rent dominates: the smallest decodable payload wins, Zstd is skipped
+        let weights = CostWeights::default();
+        let best = selector.select_cheapest(&candidates, &weights).unwrap();
+        assert_eq!(best.metadata.algorithm, CompressionAlgorithm::RunLength);
+
+        // Expensive compute and frequent decodes favour the plain copy
+        let weights = CostWeights {
+            micro_lamports_per_cu: 1_000_000,
+            expected_decodes: 100_000,
+            ..CostWeights::default()
+        };
+        let best = selector.select_cheapest(&candidates, &weights).unwrap();
+        assert_eq!(best.metadata.algorithm, CompressionAlgorithm::None);
+    }
 }
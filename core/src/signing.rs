@@ -0,0 +1,170 @@
+//! Ed25519 signatures over containers
+//!
+//! A signature covers the whole header (including the embedded signer key)
+//! and the payload, so consumers can check who produced a container and that
+//! nothing changed since. Keys are Solana keypairs: 64 bytes, the secret key
+//! followed by the public key. Encrypted containers can be signed too; the
+//! signature is over the ciphertext.
+
+use crate::container::{Container, SignatureBlock, SIGNATURE_LEN, SIGNER_LEN};
+use crate::decompressor::{DecompressOptions, Decompressor};
+use crate::error::{CompressionError, Result};
+use ed25519_dalek::{PublicKey, SecretKey, Signature, Signer};
+
+/// Length of a Solana keypair: secret key then public key
+pub const KEYPAIR_LEN: usize = 64;
+
+/// Sign `container` in place with a Solana keypair, replacing any existing
+/// signature. Returns the signer's public key.
+pub fn sign(container: &mut Container, keypair: &[u8]) -> Result<[u8; SIGNER_LEN]> {
+    if keypair.len() != KEYPAIR_LEN {
+        return Err(CompressionError::invalid_input(format!(
+            "Keypair must be {} bytes, got {}",
+            KEYPAIR_LEN,
+            keypair.len()
+        )));
+    }
+    let secret = SecretKey::from_bytes(&keypair[..32])
+        .map_err(|e| CompressionError::invalid_input(format!("Invalid secret key: {}", e)))?;
+    let public = PublicKey::from(&secret);
+    if public.as_bytes()[..] != keypair[32..] {
+        return Err(CompressionError::invalid_input(
+            "Keypair's public key does not match its secret key",
+        ));
+    }
+
+    container.signature = Some(SignatureBlock {
+        signer: public.to_bytes(),
+        signature: [0; SIGNATURE_LEN],
+    });
+    let signature = ed25519_dalek::Keypair { secret, public }.sign(&container.signed_bytes());
+    if let Some(block) = container.signature.as_mut() {
+        block.signature = signature.to_bytes();
+    }
+
+    tracing::debug!("container signed");
+    Ok(public.to_bytes())
+}
+
+/// Check the container's signature and, if `expected` is given, that it was
+/// made by that key. Returns the signer's public key.
+pub fn verify(
+    container: &Container,
+    expected: Option<&[u8; SIGNER_LEN]>,
+) -> Result<[u8; SIGNER_LEN]> {
+    let block = container.signature.ok_or(CompressionError::NotSigned)?;
+    if let Some(expected) = expected {
+        if *expected != block.signer {
+            return Err(CompressionError::UnexpectedSigner {
+                expected: *expected,
+                actual: block.signer,
+            });
+        }
+    }
+
+    let public =
+        PublicKey::from_bytes(&block.signer).map_err(|_| CompressionError::InvalidSignature)?;
+    let signature =
+        Signature::from_bytes(&block.signature).map_err(|_| CompressionError::InvalidSignature)?;
+    public
+        .verify_strict(&container.signed_bytes(), &signature)
+        .map_err(|_| CompressionError::InvalidSignature)?;
+    Ok(block.signer)
+}
+
+/// Verify that `signer` signed the container, and only then decompress it
+///
+/// The container must not be encrypted; decrypt it first and decompress the
+/// result if it is.
+pub fn decompress_verified(
+    container: &Container,
+    signer: &[u8; SIGNER_LEN],
+    options: &DecompressOptions,
+) -> Result<Vec<u8>> {
+    verify(container, Some(signer))?;
+    Decompressor::new().decompress_with(
+        container.compressed_payload()?,
+        &container.metadata,
+        options,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Compressor;
+
+    fn keypair(seed: u8) -> Vec<u8> {
+        let secret = SecretKey::from_bytes(&[seed; 32]).unwrap();
+        let mut keypair = secret.to_bytes().to_vec();
+        keypair.extend_from_slice(PublicKey::from(&secret).as_bytes());
+        keypair
+    }
+
+    fn signed_container(data: &[u8], seed: u8) -> (Container, [u8; SIGNER_LEN]) {
+        let result = Compressor::new().compress(data).unwrap();
+        let mut container = Container::new(result);
+        let signer = sign(&mut container, &keypair(seed)).unwrap();
+        (container, signer)
+    }
+
+    #[test]
+    fn test_sign_verify_roundtrip() {
+        let data = b"oracle price history ".repeat(20);
+        let (container, signer) = signed_container(&data, 7);
+
+        let parsed = Container::from_bytes(&container.to_bytes()).unwrap();
+        assert!(parsed.is_signed());
+        assert_eq!(verify(&parsed, None).unwrap(), signer);
+        let decompressed =
+            decompress_verified(&parsed, &signer, &DecompressOptions::default()).unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn test_rejects_tampering_and_other_signers() {
+        let (container, signer) = signed_container(&[5u8; 300], 7);
+
+        let (_, other) = signed_container(&[5u8; 300], 8);
+        assert_eq!(
+            decompress_verified(&container, &other, &DecompressOptions::default())
+                .unwrap_err()
+                .code(),
+            604
+        );
+
+        let mut bytes = container.to_bytes();
+        let last_payload_byte = bytes.len() - SIGNATURE_LEN - 1;
+        bytes[last_payload_byte] ^= 1;
+        let tampered = Container::from_bytes(&bytes).unwrap();
+        assert!(matches!(
+            verify(&tampered, Some(&signer)),
+            Err(CompressionError::InvalidSignature)
+        ));
+
+        let mut unsigned = container.clone();
+        unsigned.signature = None;
+        assert!(matches!(
+            verify(&unsigned, None),
+            Err(CompressionError::NotSigned)
+        ));
+        let mut mismatched = keypair(7);
+        mismatched[40] ^= 1;
+        assert!(sign(&mut unsigned, &mismatched).is_err());
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_signing_keeps_encryption_valid() {
+        use crate::encryption::{self, Key};
+
+        let result = Compressor::new().compress(&[9u8; 200]).unwrap();
+        let key = Key::raw(&[3u8; 32]).unwrap();
+        let mut container = encryption::encrypt(&result, &key).unwrap();
+        sign(&mut container, &keypair(1)).unwrap();
+
+        let parsed = Container::from_bytes(&container.to_bytes()).unwrap();
+        verify(&parsed, None).unwrap();
+        assert_eq!(encryption::decrypt(&parsed, &key).unwrap(), result.data);
+    }
+}
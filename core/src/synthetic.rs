@@ -0,0 +1,383 @@
+//! Synthetic Solana account data for benchmarks and tests
+//!
+//! Each generator lays out bytes the way the real program does (SPL Token,
+//! Metaplex Token Metadata, OpenBook, Pyth, Anchor), with field values drawn
+//! from a seeded generator so every run sees the same accounts. The aim is
+//! realistic structure (zeroed options, padded strings, clustered prices),
+//! not valid on-chain state.
+//!
+//! ```
+//! use owlsol_core::synthetic::{AccountKind, Generator};
+//!
+//! let mut generator = Generator::new(42);
+//! let account = generator.account(AccountKind::TokenAccount);
+//! assert_eq!(account.len(), 165);
+//! assert_eq!(account, Generator::new(42).account(AccountKind::TokenAccount));
+//! ```
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Size of an SPL Token account
+pub const TOKEN_ACCOUNT_LEN: usize = 165;
+/// Size of an SPL Token mint
+pub const MINT_LEN: usize = 82;
+/// Size Metaplex allocates for a metadata account
+pub const METADATA_LEN: usize = 679;
+/// Nodes in a generated order-book slab
+pub const ORDER_BOOK_NODES: usize = 512;
+/// Entries in a generated oracle price history
+pub const ORACLE_HISTORY_LEN: usize = 256;
+/// Space a generated PDA reserves, mostly left zeroed for later fields
+pub const PDA_LEN: usize = 1024;
+
+const NODE_LEN: usize = 72;
+const PRICE_ENTRY_LEN: usize = 36;
+const BASE_TIMESTAMP: i64 = 1_700_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountKind {
+    /// SPL Token account: mint, owner, amount and optional authorities
+    TokenAccount,
+    /// SPL Token mint
+    Mint,
+    /// Metaplex Token Metadata account with zero-padded strings
+    Metadata,
+    /// OpenBook (Serum) bids/asks slab, partly filled with resting orders
+    OrderBook,
+    /// Pyth-style ring of price, confidence and slot updates
+    OracleHistory,
+    /// Anchor account whose reserved space is mostly zeros
+    Pda,
+}
+
+impl AccountKind {
+    pub const ALL: [AccountKind; 6] = [
+        AccountKind::TokenAccount,
+        AccountKind::Mint,
+        AccountKind::Metadata,
+        AccountKind::OrderBook,
+        AccountKind::OracleHistory,
+        AccountKind::Pda,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::TokenAccount => "token-account",
+            Self::Mint => "mint",
+            Self::Metadata => "metadata",
+            Self::OrderBook => "order-book",
+            Self::OracleHistory => "oracle-history",
+            Self::Pda => "pda",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == name)
+    }
+}
+
+/// Deterministic account generator; the same seed yields the same accounts
+pub struct Generator {
+    state: u64,
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// The next account of `kind`
+    pub fn account(&mut self, kind: AccountKind) -> Vec<u8> {
+        match kind {
+            AccountKind::TokenAccount => self.token_account(),
+            AccountKind::Mint => self.mint(),
+            AccountKind::Metadata => self.metadata(),
+            AccountKind::OrderBook => self.order_book(),
+            AccountKind::OracleHistory => self.oracle_history(),
+            AccountKind::Pda => self.pda(),
+        }
+    }
+
+    pub fn token_account(&mut self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(TOKEN_ACCOUNT_LEN);
+        data.extend_from_slice(&self.pubkey()); // mint
+        data.extend_from_slice(&self.pubkey()); // owner
+        data.extend_from_slice(&self.amount().to_le_bytes());
+        let delegated = self.chance(10);
+        self.option_pubkey(&mut data, delegated);
+        data.push(1); // initialized
+
+        // is_native: only wrapped SOL accounts carry their rent reserve
+        if self.chance(5) {
+            data.extend_from_slice(&1u32.to_le_bytes());
+            data.extend_from_slice(&2_039_280u64.to_le_bytes());
+        } else {
+            data.extend_from_slice(&[0; 12]);
+        }
+        let delegated_amount = if delegated { self.amount() } else { 0 };
+        data.extend_from_slice(&delegated_amount.to_le_bytes());
+        let closable = self.chance(5);
+        self.option_pubkey(&mut data, closable);
+        debug_assert_eq!(data.len(), TOKEN_ACCOUNT_LEN);
+        data
+    }
+
+    pub fn mint(&mut self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(MINT_LEN);
+        // Fixed-supply mints drop their mint authority
+        let mintable = self.chance(60);
+        self.option_pubkey(&mut data, mintable);
+        let decimals = [0u8, 6, 9][self.below(3) as usize];
+        let supply = self.below(1_000_000_000) * 10u64.pow(decimals as u32);
+        data.extend_from_slice(&supply.to_le_bytes());
+        data.push(decimals);
+        data.push(1); // initialized
+        let freezable = self.chance(30);
+        self.option_pubkey(&mut data, freezable);
+        debug_assert_eq!(data.len(), MINT_LEN);
+        data
+    }
+
+    pub fn metadata(&mut self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(METADATA_LEN);
+        data.push(4); // Key::MetadataV1
+        data.extend_from_slice(&self.pubkey()); // update authority
+        data.extend_from_slice(&self.pubkey()); // mint
+
+        let collection = ["Owl", "Night Owl", "Solana Sage", "Hoot"][self.below(4) as usize];
+        let name = format!("{} #{}", collection, self.below(10_000));
+        let symbol: String = collection
+            .split(' ')
+            .filter_map(|word| word.chars().next())
+            .chain("OWL".chars())
+            .take(4)
+            .collect::<String>()
+            .to_uppercase();
+        let uri = format!("https://arweave.net/{}", self.base64url(43));
+        // Metaplex pads strings to their maximum length with NULs
+        Self::padded_string(&mut data, &name, 32);
+        Self::padded_string(&mut data, &symbol, 10);
+        Self::padded_string(&mut data, &uri, 200);
+        data.extend_from_slice(&(self.below(11) as u16 * 100).to_le_bytes());
+
+        // Creators: Some(vec) with shares adding up to 100
+        let creators = 1 + self.below(3) as usize;
+        data.push(1);
+        data.extend_from_slice(&(creators as u32).to_le_bytes());
+        for index in 0..creators {
+            data.extend_from_slice(&self.pubkey());
+            data.push((index == 0) as u8); // verified
+            let share = if index == 0 {
+                100 - 10 * (creators as u8 - 1)
+            } else {
+                10
+            };
+            data.push(share);
+        }
+
+        data.push(self.chance(70) as u8); // primary sale happened
+        data.push(self.chance(80) as u8); // is mutable
+        data.extend_from_slice(&[1, self.below(256) as u8]); // edition nonce
+        data.extend_from_slice(&[1, 0]); // token standard: NonFungible
+        data.push(1); // collection
+        data.push(1);
+        data.extend_from_slice(&self.pubkey());
+        // uses, collection details and programmable config: None
+        data.extend_from_slice(&[0, 0, 0]);
+        data.resize(METADATA_LEN, 0);
+        data
+    }
+
+    pub fn order_book(&mut self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(5 + 8 + 32 + ORDER_BOOK_NODES * NODE_LEN + 7);
+        data.extend_from_slice(b"serum");
+        data.extend_from_slice(&(0b100_0001u64).to_le_bytes()); // initialized | bids
+
+        // A tree of n leaves takes n - 1 inner nodes, so at most half the
+        // slab holds orders
+        let orders = ORDER_BOOK_NODES / 4 + self.below(ORDER_BOOK_NODES as u64 / 4) as usize;
+        let inner = orders - 1;
+        let used = orders + inner;
+        // Slab header: bump index, free list length and head, root, leaves
+        data.extend_from_slice(&(used as u64).to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&(orders as u64).to_le_bytes());
+
+        // A few market makers own most orders, priced near the mid
+        let makers: Vec<[u8; 32]> = (0..8).map(|_| self.pubkey()).collect();
+        let mid = 20_000 + self.below(5_000);
+        let mut sequence = self.below(1 << 40);
+        let mut nodes = 0;
+        for index in 0..inner {
+            let start = data.len();
+            data.extend_from_slice(&1u32.to_le_bytes()); // inner node
+            data.extend_from_slice(&(63 - (index % 64) as u32).to_le_bytes()); // prefix length
+            data.extend_from_slice(&((mid as u128) << 64).to_le_bytes());
+            data.extend_from_slice(&((2 * index + 1) as u32).to_le_bytes());
+            data.extend_from_slice(&((2 * index + 2) as u32).to_le_bytes());
+            data.resize(start + NODE_LEN, 0);
+            nodes += 1;
+        }
+        for _ in 0..orders {
+            let start = data.len();
+            let price = mid - self.below(200);
+            sequence += 1 + self.below(4);
+            data.extend_from_slice(&2u32.to_le_bytes()); // leaf
+            data.push(self.below(8) as u8); // owner slot
+            data.push(0); // fee tier
+            data.extend_from_slice(&[0, 0]);
+            data.extend_from_slice(&(((price as u128) << 64) | sequence as u128).to_le_bytes());
+            data.extend_from_slice(&makers[self.below(makers.len() as u64) as usize]);
+            data.extend_from_slice(&(1 + self.below(100) * 10).to_le_bytes()); // lots
+            data.extend_from_slice(&self.below(1 << 20).to_le_bytes()); // client order id
+            data.resize(start + NODE_LEN, 0);
+            nodes += 1;
+        }
+        // Never-used nodes stay zeroed
+        data.resize(data.len() + (ORDER_BOOK_NODES - nodes) * NODE_LEN, 0);
+        data.extend_from_slice(b"padding");
+        data
+    }
+
+    pub fn oracle_history(&mut self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(48 + ORACLE_HISTORY_LEN * PRICE_ENTRY_LEN);
+        data.extend_from_slice(&0xa1b2_c3d4u32.to_le_bytes()); // magic
+        data.extend_from_slice(&2u32.to_le_bytes()); // version
+        data.extend_from_slice(&3u32.to_le_bytes()); // price account
+        let size = 48 + ORACLE_HISTORY_LEN * PRICE_ENTRY_LEN;
+        data.extend_from_slice(&(size as u32).to_le_bytes());
+        data.extend_from_slice(&(-8i32).to_le_bytes()); // exponent
+        data.extend_from_slice(&(ORACLE_HISTORY_LEN as u32).to_le_bytes());
+        data.extend_from_slice(&self.pubkey()[..24]); // product account prefix
+
+        // Random walk: prices drift a few basis points per update
+        let mut price = (50 + self.below(150)) as i64 * 100_000_000;
+        let mut slot = 250_000_000 + self.below(10_000_000);
+        for _ in 0..ORACLE_HISTORY_LEN {
+            let step = self.below(2001) as i64 - 1000;
+            price += price / 1_000_000 * step / 10;
+            slot += 1 + self.below(3);
+            data.extend_from_slice(&price.to_le_bytes());
+            data.extend_from_slice(&(price as u64 / 2_000 + self.below(1_000)).to_le_bytes());
+            data.extend_from_slice(&1u32.to_le_bytes()); // trading
+            data.extend_from_slice(&0u32.to_le_bytes()); // corporate action
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(&0u32.to_le_bytes());
+        }
+        data
+    }
+
+    pub fn pda(&mut self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(PDA_LEN);
+        // Anchor discriminator of a `Vault` account
+        data.extend_from_slice(&[0xd3, 0x08, 0xe8, 0x2b, 0x02, 0x98, 0x75, 0x77]);
+        data.push(255 - self.below(4) as u8); // bump
+        data.extend_from_slice(&self.pubkey()); // authority
+        data.extend_from_slice(&self.pubkey()); // mint
+        data.extend_from_slice(&self.amount().to_le_bytes());
+        data.extend_from_slice(&(BASE_TIMESTAMP + self.below(30_000_000) as i64).to_le_bytes());
+        data.push(self.chance(90) as u8); // active
+        data.resize(PDA_LEN, 0);
+        data
+    }
+
+    /// SplitMix64
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    /// True `percent` percent of the time
+    fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+
+    fn pubkey(&mut self) -> [u8; 32] {
+        let mut key = [0; 32];
+        for chunk in key.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next().to_le_bytes());
+        }
+        key
+    }
+
+    /// Token amounts cluster on round numbers of a few common decimals
+    fn amount(&mut self) -> u64 {
+        match self.below(4) {
+            0 => 0,
+            1 => self.below(1_000) * 1_000_000,
+            2 => self.below(100_000) * 1_000_000_000,
+            _ => self.next() >> 20,
+        }
+    }
+
+    /// A `COption<Pubkey>`: a u32 tag, then the key or zeros
+    fn option_pubkey(&mut self, data: &mut Vec<u8>, some: bool) {
+        if some {
+            data.extend_from_slice(&1u32.to_le_bytes());
+            data.extend_from_slice(&self.pubkey());
+        } else {
+            data.extend_from_slice(&[0; 36]);
+        }
+    }
+
+    fn base64url(&mut self, len: usize) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+        (0..len)
+            .map(|_| ALPHABET[self.below(64) as usize] as char)
+            .collect()
+    }
+
+    /// Borsh string padded with NULs to `len` bytes
+    fn padded_string(data: &mut Vec<u8>, value: &str, len: usize) {
+        data.extend_from_slice(&(len as u32).to_le_bytes());
+        let start = data.len();
+        data.extend_from_slice(&value.as_bytes()[..value.len().min(len)]);
+        data.resize(start + len, 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layouts() {
+        let mut generator = Generator::new(7);
+        for _ in 0..32 {
+            let account = generator.token_account();
+            assert_eq!(account.len(), TOKEN_ACCOUNT_LEN);
+            assert_eq!(account[108], 1);
+            assert_eq!(generator.mint().len(), MINT_LEN);
+            assert_eq!(generator.metadata().len(), METADATA_LEN);
+            assert_eq!(generator.pda().len(), PDA_LEN);
+        }
+
+        let book = generator.order_book();
+        assert_eq!(book.len(), 5 + 8 + 32 + ORDER_BOOK_NODES * NODE_LEN + 7);
+        assert!(book.starts_with(b"serum") && book.ends_with(b"padding"));
+        let history = generator.oracle_history();
+        assert_eq!(history.len(), 48 + ORACLE_HISTORY_LEN * PRICE_ENTRY_LEN);
+    }
+
+    #[test]
+    fn test_seeds() {
+        for kind in AccountKind::ALL {
+            assert_eq!(AccountKind::from_name(kind.as_str()), Some(kind));
+            let account = Generator::new(1).account(kind);
+            assert_eq!(account, Generator::new(1).account(kind));
+            assert_ne!(account, Generator::new(2).account(kind));
+        }
+        assert_eq!(AccountKind::from_name("nft"), None);
+    }
+}
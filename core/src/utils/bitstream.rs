@@ -1,3 +1,5 @@
+use alloc::vec::Vec;
+
 /// Bit-level writer for efficient bit packing
 pub struct BitWriter {
     bytes: Vec<u8>,
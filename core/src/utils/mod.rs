@@ -1,6 +1,6 @@
 pub mod bitstream;
 
-use std::num::Wrapping;
+use core::num::Wrapping;
 
 /// Calculate a simple checksum for data integrity
 pub fn calculate_checksum(data: &[u8]) -> u32 {
@@ -16,3 +16,9 @@ pub fn calculate_checksum(data: &[u8]) -> u32 {
 pub fn verify_checksum(data: &[u8], expected: u32) -> bool {
     calculate_checksum(data) == expected
 }
+
+/// Read a little-endian u32 at `pos`, or `None` if the data is too short
+pub fn read_u32_le(data: &[u8], pos: usize) -> Option<u32> {
+    let bytes = data.get(pos..pos.checked_add(4)?)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
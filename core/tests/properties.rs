@@ -0,0 +1,114 @@
+//! Property tests over account-like data: every algorithm round-trips through
+//! `Compressor`/`Decompressor`, metadata matches the payload, and auto mode
+//! never does worse than storing the data uncompressed.
+
+use owlsol_core::synthetic::{AccountKind, Generator};
+use owlsol_core::{CompressionAlgorithm, Compressor, Decompressor};
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+/// Long zero runs broken up by short non-zero fields, like padded accounts
+fn zero_runs() -> impl Strategy<Value = Vec<u8>> {
+    vec(
+        prop_oneof![
+            (1usize..512).prop_map(|n| vec![0u8; n]),
+            vec(any::<u8>(), 1..64),
+        ],
+        1..16,
+    )
+    .prop_map(|parts| parts.concat())
+}
+
+/// A handful of pubkeys repeated between u64 amounts, like token accounts
+fn repeated_pubkeys() -> impl Strategy<Value = Vec<u8>> {
+    vec(any::<[u8; 32]>(), 1..4).prop_flat_map(|keys| {
+        vec((0..keys.len(), any::<u64>()), 1..32).prop_map(move |entries| {
+            entries
+                .into_iter()
+                .flat_map(|(key, amount)| {
+                    keys[key]
+                        .iter()
+                        .copied()
+                        .chain(amount.to_le_bytes())
+                        .collect::<Vec<_>>()
+                })
+                .collect()
+        })
+    })
+}
+
+/// Printable text, like JSON metadata and URIs
+fn ascii() -> impl Strategy<Value = Vec<u8>> {
+    "[ -~]{1,1024}".prop_map(String::into_bytes)
+}
+
+fn random_bytes() -> impl Strategy<Value = Vec<u8>> {
+    vec(any::<u8>(), 1..2048)
+}
+
+/// Real account layouts from the synthetic generators
+fn synthetic() -> impl Strategy<Value = Vec<u8>> {
+    (
+        proptest::sample::select(AccountKind::ALL.to_vec()),
+        any::<u64>(),
+    )
+        .prop_map(|(kind, seed)| Generator::new(seed).account(kind))
+}
+
+fn account_like() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        zero_runs(),
+        repeated_pubkeys(),
+        ascii(),
+        random_bytes(),
+        synthetic()
+    ]
+}
+
+/// Every algorithm compiled into this build
+fn available_algorithms() -> Vec<CompressionAlgorithm> {
+    (0..=u8::MAX)
+        .map_while(CompressionAlgorithm::from_u8)
+        .filter(|algo| algo.is_available())
+        .collect()
+}
+
+proptest! {
+    #[test]
+    fn every_algorithm_roundtrips(data in account_like()) {
+        let compressor = Compressor::new();
+        let decompressor = Decompressor::new();
+
+        for algo in available_algorithms() {
+            let result = compressor.compress_with_algorithm(&data, Some(algo)).unwrap();
+
+            // Falls back to None when the algorithm doesn't help
+            prop_assert!(
+                result.metadata.algorithm == algo
+                    || result.metadata.algorithm == CompressionAlgorithm::None
+            );
+            prop_assert_eq!(result.metadata.original_size, data.len() as u64);
+            prop_assert_eq!(result.metadata.compressed_size, result.data.len() as u64);
+            // Hybrid payloads with off-chain segments have no estimate either
+            prop_assert!(result.decode_cu.is_none() || result.metadata.algorithm.is_onchain());
+
+            let decompressed = decompressor.decompress(&result.data, &result.metadata).unwrap();
+            prop_assert_eq!(&decompressed, &data, "{}", algo.as_str());
+        }
+    }
+
+    #[test]
+    fn auto_is_never_worse_than_none(data in account_like()) {
+        for compressor in [Compressor::new(), Compressor::onchain()] {
+            let result = compressor.compress(&data).unwrap();
+
+            prop_assert!(result.data.len() <= data.len());
+            prop_assert_eq!(result.metadata.compressed_size, result.data.len() as u64);
+
+            let decompressed = Decompressor::new()
+                .decompress(&result.data, &result.metadata)
+                .unwrap();
+            prop_assert_eq!(decompressed, data.clone());
+        }
+    }
+}
@@ -0,0 +1,25 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use owlsol_core::{CompressionAlgorithm, CompressionMetadata, DecompressOptions, Decompressor};
+
+/// Output cap, so the fuzzer explores decoders rather than large allocations
+const MAX_OUTPUT: usize = 1 << 20;
+
+fuzz_target!(|data: &[u8]| {
+    // [algorithm(1)][original_size(4)][payload]
+    let Some((header, payload)) = data.split_first_chunk::<5>() else {
+        return;
+    };
+    let Some(algorithm) = CompressionAlgorithm::from_u8(header[0]) else {
+        return;
+    };
+    let original_size = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as u64;
+    let metadata = CompressionMetadata::new(algorithm, original_size, payload.len() as u64);
+    let options = DecompressOptions::new().with_max_output_size(MAX_OUTPUT);
+
+    if let Ok(decoded) = Decompressor::new().decompress_with(payload, &metadata, &options) {
+        assert_eq!(decoded.len() as u64, original_size);
+        assert!(decoded.len() <= MAX_OUTPUT);
+    }
+});
@@ -0,0 +1,35 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use owlsol_core::algorithms::{huffman, HuffmanCodec};
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&len, payload)) = data.split_first() else {
+        return;
+    };
+    let original_len = len as usize * 4;
+
+    // [tree_size(4)][tree][encoded], as the Decompressor sees it
+    let mut out = vec![0u8; original_len];
+    let table_result = huffman::decompress_into(payload, &mut out);
+
+    // The tree-walking codec must accept exactly the same payloads
+    let Some(tree_size) = payload
+        .get(..4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize)
+    else {
+        return;
+    };
+    let Some(tree) = payload.get(4..4usize.saturating_add(tree_size)) else {
+        return;
+    };
+    let mut codec = HuffmanCodec::new();
+    if codec.deserialize_tree(tree).is_err() {
+        assert!(table_result.is_err());
+        return;
+    }
+    let decoded = codec.decode(&payload[4 + tree_size..], original_len);
+    if let (Ok(()), Ok(decoded)) = (table_result, decoded) {
+        assert_eq!(decoded, out);
+    }
+});
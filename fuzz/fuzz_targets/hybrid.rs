@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use owlsol_core::algorithms::hybrid;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(segments) = hybrid::Segments::parse(data) {
+        let total: usize = segments.map(|(_, payload)| payload.len()).sum();
+        assert!(total <= data.len());
+    }
+    let _ = hybrid::read_table(data);
+});
@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use owlsol_core::algorithms::rle;
+
+fuzz_target!(|data: &[u8]| {
+    let decoded = rle::decompress(data);
+
+    // The allocation-free decoder must agree with the Vec one
+    let mut out = vec![0u8; 4096];
+    match (&decoded, rle::decompress_into(data, &mut out)) {
+        (Ok(expected), Ok(written)) => assert_eq!(&out[..written], expected.as_slice()),
+        (Ok(expected), Err(_)) => assert!(expected.len() > out.len()),
+        (Err(_), result) => assert!(result.is_err()),
+    }
+});
@@ -0,0 +1,51 @@
+//! Sample program that decompresses instruction data straight into a
+//! writable account. Used by the integration tests natively and, built with
+//! `cargo build-sbf`, to calibrate the compute-unit model in
+//! `owlsol_core::cost`.
+
+use owlsol_core::CompressionAlgorithm;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+/// Instruction data: [algorithm(1)][checksum(4)][payload]
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let target = next_account_info(&mut accounts.iter())?;
+
+    let (&algorithm, rest) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let (checksum, payload) = rest
+        .split_first_chunk::<4>()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let algorithm =
+        CompressionAlgorithm::from_u8(algorithm).ok_or(ProgramError::InvalidInstructionData)?;
+
+    let mut data = target.try_borrow_mut_data()?;
+    owlsol_onchain::decompress_checked(
+        algorithm,
+        payload,
+        u32::from_le_bytes(*checksum),
+        &mut data,
+    )?;
+    Ok(())
+}
+
+/// Build the instruction data `process_instruction` expects
+pub fn instruction_data(algorithm: CompressionAlgorithm, checksum: u32, payload: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(5 + payload.len());
+    data.push(algorithm.to_u8());
+    data.extend_from_slice(&checksum.to_le_bytes());
+    data.extend_from_slice(payload);
+    data
+}
@@ -0,0 +1,230 @@
+//! Compares `owlsol_core::cost` estimates with the compute units the SBF build
+//! of the sample program actually consumes, and records the measurements in
+//! `core/tests/fixtures/decode_cu.csv`, which the core unit tests check the
+//! coefficients against. Native `processor!` programs are not metered, so this
+//! needs the compiled program:
+//!
+//! ```text
+//! cargo build-sbf --manifest-path onchain/sample/Cargo.toml
+//! BPF_OUT_DIR=target/deploy cargo test -p owlsol-sample-program \
+//!     --test calibration -- --ignored --nocapture
+//! ```
+
+use owlsol_core::cost::{self, CALIBRATION_TOLERANCE};
+use owlsol_core::{CompressionAlgorithm, Compressor};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use solana_program_test::{BanksClient, ProgramTest};
+use solana_sdk::{
+    account::Account, hash::Hash, signature::Keypair, signature::Signer, transaction::Transaction,
+};
+
+/// Measurements, relative to this crate
+const FIXTURE: &str = "../../core/tests/fixtures/decode_cu.csv";
+
+fn corpora() -> Vec<(&'static str, Vec<u8>)> {
+    let mut token = Vec::new();
+    token.extend_from_slice(&[7u8; 32]);
+    token.extend_from_slice(&[9u8; 32]);
+    token.extend_from_slice(&1_000_000u64.to_le_bytes());
+    token.extend(vec![0u8; 93]);
+
+    let text = b"name: owl #42, uri: https://example.com/owl/42.json; ".repeat(12);
+    let random = (0..600u32)
+        .map(|i| (i.wrapping_mul(7919) >> 3) as u8)
+        .collect();
+
+    vec![
+        ("token", token),
+        ("zeros", vec![0u8; 900]),
+        ("text", text),
+        ("random", random),
+    ]
+}
+
+struct Harness {
+    banks_client: BanksClient,
+    payer: Keypair,
+    recent_blockhash: Hash,
+    program_id: Pubkey,
+    targets: Vec<(usize, Pubkey)>,
+}
+
+impl Harness {
+    async fn start(sizes: &[usize]) -> Self {
+        let program_id = Pubkey::new_unique();
+        let mut program_test = ProgramTest::new("owlsol_sample_program", program_id, None);
+        program_test.prefer_bpf(true);
+
+        let targets: Vec<_> = sizes.iter().map(|&n| (n, Pubkey::new_unique())).collect();
+        for &(size, target) in &targets {
+            program_test.add_account(
+                target,
+                Account {
+                    lamports: 1_000_000_000,
+                    data: vec![0; size],
+                    owner: program_id,
+                    ..Account::default()
+                },
+            );
+        }
+
+        let (banks_client, payer, recent_blockhash) = program_test.start().await;
+        Self {
+            banks_client,
+            payer,
+            recent_blockhash,
+            program_id,
+            targets,
+        }
+    }
+
+    /// Compute units consumed decoding `payload` into an account of `size` bytes
+    async fn measure(
+        &mut self,
+        algorithm: CompressionAlgorithm,
+        checksum: u32,
+        payload: &[u8],
+        size: usize,
+    ) -> u64 {
+        let target = self
+            .targets
+            .iter()
+            .find(|(n, _)| *n == size)
+            .map(|(_, key)| *key)
+            .unwrap();
+        let instruction = Instruction::new_with_bytes(
+            self.program_id,
+            &owlsol_sample_program::instruction_data(algorithm, checksum, payload),
+            vec![AccountMeta::new(target, false)],
+        );
+        let mut transaction =
+            Transaction::new_with_payer(&[instruction], Some(&self.payer.pubkey()));
+        transaction.sign(&[&self.payer], self.recent_blockhash);
+
+        let simulation = self
+            .banks_client
+            .simulate_transaction(transaction)
+            .await
+            .unwrap();
+        assert!(
+            matches!(simulation.result, Some(Ok(()))),
+            "{:?}",
+            simulation.result
+        );
+        simulation.simulation_details.unwrap().units_consumed
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs the SBF build of the sample program in BPF_OUT_DIR"]
+async fn calibrate_cost_model() {
+    let corpora = corpora();
+    let mut sizes: Vec<usize> = corpora.iter().map(|(_, data)| data.len()).collect();
+    sizes.push(1);
+    let mut harness = Harness::start(&sizes).await;
+
+    // Entrypoint, account deserialization and instruction parsing are paid by
+    // every call; measure them with the cheapest possible decode
+    let overhead = harness
+        .measure(CompressionAlgorithm::None, 0, &[0], 1)
+        .await
+        .saturating_sub(cost::estimate_decode_cu(CompressionAlgorithm::None, &[0], 1).unwrap());
+
+    println!("program overhead: {} CU", overhead);
+    println!(
+        "{:<8} {:<11} {:>8} {:>10} {:>10} {:>8}",
+        "corpus", "algorithm", "payload", "measured", "estimate", "error"
+    );
+
+    let compressor = Compressor::onchain();
+    let mut failures = Vec::new();
+    let mut rows = Vec::new();
+    for (name, data) in &corpora {
+        for algorithm in [
+            CompressionAlgorithm::None,
+            CompressionAlgorithm::RunLength,
+            CompressionAlgorithm::Dictionary,
+            CompressionAlgorithm::Huffman,
+            CompressionAlgorithm::Hybrid,
+        ] {
+            let result = compressor
+                .compress_with_algorithm(data, Some(algorithm))
+                .unwrap();
+            if result.metadata.algorithm != algorithm {
+                continue; // Fell back to None, already measured
+            }
+
+            // Checksum 0 skips verification so only the decode is measured
+            let measured = harness
+                .measure(algorithm, 0, &result.data, data.len())
+                .await
+                .saturating_sub(overhead);
+            let estimate = result.decode_cu.unwrap();
+            let error = (estimate as f64 - measured as f64) / measured.max(1) as f64;
+
+            println!(
+                "{:<8} {:<11} {:>8} {:>10} {:>10} {:>7.1}%",
+                name,
+                algorithm.as_str(),
+                result.data.len(),
+                measured,
+                estimate,
+                error * 100.0
+            );
+            if error.abs() > CALIBRATION_TOLERANCE {
+                failures.push(format!("{}/{}", name, algorithm.as_str()));
+            }
+
+            // Hybrid is the sum of its segments, which the other rows cover
+            if algorithm != CompressionAlgorithm::Hybrid {
+                let (input_bytes, code_bits) = model_inputs(algorithm, &result.data);
+                rows.push(format!(
+                    "{},{},{},{},{},{}",
+                    name,
+                    algorithm.as_str(),
+                    input_bytes,
+                    data.len(),
+                    code_bits,
+                    measured
+                ));
+            }
+        }
+    }
+
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURE);
+    let header: String = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .take_while(|line| line.starts_with('#'))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    std::fs::write(
+        &path,
+        format!(
+            "{}corpus,algorithm,input_bytes,output_bytes,code_bits,measured_cu\n{}\n",
+            header,
+            rows.join("\n")
+        ),
+    )
+    .unwrap();
+    println!("recorded {} measurements in {}", rows.len(), path.display());
+
+    assert!(
+        failures.is_empty(),
+        "estimates off by more than {:.0}%: {:?}",
+        CALIBRATION_TOLERANCE * 100.0,
+        failures
+    );
+}
+
+/// Bytes read and Huffman code bits walked, as `cost::DecodeCost` counts them
+fn model_inputs(algorithm: CompressionAlgorithm, payload: &[u8]) -> (usize, usize) {
+    if algorithm != CompressionAlgorithm::Huffman {
+        return (payload.len(), 0);
+    }
+    let tree_size = u32::from_le_bytes(payload[..4].try_into().unwrap()) as usize;
+    (tree_size, (payload.len() - 4 - tree_size) * 8)
+}
@@ -0,0 +1,87 @@
+//! Runs the sample program natively under `solana-program-test`.
+
+use owlsol_core::{CompressionAlgorithm, CompressionResult, Compressor};
+use owlsol_sample_program::process_instruction;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use solana_program_test::{processor, ProgramTest};
+use solana_sdk::{account::Account, signature::Signer, transaction::Transaction};
+
+fn instruction_data(result: &CompressionResult) -> Vec<u8> {
+    owlsol_sample_program::instruction_data(
+        result.metadata.algorithm,
+        result.metadata.checksum,
+        &result.data,
+    )
+}
+
+fn account_like_data() -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&[7u8; 32]); // mint
+    data.extend_from_slice(&[9u8; 32]); // owner
+    data.extend_from_slice(&1_000_000u64.to_le_bytes());
+    data.extend_from_slice(b"metadata: owl #42, https://example.com/owl/42.json");
+    data.extend(vec![0u8; 600]);
+    data
+}
+
+async fn run(algorithm: CompressionAlgorithm) -> std::result::Result<Vec<u8>, String> {
+    let program_id = Pubkey::new_unique();
+    let target = Pubkey::new_unique();
+    let original = account_like_data();
+    let result = Compressor::onchain()
+        .compress_with_algorithm(&original, Some(algorithm))
+        .unwrap();
+
+    let mut program_test = ProgramTest::new(
+        "owlsol_sample_program",
+        program_id,
+        processor!(process_instruction),
+    );
+    program_test.add_account(
+        target,
+        Account {
+            lamports: 1_000_000_000,
+            data: vec![0; original.len()],
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let instruction = Instruction::new_with_bytes(
+        program_id,
+        &instruction_data(&result),
+        vec![AccountMeta::new(target, false)],
+    );
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let account = banks_client.get_account(target).await.unwrap().unwrap();
+    Ok(account.data)
+}
+
+#[tokio::test]
+async fn test_decompress_into_account() {
+    for algorithm in [
+        CompressionAlgorithm::RunLength,
+        CompressionAlgorithm::Dictionary,
+        CompressionAlgorithm::Huffman,
+        CompressionAlgorithm::Hybrid,
+    ] {
+        let data = run(algorithm).await.unwrap();
+        assert_eq!(data, account_like_data(), "{}", algorithm.as_str());
+    }
+}
+
+#[tokio::test]
+async fn test_unsupported_algorithm_fails_instruction() {
+    let err = run(CompressionAlgorithm::Zstd).await.unwrap_err();
+    assert!(err.contains("custom program error: 0x1"), "{}", err);
+}
@@ -0,0 +1,39 @@
+use owlsol_core::DecodeError;
+use solana_program::program_error::ProgramError;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, OnchainError>;
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnchainError {
+    #[error("Algorithm {0} cannot be decoded on-chain")]
+    UnsupportedAlgorithm(u8),
+
+    #[error("Decoded size does not match the output buffer")]
+    SizeMismatch,
+
+    #[error("Checksum mismatch")]
+    ChecksumMismatch,
+
+    #[error("Decode error: {0}")]
+    Decode(#[from] DecodeError),
+}
+
+impl OnchainError {
+    /// Code reported through `ProgramError::Custom`
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::UnsupportedAlgorithm(_) => 1,
+            Self::SizeMismatch => 2,
+            Self::ChecksumMismatch => 3,
+            // 10 and up mirror `DecodeError::code`
+            Self::Decode(err) => 9 + err.code(),
+        }
+    }
+}
+
+impl From<OnchainError> for ProgramError {
+    fn from(err: OnchainError) -> Self {
+        ProgramError::Custom(err.code())
+    }
+}
@@ -0,0 +1,155 @@
+//! OWLSOL On-chain - Decompression for Solana programs
+//!
+//! Decodes OWLSOL payloads inside an instruction using the pure-Rust core
+//! decoders (RLE, Dictionary, Huffman and Hybrid tables built from them).
+//! Nothing is allocated: output goes straight into a caller-supplied buffer,
+//! typically the data of a writable account.
+//!
+//! Payloads must use the current format version: version 1 RLE runs have
+//! their count and byte the other way round, so recompress those first.
+
+#![cfg_attr(
+    not(test),
+    deny(
+        clippy::indexing_slicing,
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic
+    )
+)]
+
+pub mod error;
+
+pub use error::{OnchainError, Result};
+pub use owlsol_core::CompressionAlgorithm;
+
+use owlsol_core::algorithms::{dictionary, huffman, hybrid, rle};
+use owlsol_core::utils::verify_checksum;
+
+/// Decompress `payload` into `out`, which must be exactly the original size
+pub fn decompress(algorithm: CompressionAlgorithm, payload: &[u8], out: &mut [u8]) -> Result<()> {
+    let written = match algorithm {
+        CompressionAlgorithm::None => {
+            if payload.len() != out.len() {
+                return Err(OnchainError::SizeMismatch);
+            }
+            out.copy_from_slice(payload);
+            payload.len()
+        }
+        CompressionAlgorithm::RunLength => rle::decompress_into(payload, out)?,
+        CompressionAlgorithm::Dictionary => dictionary::decompress_into(payload, out)?,
+        CompressionAlgorithm::Huffman => {
+            huffman::decompress_into(payload, out)?;
+            out.len()
+        }
+        CompressionAlgorithm::Hybrid => decompress_hybrid(payload, out)?,
+        other => return Err(OnchainError::UnsupportedAlgorithm(other.to_u8())),
+    };
+
+    if written != out.len() {
+        return Err(OnchainError::SizeMismatch);
+    }
+
+    Ok(())
+}
+
+/// Verify the checksum recorded in `CompressionMetadata`, then decompress
+pub fn decompress_checked(
+    algorithm: CompressionAlgorithm,
+    payload: &[u8],
+    checksum: u32,
+    out: &mut [u8],
+) -> Result<()> {
+    if checksum != 0 && !verify_checksum(payload, checksum) {
+        return Err(OnchainError::ChecksumMismatch);
+    }
+
+    decompress(algorithm, payload, out)
+}
+
+fn decompress_hybrid(payload: &[u8], out: &mut [u8]) -> Result<usize> {
+    let mut written = 0usize;
+
+    for (segment, data) in hybrid::Segments::parse(payload)? {
+        let end = written
+            .checked_add(segment.original_len as usize)
+            .ok_or(OnchainError::SizeMismatch)?;
+        let block = out
+            .get_mut(written..end)
+            .ok_or(OnchainError::SizeMismatch)?;
+
+        // Segments are never Hybrid themselves, so this recurses at most once
+        decompress(segment.algorithm, data, block)?;
+        written = end;
+    }
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use owlsol_core::Compressor;
+
+    #[test]
+    fn test_roundtrip_onchain_algorithms() {
+        let compressor = Compressor::onchain();
+        let mut data = b"mint owner amount delegate state".repeat(6);
+        data.extend(vec![0u8; 300]);
+
+        for algo in [
+            CompressionAlgorithm::None,
+            CompressionAlgorithm::RunLength,
+            CompressionAlgorithm::Dictionary,
+            CompressionAlgorithm::Huffman,
+            CompressionAlgorithm::Hybrid,
+        ] {
+            let result = compressor
+                .compress_with_algorithm(&data, Some(algo))
+                .unwrap();
+            let mut out = vec![0u8; data.len()];
+            decompress_checked(
+                result.metadata.algorithm,
+                &result.data,
+                result.metadata.checksum,
+                &mut out,
+            )
+            .unwrap();
+            assert_eq!(out, data);
+        }
+    }
+
+    #[test]
+    fn test_output_size_must_match() {
+        let result = Compressor::onchain()
+            .compress_with_algorithm(&[7u8; 100], Some(CompressionAlgorithm::RunLength))
+            .unwrap();
+
+        let mut short = [0u8; 50];
+        assert_eq!(
+            decompress(CompressionAlgorithm::RunLength, &result.data, &mut short),
+            Err(OnchainError::Decode(
+                owlsol_core::DecodeError::OutputOverflow
+            ))
+        );
+        let mut long = [0u8; 150];
+        assert_eq!(
+            decompress(CompressionAlgorithm::RunLength, &result.data, &mut long),
+            Err(OnchainError::SizeMismatch)
+        );
+    }
+
+    #[test]
+    fn test_rejects_unsupported_and_corrupt() {
+        let mut out = [0u8; 8];
+        assert_eq!(
+            decompress(CompressionAlgorithm::Zstd, &[0; 8], &mut out),
+            Err(OnchainError::UnsupportedAlgorithm(6))
+        );
+        assert_eq!(
+            decompress_checked(CompressionAlgorithm::None, &[0; 8], 1, &mut out),
+            Err(OnchainError::ChecksumMismatch)
+        );
+        assert!(decompress(CompressionAlgorithm::Hybrid, &[1, 0, 0, 0, 9], &mut out).is_err());
+    }
+}
@@ -35,13 +35,13 @@ rm -f "$out" "$meta"
 # Measure compression time
 if date +%s%3N >/dev/null 2>&1; then
   start=$(date +%s%3N)
-  owlsol compress "$in" -o "$out" 2>&1 | grep -v "^$" || true
+  owlsol compress -i "$in" -o "$out" -k 2>&1 | grep -v "^$" || true
   end=$(date +%s%3N)
   duration_ms=$((end-start))
 else
   # Fallback for systems without millisecond precision
   start=$(date +%s)
-  owlsol compress "$in" -o "$out" 2>&1 | grep -v "^$" || true
+  owlsol compress -i "$in" -o "$out" -k 2>&1 | grep -v "^$" || true
   end=$(date +%s)
   duration_ms=$(((end-start)*1000))
 fi
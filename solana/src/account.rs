@@ -1,3 +1,4 @@
+use owlsol_core::rent::{Rent, LAMPORTS_PER_SOL};
 use owlsol_core::{CompressionMetadata, CompressionResult};
 use solana_sdk::pubkey::Pubkey;
 
@@ -6,39 +7,43 @@ pub struct CompressedAccount {
     pub address: Pubkey,
     pub data: Vec<u8>,
     pub metadata: CompressionMetadata,
+    /// Rent-exempt balance of the compressed account
     pub rent: u64,
+    /// Rent-exempt balance the data would need uncompressed
+    pub original_rent: u64,
 }
 
 impl CompressedAccount {
-    pub fn new(address: Pubkey, result: CompressionResult, rent: u64) -> Self {
+    pub fn new(address: Pubkey, result: CompressionResult, rent: &Rent) -> Self {
         Self {
             address,
+            rent: rent.minimum_balance(result.data.len()),
+            original_rent: rent.minimum_balance(result.metadata.original_size as usize),
             data: result.data,
             metadata: result.metadata,
-            rent,
         }
     }
 
+    /// Lamports freed by storing the data compressed; both balances include
+    /// the per-account overhead, so this is less than the proportional figure
     pub fn savings(&self) -> u64 {
-        let original_rent =
-            self.rent * self.metadata.original_size / self.metadata.compressed_size.max(1);
-        original_rent.saturating_sub(self.rent)
+        self.original_rent.saturating_sub(self.rent)
     }
 
     pub fn savings_sol(&self) -> f64 {
-        self.savings() as f64 / 1_000_000_000.0
+        self.savings() as f64 / LAMPORTS_PER_SOL as f64
     }
 
     pub fn rent_sol(&self) -> f64 {
-        self.rent as f64 / 1_000_000_000.0
+        self.rent as f64 / LAMPORTS_PER_SOL as f64
     }
 
     pub fn original_rent(&self) -> u64 {
-        self.rent * self.metadata.original_size / self.metadata.compressed_size.max(1)
+        self.original_rent
     }
 
     pub fn original_rent_sol(&self) -> f64 {
-        self.original_rent() as f64 / 1_000_000_000.0
+        self.original_rent as f64 / LAMPORTS_PER_SOL as f64
     }
 
     pub fn compression_details(&self) -> String {
@@ -53,3 +58,22 @@ impl CompressedAccount {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use owlsol_core::{CompressionAlgorithm, Compressor};
+
+    #[test]
+    fn test_savings_include_overhead() {
+        let result = Compressor::new()
+            .compress_with_algorithm(&[0; 1000], Some(CompressionAlgorithm::RunLength))
+            .unwrap();
+        let compressed = result.data.len() as u64;
+        let account = CompressedAccount::new(Pubkey::new_unique(), result, &Rent::default());
+
+        assert_eq!(account.original_rent(), (128 + 1000) * 6_960);
+        assert_eq!(account.savings(), (1000 - compressed) * 6_960);
+        assert!(account.savings() < account.rent * 1000 / compressed - account.rent);
+    }
+}
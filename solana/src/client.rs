@@ -6,7 +6,27 @@ use solana_sdk::{
     signature::{Keypair, Signer},
     transaction::Transaction,
 };
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// RPC endpoint used when none is configured
+pub const DEVNET_RPC_URL: &str = "https://api.devnet.solana.com";
+
+/// Keypair used by the Solana CLI when none is configured
+pub fn default_keypair_path() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+    Path::new(&home).join(".config/solana/id.json")
+}
+
+/// Load a keypair from either the Solana CLI's JSON format (an array of 64
+/// byte values) or the raw 64 bytes
+pub fn read_keypair<P: AsRef<Path>>(path: P) -> Result<Keypair> {
+    let contents = std::fs::read(path)?;
+    let bytes = match serde_json::from_slice::<Vec<u8>>(&contents) {
+        Ok(bytes) => bytes,
+        Err(_) => contents,
+    };
+    Keypair::from_bytes(&bytes).map_err(|_| SolanaError::InvalidKeypair)
+}
 
 pub struct SolanaClient {
     rpc: RpcClient,
@@ -15,25 +35,29 @@ pub struct SolanaClient {
 
 impl SolanaClient {
     pub fn new<P: AsRef<Path>>(rpc_url: &str, keypair_path: P) -> Result<Self> {
-        let rpc =
-            RpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::confirmed());
+        Self::with_commitment(rpc_url, keypair_path, CommitmentConfig::confirmed())
+    }
+
+    #[tracing::instrument(skip(keypair_path))]
+    pub fn with_commitment<P: AsRef<Path>>(
+        rpc_url: &str,
+        keypair_path: P,
+        commitment: CommitmentConfig,
+    ) -> Result<Self> {
+        let rpc = RpcClient::new_with_commitment(rpc_url.to_string(), commitment);
 
-        let keypair_bytes = std::fs::read(keypair_path)?;
-        let payer = Keypair::from_bytes(&keypair_bytes).map_err(|_| SolanaError::InvalidKeypair)?;
+        let payer = read_keypair(keypair_path)?;
+        tracing::debug!(payer = %payer.pubkey(), "loaded keypair");
 
         Ok(Self { rpc, payer })
     }
 
     pub fn devnet() -> Result<Self> {
-        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-        let keypair_path = format!("{}/.config/solana/id.json", home);
-        Self::new("https://api.devnet.solana.com", keypair_path)
+        Self::new(DEVNET_RPC_URL, default_keypair_path())
     }
 
     pub fn testnet() -> Result<Self> {
-        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-        let keypair_path = format!("{}/.config/solana/id.json", home);
-        Self::new("https://api.testnet.solana.com", keypair_path)
+        Self::new("https://api.testnet.solana.com", default_keypair_path())
     }
 
     pub fn get_balance(&self) -> Result<u64> {
@@ -46,11 +70,13 @@ impl SolanaClient {
         Ok(self.get_balance()? as f64 / 1_000_000_000.0)
     }
 
+    #[tracing::instrument(skip(self))]
     pub fn airdrop(&self, lamports: u64) -> Result<()> {
         let signature = self
             .rpc
             .request_airdrop(&self.payer.pubkey(), lamports)
             .map_err(|e| SolanaError::RpcError(e.to_string()))?;
+        tracing::debug!(%signature, "airdrop requested");
 
         // Wait for confirmation
         loop {
@@ -69,6 +95,7 @@ impl SolanaClient {
         let balance = self.get_balance()?;
         if balance < min_balance {
             let amount = min_balance - balance + 1_000_000_000; // Add 1 SOL buffer
+            tracing::info!(balance, min_balance, "balance low, requesting airdrop");
             self.airdrop(amount)?;
         }
         Ok(())
@@ -78,6 +105,7 @@ impl SolanaClient {
         self.payer.pubkey()
     }
 
+    #[tracing::instrument(level = "debug", skip(self))]
     pub fn calculate_rent(&self, data_len: usize) -> Result<u64> {
         self.rpc
             .get_minimum_balance_for_rent_exemption(data_len)
@@ -94,11 +122,14 @@ impl SolanaClient {
             .map_err(|e| SolanaError::RpcError(e.to_string()))
     }
 
+    #[tracing::instrument(skip_all, fields(signatures = transaction.signatures.len()))]
     pub fn send_transaction(&self, transaction: &Transaction) -> Result<String> {
-        self.rpc
+        let signature = self
+            .rpc
             .send_and_confirm_transaction(transaction)
-            .map(|sig| sig.to_string())
-            .map_err(|e| SolanaError::TransactionFailed(e.to_string()))
+            .map_err(|e| SolanaError::TransactionFailed(e.to_string()))?;
+        tracing::debug!(%signature, "transaction confirmed");
+        Ok(signature.to_string())
     }
 }
 
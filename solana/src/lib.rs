@@ -1,7 +1,13 @@
 pub mod account;
 pub mod client;
 pub mod error;
+pub mod seal;
 
 pub use account::CompressedAccount;
-pub use client::SolanaClient;
+pub use client::{default_keypair_path, read_keypair, SolanaClient, DEVNET_RPC_URL};
 pub use error::{Result, SolanaError};
+pub use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
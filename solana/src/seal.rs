@@ -0,0 +1,172 @@
+//! Seal compressed payloads to a Solana wallet
+//!
+//! The recipient's ed25519 public key is converted to its X25519 (Montgomery)
+//! form and combined with a fresh ephemeral key; the shared secret keys
+//! ChaCha20-Poly1305 through the same container encryption used for
+//! passphrases. Only the holder of the recipient keypair can open the result.
+
+use curve25519_dalek::constants::X25519_BASEPOINT;
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use curve25519_dalek::montgomery::MontgomeryPoint;
+use curve25519_dalek::scalar::Scalar;
+use owlsol_core::container::{Container, Kdf};
+use owlsol_core::encryption::{self, KEY_LEN};
+use owlsol_core::{CompressionError, CompressionResult};
+use sha2::{Digest, Sha256, Sha512};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const KEY_CONTEXT: &[u8] = b"owlsol seal v1";
+
+/// Encrypt a compression result so only `recipient` can decrypt it
+pub fn seal(result: &CompressionResult, recipient: &Pubkey) -> owlsol_core::Result<Container> {
+    let recipient_point = montgomery_public(recipient)?;
+    let ephemeral_secret = clamp(encryption::random_bytes()?);
+    let ephemeral = (X25519_BASEPOINT * ephemeral_secret).to_bytes();
+    let shared = recipient_point * ephemeral_secret;
+
+    let recipient = recipient.to_bytes();
+    let key = derive_key(&shared, &ephemeral, &recipient)?;
+    let kdf = Kdf::X25519 {
+        ephemeral,
+        recipient,
+    };
+    // The salt is unused by this KDF but stays random like every other header
+    encryption::encrypt_with_key(result, &key, kdf, encryption::random_bytes()?)
+}
+
+/// Decrypt a sealed container with the recipient's keypair
+///
+/// Fails with [`CompressionError::WrongKey`] if the container was sealed to a
+/// different key or has been modified.
+pub fn open(container: &Container, keypair: &Keypair) -> owlsol_core::Result<Vec<u8>> {
+    let Some(Kdf::X25519 {
+        ephemeral,
+        recipient,
+    }) = container.encryption.map(|encryption| encryption.kdf)
+    else {
+        return Err(CompressionError::invalid_input(
+            "Data is not sealed to a Solana public key",
+        ));
+    };
+    if recipient != keypair.pubkey().to_bytes() {
+        tracing::debug!(
+            recipient = %Pubkey::new_from_array(recipient),
+            keypair = %keypair.pubkey(),
+            "container sealed to another key"
+        );
+        return Err(CompressionError::WrongKey);
+    }
+
+    let shared = MontgomeryPoint(ephemeral) * montgomery_secret(keypair);
+    let key = derive_key(&shared, &ephemeral, &recipient)?;
+    encryption::decrypt_with_key(container, &key)
+}
+
+/// Recipient the container was sealed to, if it is sealed
+pub fn recipient(container: &Container) -> Option<Pubkey> {
+    match container.encryption?.kdf {
+        Kdf::X25519 { recipient, .. } => Some(Pubkey::new_from_array(recipient)),
+        _ => None,
+    }
+}
+
+fn montgomery_public(pubkey: &Pubkey) -> owlsol_core::Result<MontgomeryPoint> {
+    let point = CompressedEdwardsY(pubkey.to_bytes())
+        .decompress()
+        .filter(|point| !point.is_small_order())
+        .ok_or_else(|| {
+            CompressionError::invalid_input(format!(
+                "{} is not a wallet public key (off-curve or weak)",
+                pubkey
+            ))
+        })?;
+    Ok(point.to_montgomery())
+}
+
+/// The X25519 scalar matching an ed25519 keypair: the clamped lower half of
+/// SHA-512 over the secret seed, exactly as ed25519 signing derives it
+fn montgomery_secret(keypair: &Keypair) -> Scalar {
+    let hash = Sha512::digest(&keypair.to_bytes()[..32]);
+    clamp(hash[..32].try_into().unwrap())
+}
+
+fn clamp(mut bytes: [u8; 32]) -> Scalar {
+    bytes[0] &= 248;
+    bytes[31] &= 127;
+    bytes[31] |= 64;
+    Scalar::from_bits(bytes)
+}
+
+fn derive_key(
+    shared: &MontgomeryPoint,
+    ephemeral: &[u8; 32],
+    recipient: &[u8; 32],
+) -> owlsol_core::Result<[u8; KEY_LEN]> {
+    // An all-zero secret means a low-order ephemeral point was planted
+    if shared.to_bytes() == [0u8; 32] {
+        return Err(CompressionError::WrongKey);
+    }
+    let mut hasher = Sha256::new();
+    hasher.update(KEY_CONTEXT);
+    hasher.update(shared.as_bytes());
+    hasher.update(ephemeral);
+    hasher.update(recipient);
+    Ok(hasher.finalize().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use owlsol_core::{Compressor, Decompressor};
+
+    fn sample() -> (Vec<u8>, CompressionResult) {
+        let data = b"token account for the recipient only".repeat(12);
+        let result = Compressor::new().compress(&data).unwrap();
+        (data, result)
+    }
+
+    #[test]
+    fn test_seal_roundtrip() {
+        let (data, result) = sample();
+        let wallet = Keypair::new();
+        let bytes = seal(&result, &wallet.pubkey()).unwrap().to_bytes();
+
+        let container = Container::from_bytes(&bytes).unwrap();
+        assert_eq!(recipient(&container), Some(wallet.pubkey()));
+        let compressed = open(&container, &wallet).unwrap();
+        let decompressed = Decompressor::new()
+            .decompress(&compressed, &container.metadata)
+            .unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn test_wrong_keypair_and_tampering() {
+        let (_, result) = sample();
+        let wallet = Keypair::new();
+        let container = seal(&result, &wallet.pubkey()).unwrap();
+
+        assert!(matches!(
+            open(&container, &Keypair::new()),
+            Err(CompressionError::WrongKey)
+        ));
+        // Swapping the recipient field does not help another keypair
+        let other = Keypair::new();
+        let mut redirected = container.clone();
+        if let Some(encryption) = redirected.encryption.as_mut() {
+            if let Kdf::X25519 { recipient, .. } = &mut encryption.kdf {
+                *recipient = other.pubkey().to_bytes();
+            }
+        }
+        assert!(matches!(
+            open(&redirected, &other),
+            Err(CompressionError::WrongKey)
+        ));
+        // An off-curve recipient is rejected up front
+        let mut bad = [0u8; 32];
+        bad[0] = 2;
+        let err = seal(&result, &Pubkey::new_from_array(bad)).unwrap_err();
+        assert_eq!(err.code(), 100);
+    }
+}